@@ -0,0 +1,31 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rusty_evm::{u256, Account, Address, Block, Evm, Transaction};
+
+// Counts down from the 2 bytes of calldata: PUSH0 CALLDATALOAD PUSH1 240 SHR, then JUMPDEST PUSH1 1 SWAP1 SUB DUP1
+// PUSH1 5 JUMPI STOP, which is 26 gas per iteration
+const COUNTDOWN: &str = "5F3560F01C5B600190038060055700";
+
+// Compared against another revision with criterion baselines, by running
+// `cargo bench --bench interpreter -- --save-baseline before` on that revision, then
+// `cargo bench --bench interpreter -- --baseline before` on this one. Likewise, the JIT is measured against the
+// interpreter by saving a baseline without the `jit` feature, then comparing with `--features jit`. The contract gets
+// compiled after its first runs, during the warm up
+fn countdown(c: &mut Criterion) {
+    let (caller, contract) = (Address(u256::new(0xA0)), Address(u256::new(0xC0)));
+    let evm = Evm::builder()
+        .account(caller, Account { balance: u256::MAX, ..Default::default() })
+        .account(contract, Account { balance: u256::ZERO, code: hex::decode(COUNTDOWN).unwrap().into(), nonce: 1 })
+        .build();
+
+    let mut group = c.benchmark_group("interpreter");
+    for iterations in [1000u16, 10000] {
+        let tx = Transaction { data: iterations.to_be_bytes().to_vec(), from: caller, gas: 1_000_000, gas_price: 1, to: contract, ..Default::default() };
+        group.bench_with_input(BenchmarkId::new("countdown", iterations), &tx, |b, tx| {
+            b.iter(|| evm.call(Block::default(), black_box(tx.clone())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, countdown);
+criterion_main!(benches);
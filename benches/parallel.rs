@@ -0,0 +1,37 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rusty_evm::{u256, Account, Address, Block, Evm, Transaction};
+
+// Counts down from 1000 (PUSH2 1000, then JUMPDEST PUSH1 1 SWAP1 SUB DUP1 PUSH1 3 JUMPI STOP), reading nothing
+const COUNTDOWN: &str = "6103E85B600190038060035700";
+
+// Accounts that no transaction touches, which a copy of the whole state would have to clone
+const IDLE_ACCOUNTS: u64 = 10000;
+
+const TRANSACTIONS: u64 = 64;
+
+fn evm() -> Evm {
+    let contract = Address(u256::new(0xC0));
+    (1..=IDLE_ACCOUNTS + TRANSACTIONS)
+        .fold(Evm::builder(), |builder, i| builder.account(Address(u256::from(0x10000 + i)), Account { balance: u256::from(u64::MAX), ..Default::default() }))
+        .account(contract, Account { balance: u256::ZERO, code: hex::decode(COUNTDOWN).unwrap().into(), nonce: 1 })
+        .build()
+}
+
+// Independent transactions from distinct senders, which each run the contract once. The speedup of parallel
+// execution depends on the cores of the machine, and a single core only shows its overhead
+fn independent_transactions(c: &mut Criterion) {
+    let txs: Vec<_> = (1..=TRANSACTIONS)
+        .map(|i| Transaction { from: Address(u256::from(0x10000 + IDLE_ACCOUNTS + i)), gas: 100000, gas_price: 1, to: Address(u256::new(0xC0)), ..Default::default() })
+        .collect();
+
+    let mut group = c.benchmark_group("block");
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(BenchmarkId::new("threads", threads), &threads, |b, threads| {
+            b.iter_batched(evm, |mut evm| evm.transact_block(&Block::default(), &txs, *threads), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, independent_transactions);
+criterion_main!(benches);
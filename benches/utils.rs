@@ -0,0 +1,84 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ethnum::{u256, uint, U256};
+use sha3::{Digest, Keccak256};
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/utils.rs"]
+mod utils;
+
+use utils::{Hash, IsNeg, NeededSizeInBytes, WrappingBigPow};
+
+// The previous implementations, kept to measure the speedup against
+mod baseline {
+    use super::*;
+
+    pub fn keccak256(data: &[u8]) -> u256 {
+        let mut result = U256::ZERO;
+        let arr = Keccak256::digest(data);
+        for byte in arr {
+            result <<= 8;
+            result |= u256::from(byte);
+        }
+        result
+    }
+
+    pub fn needed_size_in_bytes(mut value: u256) -> usize {
+        let mut n = 0;
+        while value != 0 {
+            value >>= 8;
+            n += 1;
+        }
+        n
+    }
+
+    pub fn is_neg(value: u256) -> bool {
+        (value & u256::from_str_hex("0x8000000000000000000000000000000000000000000000000000000000000000").unwrap()) != 0
+    }
+
+    pub fn wrapping_big_pow(base: u256, e: u256) -> u256 {
+        match TryInto::<u32>::try_into(e) {
+            Ok(e) => base.wrapping_pow(e),
+            _ => {
+                let ep = e.div_euclid(u32::MAX.into());
+                let r: u32 = e.rem_euclid(u32::MAX.into()).try_into().unwrap();
+                wrapping_big_pow(base.wrapping_pow(u32::MAX), ep).wrapping_mul(base.wrapping_pow(r))
+            }
+        }
+    }
+}
+
+fn exp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("exp");
+    for (name, e) in [("small", uint!("18")), ("u32", uint!("4294967295")), ("u256", U256::MAX - 1)] {
+        group.bench_function(format!("baseline/{name}"), |b| b.iter(|| baseline::wrapping_big_pow(black_box(uint!("3")), black_box(e))));
+        group.bench_function(format!("fast/{name}"), |b| b.iter(|| black_box(uint!("3")).wrapping_big_pow(black_box(e))));
+    }
+    group.finish();
+}
+
+fn is_neg(c: &mut Criterion) {
+    let value = uint!("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFE");
+    let mut group = c.benchmark_group("is_neg");
+    group.bench_function("baseline", |b| b.iter(|| baseline::is_neg(black_box(value))));
+    group.bench_function("fast", |b| b.iter(|| black_box(value).is_neg()));
+    group.finish();
+}
+
+fn needed_size_in_bytes(c: &mut Criterion) {
+    let value = uint!("0x0100000000000000000000000000000000000000000000000000000000000000");
+    let mut group = c.benchmark_group("needed_size_in_bytes");
+    group.bench_function("baseline", |b| b.iter(|| baseline::needed_size_in_bytes(black_box(value))));
+    group.bench_function("fast", |b| b.iter(|| black_box(value).needed_size_in_bytes()));
+    group.finish();
+}
+
+fn keccak256(c: &mut Criterion) {
+    let data = [0xAB; 64];
+    let mut group = c.benchmark_group("keccak256");
+    group.bench_function("baseline", |b| b.iter(|| baseline::keccak256(black_box(&data))));
+    group.bench_function("fast", |b| b.iter(|| black_box(&data[..]).keccak256()));
+    group.finish();
+}
+
+criterion_group!(benches, exp, is_neg, needed_size_in_bytes, keccak256);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+use ethnum::u256;
+use crate::blockchain::StateKey;
+use crate::blockchain::bytecode::Bytecode;
+use crate::blockchain::primitives::{Account, Address};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct AccountInfo {
+    pub balance: u256,
+    pub code_hash: u256,
+    pub nonce: usize,
+}
+
+// Why a backend could not provide some state, such as an I/O error or a node that could not be reached
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BackendError(pub String);
+
+// Source of the state that a WorldState has not loaded yet, such as an on-disk database or a remote node. Execution
+// cannot go on without the state it asked for, so its errors abort the transaction
+pub trait StateBackend: Send + Sync {
+    fn basic(&self, address: Address) -> Result<Option<AccountInfo>, BackendError>;
+
+    fn code_by_hash(&self, hash: u256) -> Result<Bytecode, BackendError>;
+
+    fn storage(&self, address: Address, key: u256) -> Result<u256, BackendError>;
+
+    fn block_hash(&self, number: u256) -> u256;
+
+    fn account(&self, address: Address) -> Result<Account, BackendError> {
+        Ok(match self.basic(address)? {
+            Some(AccountInfo { balance, code_hash, nonce }) => Account {
+                balance,
+                code: if code_hash == Bytecode::default().hash() { Bytecode::default() } else { self.code_by_hash(code_hash)? },
+                nonce,
+            },
+            None => Account::default(),
+        })
+    }
+}
+
+// A backend that the changes made on top of it can be committed to
+pub trait StateBackendMut: StateBackend {
+    fn apply(&mut self, changes: ChangeSet) -> Result<(), BackendError>;
+}
+
+// New values of some accounts and slots
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ChangeSet {
+    pub accounts: HashMap<Address, Account>,
+    pub storage: HashMap<Address, HashMap<u256, u256>>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storage.values().all(HashMap::is_empty)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = StateKey> + '_ {
+        self.accounts.keys().map(|address| StateKey::Account(*address))
+            .chain(self.storage.iter().flat_map(|(address, slots)| slots.keys().map(|key| StateKey::Slot(*address, *key))))
+    }
+}
@@ -0,0 +1,273 @@
+use ethnum::u256;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use crate::machine::opcode::OpCode;
+use crate::utils::Hash;
+
+// Enough zeros for a PUSH32 at the very end of the code to read its immediate and be followed by STOP
+const PADDING: usize = 33;
+
+// Analyses kept for the most recently used deployed code
+const CACHE_CAPACITY: usize = 4096;
+
+static ANALYSES: OnceLock<RwLock<AnalysisCache>> = OnceLock::new();
+
+// Evicts the least recently used analysis when full
+struct AnalysisCache {
+    // Along with when each analysis was last used, which reads update without taking the lock for writing
+    analyses: HashMap<u256, (Arc<Analysis>, AtomicU64)>,
+    // Hashes by the last use they were indexed at, which only falls behind the actual one until they get evicted
+    by_use: BTreeMap<u64, u256>,
+    capacity: usize,
+    clock: AtomicU64,
+}
+
+impl AnalysisCache {
+    fn new(capacity: usize) -> Self {
+        Self { analyses: HashMap::new(), by_use: BTreeMap::new(), capacity, clock: AtomicU64::new(0) }
+    }
+
+    fn get(&self, hash: u256) -> Option<Arc<Analysis>> {
+        let (analysis, used) = self.analyses.get(&hash)?;
+        used.store(self.clock.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        Some(analysis.clone())
+    }
+
+    fn insert(&mut self, hash: u256, analysis: Arc<Analysis>) -> Arc<Analysis> {
+        if let Some(analysis) = self.get(hash) { return analysis; }
+        // The oldest indexed hash is the least recently used one, unless it was used since and gets indexed again
+        while self.analyses.len() >= self.capacity {
+            let Some((indexed, oldest)) = self.by_use.pop_first() else { break };
+            let used = self.analyses[&oldest].1.load(Ordering::Relaxed);
+            match used == indexed {
+                true => { self.analyses.remove(&oldest); },
+                false => { self.by_use.insert(used, oldest); },
+            }
+        }
+        let used = self.clock.fetch_add(1, Ordering::Relaxed);
+        self.analyses.insert(hash, (analysis.clone(), AtomicU64::new(used)));
+        self.by_use.insert(used, hash);
+        analysis
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Analysis {
+    block_gas: Vec<u32>,
+    blocks: Vec<usize>,
+    // Only computed when needed, as initcode rarely is hashed
+    hash: OnceLock<u256>,
+    jumpdests: Vec<u64>,
+    len: usize,
+    padded: Vec<u8>,
+}
+
+impl Analysis {
+    fn new(code: &[u8]) -> Self {
+        let mut blocks = vec![0];
+        let mut block_gas = vec![0u32; code.len()];
+        let mut jumpdests = vec![0u64; code.len().div_ceil(64)];
+        let mut pc = 0;
+        while pc < code.len() {
+            let opcode = code[pc];
+            let next = pc + 1 + if (0x60..=0x7F).contains(&opcode) { usize::from(opcode - 0x5F) } else { 0 };
+            if opcode == 0x5B {
+                jumpdests[pc >> 6] |= 1 << (pc & 63);
+                if blocks.last() != Some(&pc) { blocks.push(pc); }
+            }
+            block_gas[*blocks.last().unwrap()] += OpCode(opcode).static_gas() as u32;
+            // Blocks end after instructions that halt, may jump, or read the remaining gas
+            if matches!(opcode, 0x00 | 0x56 | 0x57 | 0x5A | 0xF0..=0xF5 | 0xFA | 0xFD..=0xFF) && next < code.len() {
+                blocks.push(next);
+            }
+            pc = next;
+        }
+
+        let mut padded = Vec::with_capacity(code.len() + PADDING);
+        padded.extend_from_slice(code);
+        padded.resize(code.len() + PADDING, 0);
+        Self { block_gas, blocks, hash: OnceLock::new(), jumpdests, len: code.len(), padded }
+    }
+}
+
+// Shared handle to some code and its analysis, which is computed once per code hash while it stays cached
+#[derive(Clone, Eq)]
+pub struct Bytecode(Arc<Analysis>);
+
+impl Bytecode {
+    pub fn new(code: &[u8]) -> Self {
+        let hash = code.keccak256();
+        let analyses = ANALYSES.get_or_init(|| RwLock::new(AnalysisCache::new(CACHE_CAPACITY)));
+        if let Some(analysis) = analyses.read().unwrap().get(hash) {
+            return Self(analysis);
+        }
+
+        let analysis = Analysis::new(code);
+        analysis.hash.get_or_init(|| hash);
+        Self(analyses.write().unwrap().insert(hash, Arc::new(analysis)))
+    }
+
+    // Deployed code with the given hash, unless it was never analyzed or got evicted since
+    pub fn cached(hash: u256) -> Option<Self> {
+        ANALYSES.get()?.read().unwrap().get(hash).map(Self)
+    }
+
+    // For initcode, which runs once and is neither hashed nor cached
+    pub fn uncached(code: &[u8]) -> Self {
+        Self(Arc::new(Analysis::new(code)))
+    }
+
+    pub fn hash(&self) -> u256 {
+        *self.0.hash.get_or_init(|| self.keccak256())
+    }
+
+    // The code followed by zeros, so that reading past its end does not need bounds checks
+    pub fn padded(&self) -> &[u8] {
+        &self.0.padded
+    }
+
+    // Whether pc is a JUMPDEST opcode, and not part of a PUSH immediate
+    pub fn is_jumpdest(&self, pc: usize) -> bool {
+        pc < self.0.len && self.0.jumpdests[pc >> 6] & (1 << (pc & 63)) != 0
+    }
+
+    // Offsets at which basic blocks start
+    pub fn blocks(&self) -> &[usize] {
+        &self.0.blocks
+    }
+
+    // Static gas of the basic block starting at pc, or 0 if none does
+    pub fn block_gas(&self, pc: usize) -> usize {
+        self.0.block_gas.get(pc).map_or(0, |gas| *gas as usize)
+    }
+}
+
+impl Default for Bytecode {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl Deref for Bytecode {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0.padded[..self.0.len]
+    }
+}
+
+impl From<Vec<u8>> for Bytecode {
+    fn from(code: Vec<u8>) -> Self {
+        Self::new(&code)
+    }
+}
+
+impl From<&[u8]> for Bytecode {
+    fn from(code: &[u8]) -> Self {
+        Self::new(code)
+    }
+}
+
+impl PartialEq for Bytecode {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash() == other.hash()
+    }
+}
+
+impl Debug for Bytecode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bytecode({})", hex::encode(&**self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use super::*;
+
+    #[test]
+    fn analyzes_jumpdests() {
+        let code = Bytecode::new(&hex::decode("5B605B5B615B").unwrap()); // JUMPDEST PUSH1 0x5B JUMPDEST PUSH2 0x5B..
+
+        assert!(code.is_jumpdest(0));
+        assert!(!code.is_jumpdest(2));
+        assert!(code.is_jumpdest(3));
+        assert!(!code.is_jumpdest(5));
+        assert!(!code.is_jumpdest(6));
+        assert!(!code.is_jumpdest(1000));
+    }
+
+    #[test]
+    fn pads_the_code() {
+        let code = Bytecode::new(&hex::decode("7F").unwrap());
+
+        assert_eq!(&*code, [0x7F]);
+        assert_eq!(code.len(), 1);
+        assert_eq!(code.padded().len(), 34);
+        assert_eq!(code.padded()[33], 0x00);
+    }
+
+    #[test]
+    fn finds_basic_blocks() {
+        let code = Bytecode::new(&hex::decode("6001600A575B00FE5B5B").unwrap()); // PUSH1 1 PUSH1 10 JUMPI JUMPDEST STOP INVALID JUMPDEST JUMPDEST
+
+        assert_eq!(code.blocks(), [0, 5, 7, 8, 9]);
+    }
+
+    #[test]
+    fn sums_the_static_gas_of_blocks() {
+        let code = Bytecode::new(&hex::decode("6001600A575B5A01600052F35B").unwrap()); // PUSH1 1 PUSH1 10 JUMPI JUMPDEST GAS ADD PUSH1 0 MSTORE RETURN JUMPDEST
+
+        assert_eq!(code.blocks(), [0, 5, 7, 12]);
+        assert_eq!(code.block_gas(0), 16);
+        assert_eq!(code.block_gas(5), 1);
+        assert_eq!(code.block_gas(7), 9);
+        assert_eq!(code.block_gas(12), 1);
+        assert_eq!(code.block_gas(8), 0);
+        assert_eq!(code.block_gas(1000), 0);
+    }
+
+    #[test]
+    fn caches_analyses_by_code_hash() {
+        let code = Bytecode::new(&hex::decode("600160020100").unwrap());
+
+        assert!(Arc::ptr_eq(&code.0, &Bytecode::from(hex::decode("600160020100").unwrap()).0));
+        assert_eq!(code.hash(), hex::decode("600160020100").unwrap().keccak256());
+        assert_eq!(code, Bytecode::from(&[0x60, 0x01, 0x60, 0x02, 0x01, 0x00][..]));
+        assert_ne!(code, Bytecode::default());
+        assert!(Arc::ptr_eq(&Bytecode::cached(code.hash()).unwrap().0, &code.0));
+        assert!(Bytecode::cached(uint!("1")).is_none());
+    }
+
+    #[test]
+    fn does_not_cache_initcode() {
+        let code = Bytecode::uncached(&hex::decode("600260020100").unwrap());
+
+        assert!(code.0.hash.get().is_none());
+        assert!(!Arc::ptr_eq(&code.0, &Bytecode::uncached(&code).0));
+        assert_eq!(code, Bytecode::from(hex::decode("600260020100").unwrap()));
+        assert_eq!(code.hash(), hex::decode("600260020100").unwrap().keccak256());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_analysis() {
+        let mut cache = AnalysisCache::new(2);
+        let analysis = Arc::new(Analysis::new(&[]));
+        cache.insert(uint!("1"), analysis.clone());
+        cache.insert(uint!("2"), analysis.clone());
+        cache.get(uint!("1"));
+        cache.insert(uint!("3"), analysis);
+
+        assert!(cache.get(uint!("1")).is_some());
+        assert!(cache.get(uint!("2")).is_none());
+        assert!(cache.get(uint!("3")).is_some());
+
+        cache.insert(uint!("4"), Arc::new(Analysis::new(&[])));
+        assert!(cache.get(uint!("1")).is_none());
+        assert_eq!(cache.analyses.len(), 2);
+        assert_eq!(cache.by_use.len(), 2);
+    }
+}
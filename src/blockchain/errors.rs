@@ -1,13 +1,20 @@
 use ethnum::u256;
+use crate::blockchain::backend::BackendError;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
+    Backend(BackendError),
     EmptyStack,
     InsufficientFunds(u256),
     IntrisicGasTooLow(usize),
     InvalidAddress,
+    InvalidContainer,
     InvalidJumpDest,
+    InvalidNonce(usize),
+    InvalidTrustedSetup,
     MemoryOutOfBounds,
     OutOfGas,
+    PrecompileFailure,
     StackOverflow,
+    StaticStateChange,
 }
@@ -1,29 +1,326 @@
+pub mod backend;
+pub mod bytecode;
 pub mod errors;
 pub mod primitives;
 pub mod storage;
 
 use ethnum::u256;
+use crate::blockchain::backend::{AccountInfo, BackendError, ChangeSet, StateBackend, StateBackendMut};
+use crate::blockchain::bytecode::Bytecode;
 use crate::blockchain::errors::Error;
 use crate::blockchain::primitives::{Account, Address};
-use crate::blockchain::storage::Storage;
-use std::collections::HashMap;
+use crate::blockchain::storage::{Storage, StorageValue};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StateKey {
+    Account(Address),
+    Slot(Address, u256),
+}
+
+// Keys read and written during a transaction, including in sub-contexts that were rolled back, along with the values
+// that the accounts and slots had when the transaction first accessed them
+#[derive(Default, Debug)]
+pub struct AccessLog {
+    pub accounts: HashMap<Address, Account>,
+    pub reads: HashSet<StateKey>,
+    pub slots: HashMap<(Address, u256), u256>,
+    pub writes: HashSet<StateKey>,
+}
+
+// What an account or a slot was before it got written or warmed, none if it was not in memory
+#[derive(Clone, Debug)]
+pub enum JournalEntry {
+    Account(Address, Option<StorageValue<Account>>),
+    Slot(Address, u256, Option<StorageValue<u256>>),
+}
+
+#[derive(Default, Clone)]
 pub struct WorldState {
     pub accounts: Storage<Address, Account>,
+    // Where the accounts and slots that are not in memory yet get loaded from, if they are not all in memory
+    pub backend: Option<Arc<dyn StateBackend>>,
     pub chain_id: u256,
+    // Number of running checkpoints, changes are only journaled while there is one
+    pub checkpoints: usize,
+    // Code of the accounts that have been in memory, by hash, so that it is found without going through the accounts
+    pub codes: HashMap<u256, Bytecode>,
+    pub journal: Vec<JournalEntry>,
+    // Not journaled, so that rolling back a sub-context does not forget its accesses
+    pub log: Option<Arc<Mutex<AccessLog>>>,
+    // Values that the accounts and slots written since the last changeset had before
+    pub pristine: ChangeSet,
+    // Gas refund counter of the running transaction, which only EIP-7702 authorizations add to so far
+    pub refund: usize,
     pub storage: HashMap<Address, Storage<u256, u256>>,
 }
 
 impl WorldState {
+    // Called once the key is fetched and before the access, so that the first one finds the value from before the transaction
+    fn record(&self, key: StateKey, write: bool) {
+        if let Some(log) = &self.log {
+            let mut log = log.lock().unwrap();
+            match key {
+                StateKey::Account(address) => { log.accounts.entry(address).or_insert_with(|| self.accounts.0.get(&address).map(|entry| entry.value.clone()).unwrap_or_default()); },
+                StateKey::Slot(address, key) => { log.slots.entry((address, key)).or_insert_with(|| self.storage.get(&address).and_then(|storage| storage.0.get(&key)).map_or(u256::ZERO, |entry| entry.value)); },
+            }
+            if write { log.writes.insert(key) } else { log.reads.insert(key) };
+        }
+    }
+
+    // Brings an account from the backend, cold, unless it is already in memory
+    fn fetch_account(&mut self, address: Address) -> Result<(), BackendError> {
+        let Some(backend) = &self.backend else { return Ok(()) };
+        if !self.accounts.0.contains_key(&address) {
+            let account = backend.account(address)?;
+            self.index_code(&account.code);
+            self.accounts.insert_missing(address, || account);
+        }
+        Ok(())
+    }
+
+    fn fetch_slot(&mut self, address: Address, key: u256) -> Result<(), BackendError> {
+        let Some(backend) = &self.backend else { return Ok(()) };
+        let storage = self.storage.entry(address).or_default();
+        if !storage.0.contains_key(&key) {
+            let value = backend.storage(address, key)?;
+            storage.insert_missing(key, || value);
+        }
+        Ok(())
+    }
+
+    fn index_code(&mut self, code: &Bytecode) {
+        if !code.is_empty() { self.codes.entry(code.hash()).or_insert_with(|| code.clone()); }
+    }
+
+    fn journal_account(&mut self, address: Address, write: bool) {
+        if self.checkpoints == 0 { return; }
+        let entry = self.accounts.0.get(&address);
+        if write || entry.is_none_or(|entry| !entry.warm) {
+            self.journal.push(JournalEntry::Account(address, entry.cloned()));
+        }
+    }
+
+    fn journal_slot(&mut self, address: Address, key: u256, write: bool) {
+        if self.checkpoints == 0 { return; }
+        let entry = self.storage.get(&address).and_then(|storage| storage.0.get(&key));
+        if write || entry.is_none_or(|entry| !entry.warm) {
+            self.journal.push(JournalEntry::Slot(address, key, entry.cloned()));
+        }
+    }
+
+    fn touch_account(&mut self, address: Address) {
+        if !self.pristine.accounts.contains_key(&address) {
+            let account = self.accounts.0.get(&address).map(|entry| entry.value.clone()).unwrap_or_default();
+            self.pristine.accounts.insert(address, account);
+        }
+    }
+
+    fn touch_slot(&mut self, address: Address, key: u256) {
+        let value = self.storage.get(&address).and_then(|storage| storage.0.get(&key)).map_or(u256::ZERO, |entry| entry.value);
+        self.pristine.storage.entry(address).or_default().entry(key).or_insert(value);
+    }
+
+    pub fn load_account(&mut self, address: Address) -> Result<StorageValue<Account>, Error> {
+        self.fetch_account(address).map_err(Error::Backend)?;
+        self.record(StateKey::Account(address), false);
+        self.journal_account(address, false);
+        Ok(self.accounts.load(address))
+    }
+
+    pub fn store_account(&mut self, address: Address, account: Account) -> Result<(), Error> {
+        self.fetch_account(address).map_err(Error::Backend)?;
+        self.record(StateKey::Account(address), true);
+        self.touch_account(address);
+        self.journal_account(address, true);
+        self.index_code(&account.code);
+        self.accounts.store(address, account);
+        Ok(())
+    }
+
+    pub fn load_slot(&mut self, address: Address, key: u256) -> Result<StorageValue<u256>, Error> {
+        self.fetch_slot(address, key).map_err(Error::Backend)?;
+        self.record(StateKey::Slot(address, key), false);
+        self.journal_slot(address, key, false);
+        Ok(self.storage.entry(address).or_default().load(key))
+    }
+
+    // Returns the previous value, so the slot is read as well
+    pub fn store_slot(&mut self, address: Address, key: u256, value: u256) -> Result<Option<StorageValue<u256>>, Error> {
+        self.fetch_slot(address, key).map_err(Error::Backend)?;
+        self.record(StateKey::Slot(address, key), false);
+        self.record(StateKey::Slot(address, key), true);
+        self.touch_slot(address, key);
+        self.journal_slot(address, key, true);
+        Ok(self.storage.entry(address).or_default().store(key, value))
+    }
+
+    // Starts journaling the changes, to roll them back if the sub-context that takes the checkpoint fails
+    pub fn checkpoint(&mut self) -> usize {
+        self.checkpoints += 1;
+        self.journal.len()
+    }
+
+    // Keeps the changes since the checkpoint, the enclosing checkpoint can still roll them back
+    pub fn commit_checkpoint(&mut self) {
+        self.checkpoints -= 1;
+        if self.checkpoints == 0 { self.journal.clear(); }
+    }
+
+    // Puts back the accounts and slots, warmth included, as they were when the checkpoint was taken
+    pub fn revert_checkpoint(&mut self, checkpoint: usize) {
+        for entry in self.journal.drain(checkpoint..).rev() {
+            match entry {
+                JournalEntry::Account(address, Some(previous)) => { self.accounts.0.insert(address, previous); },
+                JournalEntry::Account(address, None) => { self.accounts.0.remove(&address); },
+                JournalEntry::Slot(address, key, Some(previous)) => { self.storage.entry(address).or_default().0.insert(key, previous); },
+                JournalEntry::Slot(address, key, None) => { self.storage.entry(address).or_default().0.remove(&key); },
+            }
+        }
+        self.commit_checkpoint();
+    }
+
+    // Hashes of past blocks are only known to the backend
+    pub fn block_hash(&self, number: u256) -> u256 {
+        self.backend.as_ref().map_or(u256::ZERO, |backend| backend.block_hash(number))
+    }
+
+    // Ends a transaction: current values become the original ones, and every account and slot gets cold again
+    pub fn commit(&mut self) {
+        for entry in self.accounts.0.values_mut() {
+            entry.original_value = entry.value.clone();
+            entry.warm = false;
+        }
+        for entry in self.storage.values_mut().flat_map(|storage| storage.0.values_mut()) {
+            entry.original_value = entry.value;
+            entry.warm = false;
+        }
+    }
+
+    // Ends a changeset, and returns the accounts and slots whose values changed since the previous one
+    pub fn take_changes(&mut self) -> ChangeSet {
+        let pristine = std::mem::take(&mut self.pristine);
+        let mut changes = ChangeSet::default();
+        for (address, account) in pristine.accounts {
+            let current = self.accounts.0.get(&address).map(|entry| &entry.value).unwrap_or(&account);
+            if *current != account { changes.accounts.insert(address, current.clone()); }
+        }
+        for (address, slots) in pristine.storage {
+            for (key, value) in slots {
+                let current = self.storage.get(&address).and_then(|storage| storage.0.get(&key)).map_or(value, |entry| entry.value);
+                if current != value { changes.storage.entry(address).or_default().insert(key, current); }
+            }
+        }
+        changes
+    }
+
+    // Puts back the values that the accounts and slots had at the end of the last changeset
+    pub fn discard_changes(&mut self) {
+        let ChangeSet { accounts, storage } = std::mem::take(&mut self.pristine);
+        for (address, account) in accounts {
+            self.accounts.0.insert(address, StorageValue { original_value: account.clone(), value: account, warm: false });
+        }
+        for (address, slots) in storage {
+            for (key, value) in slots {
+                self.storage.entry(address).or_default().0.insert(key, StorageValue { original_value: value, value, warm: false });
+            }
+        }
+    }
+
     pub fn decrease_balance(&mut self, address: Address, cost: u256) -> Result<(), Error> {
-        let account = self.accounts.load(address).value;
+        let account = self.load_account(address)?.value;
 
-        self.accounts.store(address, Account {
+        self.store_account(address, Account {
             balance: account.check_enough_funds(cost)?,
             code: account.code,
-        });
+            nonce: account.nonce,
+        })
+    }
+
+    pub fn increase_balance(&mut self, address: Address, value: u256) -> Result<(), Error> {
+        let account = self.load_account(address)?.value;
+
+        self.store_account(address, Account {
+            balance: account.balance + value,
+            ..account
+        })
+    }
+
+    // Follows EIP-7702 delegation designators, and returns the cost of accessing the delegated account along with the
+    // code. EXTCODE* read the designator itself
+    pub fn load_code(&mut self, address: Address) -> Result<(Bytecode, usize), Error> {
+        let account = self.load_account(address)?.value;
+        Ok(match account.delegation() {
+            Some(delegated) => {
+                let delegate = self.load_account(delegated)?;
+                (delegate.value.code, if delegate.warm { 100 } else { 2600 })
+            },
+            None => (account.code, 0),
+        })
+    }
+}
+
+// Current values, without warming anything, so that a WorldState can be the backend of another one
+impl StateBackend for WorldState {
+    fn basic(&self, address: Address) -> Result<Option<AccountInfo>, BackendError> {
+        match self.accounts.0.get(&address) {
+            Some(entry) => Ok(Some(AccountInfo { balance: entry.value.balance, code_hash: entry.value.code.hash(), nonce: entry.value.nonce })),
+            None => self.backend.as_ref().map_or(Ok(None), |backend| backend.basic(address)),
+        }
+    }
+
+    // Shares the code of accounts that are in memory instead of looking it up by hash
+    fn account(&self, address: Address) -> Result<Account, BackendError> {
+        match self.accounts.0.get(&address) {
+            Some(entry) => Ok(entry.value.clone()),
+            None => self.backend.as_ref().map_or_else(|| Ok(Account::default()), |backend| backend.account(address)),
+        }
+    }
 
+    fn code_by_hash(&self, hash: u256) -> Result<Bytecode, BackendError> {
+        match self.codes.get(&hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.backend.as_ref().map_or_else(|| Ok(Bytecode::default()), |backend| backend.code_by_hash(hash)),
+        }
+    }
+
+    fn storage(&self, address: Address, key: u256) -> Result<u256, BackendError> {
+        match self.storage.get(&address).and_then(|storage| storage.0.get(&key)) {
+            Some(entry) => Ok(entry.value),
+            None => self.backend.as_ref().map_or(Ok(u256::ZERO), |backend| backend.storage(address, key)),
+        }
+    }
+
+    fn block_hash(&self, number: u256) -> u256 {
+        WorldState::block_hash(self, number)
+    }
+}
+
+// Applied changes are cold and count as original values, yet they still belong to the current changeset. Everything
+// they overwrite is fetched first, so that a failing backend leaves the state as it was
+impl StateBackendMut for WorldState {
+    fn apply(&mut self, changes: ChangeSet) -> Result<(), BackendError> {
+        for address in changes.accounts.keys() {
+            self.fetch_account(*address)?;
+        }
+        for (address, slots) in &changes.storage {
+            for key in slots.keys() {
+                self.fetch_slot(*address, *key)?;
+            }
+        }
+
+        for (address, account) in changes.accounts {
+            self.touch_account(address);
+            self.index_code(&account.code);
+            self.accounts.0.insert(address, StorageValue { original_value: account.clone(), value: account, warm: false });
+        }
+        for (address, slots) in changes.storage {
+            for (key, value) in slots {
+                self.touch_slot(address, key);
+                self.storage.entry(address).or_default().0.insert(key, StorageValue { original_value: value, value, warm: false });
+            }
+        }
         Ok(())
     }
 }
@@ -31,8 +328,166 @@ impl WorldState {
 #[cfg(test)]
 mod tests {
     use ethnum::uint;
-    use storage::StorageValue;
     use super::*;
+    use crate::blockchain::primitives::{Block, Transaction};
+    use crate::evm::Evm;
+
+    #[derive(Default)]
+    struct MockBackend {
+        accounts: HashMap<Address, Account>,
+        slots: HashMap<(Address, u256), u256>,
+    }
+
+    // Fails for the account and the slot at 0xBAD
+    impl StateBackend for MockBackend {
+        fn basic(&self, address: Address) -> Result<Option<AccountInfo>, BackendError> {
+            if address == Address(uint!("0xBAD")) { return Err(BackendError("unreachable".to_string())); }
+            Ok(self.accounts.get(&address).map(|account| AccountInfo { balance: account.balance, code_hash: account.code.hash(), nonce: account.nonce }))
+        }
+
+        fn code_by_hash(&self, hash: u256) -> Result<Bytecode, BackendError> {
+            Ok(self.accounts.values().find(|account| account.code.hash() == hash).unwrap().code.clone())
+        }
+
+        fn storage(&self, address: Address, key: u256) -> Result<u256, BackendError> {
+            if key == uint!("0xBAD") { return Err(BackendError("unreachable".to_string())); }
+            Ok(self.slots.get(&(address, key)).copied().unwrap_or_default())
+        }
+
+        fn block_hash(&self, number: u256) -> u256 {
+            number + 1000
+        }
+    }
+
+    fn backend() -> Arc<dyn StateBackend> {
+        let mut backend = MockBackend::default();
+        backend.accounts.insert(Address(uint!("0xC0")), Account { balance: uint!("42"), code: vec![0x60, 0x42].into(), nonce: 1 });
+        backend.slots.insert((Address(uint!("0xC0")), uint!("1")), uint!("7"));
+        Arc::new(backend)
+    }
+
+    #[test]
+    fn loads_state_from_the_backend() {
+        let mut s = WorldState { backend: Some(backend()), ..Default::default() };
+
+        assert_eq!(s.load_account(Address(uint!("0xC0"))).unwrap(), StorageValue {
+            original_value: Account { balance: uint!("42"), code: vec![0x60, 0x42].into(), nonce: 1 },
+            value: Account { balance: uint!("42"), code: vec![0x60, 0x42].into(), nonce: 1 },
+            warm: false,
+        });
+        assert!(s.load_account(Address(uint!("0xC0"))).unwrap().warm);
+        assert_eq!(s.codes.get(&Bytecode::from(vec![0x60, 0x42]).hash()), Some(&vec![0x60, 0x42].into()));
+        assert_eq!(s.load_account(Address(uint!("0xC1"))).unwrap().value, Account::default());
+
+        s.increase_balance(Address(uint!("0xC0")), uint!("8")).unwrap();
+        assert_eq!(s.accounts.load(Address(uint!("0xC0"))).original_value.balance, uint!("42"));
+        assert_eq!(s.accounts.load(Address(uint!("0xC0"))).value.balance, uint!("50"));
+
+        assert_eq!(s.store_slot(Address(uint!("0xC0")), uint!("1"), uint!("8")).unwrap(), Some(StorageValue { original_value: uint!("7"), value: uint!("7"), warm: false }));
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("1")).unwrap(), StorageValue { original_value: uint!("7"), value: uint!("8"), warm: true });
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("2")).unwrap(), StorageValue { original_value: uint!("0"), value: uint!("0"), warm: false });
+        assert_eq!(s.block_hash(uint!("5")), uint!("1005"));
+        assert_eq!(WorldState::default().block_hash(uint!("5")), uint!("0"));
+    }
+
+    #[test]
+    fn fails_when_the_backend_does() {
+        let mut s = WorldState { backend: Some(backend()), ..Default::default() };
+        let unreachable = || Error::Backend(BackendError("unreachable".to_string()));
+
+        assert_eq!(s.load_account(Address(uint!("0xBAD"))), Err(unreachable()));
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("0xBAD")), Err(unreachable()));
+        assert_eq!(s.store_slot(Address(uint!("0xC0")), uint!("0xBAD"), uint!("1")), Err(unreachable()));
+        assert!(!s.accounts.0.contains_key(&Address(uint!("0xBAD"))));
+
+        // Nothing gets applied when some of the changes cannot be fetched
+        let changes = ChangeSet { accounts: HashMap::from([(Address(uint!("0xC0")), Account::default()), (Address(uint!("0xBAD")), Account::default())]), ..Default::default() };
+        assert_eq!(s.apply(changes), Err(BackendError("unreachable".to_string())));
+        assert_eq!(s.load_account(Address(uint!("0xC0"))).unwrap().value.balance, uint!("42"));
+        assert!(s.take_changes().is_empty());
+
+        // Calling an account that cannot be loaded aborts the transaction instead of failing the call: PUSH0 PUSH0
+        // PUSH0 PUSH0 PUSH0 PUSH2 0x0BAD GAS CALL STOP
+        let mut evm = Evm::builder()
+            .account(Address(uint!("0xA0")), Account { balance: uint!("100000000"), ..Default::default() })
+            .account(Address(uint!("0xC2")), Account { balance: uint!("0"), code: vec![0x5F, 0x5F, 0x5F, 0x5F, 0x5F, 0x61, 0x0B, 0xAD, 0x5A, 0xF1, 0x00].into(), nonce: 1 })
+            .backend(backend())
+            .build();
+        let tx = Transaction { from: Address(uint!("0xA0")), gas: 100000, gas_price: 1, to: Address(uint!("0xC2")), ..Default::default() };
+        assert_eq!(evm.transact(Block::default(), tx), Err(unreachable()));
+    }
+
+    #[test]
+    fn is_a_backend_of_its_current_values() {
+        let mut s = WorldState { backend: Some(backend()), ..Default::default() };
+        s.store_account(Address(uint!("0xC1")), Account { balance: uint!("1"), code: vec![0x00].into(), nonce: 0 }).unwrap();
+        s.store_slot(Address(uint!("0xC1")), uint!("1"), uint!("3")).unwrap();
+
+        assert_eq!(s.account(Address(uint!("0xC1"))).unwrap(), Account { balance: uint!("1"), code: vec![0x00].into(), nonce: 0 });
+        assert_eq!(s.account(Address(uint!("0xC0"))).unwrap(), Account { balance: uint!("42"), code: vec![0x60, 0x42].into(), nonce: 1 });
+        assert_eq!(StateBackend::storage(&s, Address(uint!("0xC1")), uint!("1")).unwrap(), uint!("3"));
+        assert_eq!(StateBackend::storage(&s, Address(uint!("0xC0")), uint!("1")).unwrap(), uint!("7"));
+        assert_eq!(StateBackend::block_hash(&s, uint!("5")), uint!("1005"));
+        assert_eq!(s.code_by_hash(Bytecode::from(vec![0x00]).hash()).unwrap(), vec![0x00].into());
+        assert!(!s.accounts.0.contains_key(&Address(uint!("0xC0"))));
+    }
+
+    #[test]
+    fn tracks_changes_since_the_last_changeset() {
+        let mut s = WorldState { backend: Some(backend()), ..Default::default() };
+        s.increase_balance(Address(uint!("0xC0")), uint!("8")).unwrap();
+        s.store_slot(Address(uint!("0xC0")), uint!("1"), uint!("8")).unwrap();
+        s.store_slot(Address(uint!("0xC0")), uint!("2"), uint!("0")).unwrap();
+        s.commit();
+        s.store_slot(Address(uint!("0xC0")), uint!("1"), uint!("9")).unwrap();
+        s.load_account(Address(uint!("0xC1"))).unwrap();
+
+        let changes = s.take_changes();
+        assert_eq!(changes, ChangeSet {
+            accounts: HashMap::from([(Address(uint!("0xC0")), Account { balance: uint!("50"), code: vec![0x60, 0x42].into(), nonce: 1 })]),
+            storage: HashMap::from([(Address(uint!("0xC0")), HashMap::from([(uint!("1"), uint!("9"))]))]),
+        });
+        assert!(s.take_changes().is_empty());
+
+        s.increase_balance(Address(uint!("0xC0")), uint!("1")).unwrap();
+        s.store_slot(Address(uint!("0xC0")), uint!("1"), uint!("10")).unwrap();
+        s.discard_changes();
+        assert_eq!(s.load_account(Address(uint!("0xC0"))).unwrap().value.balance, uint!("50"));
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("1")).unwrap(), StorageValue { original_value: uint!("9"), value: uint!("9"), warm: false });
+        assert!(s.take_changes().is_empty());
+
+        let mut parent = WorldState::default();
+        parent.apply(changes.clone()).unwrap();
+        assert_eq!(parent.load_slot(Address(uint!("0xC0")), uint!("1")).unwrap(), StorageValue { original_value: uint!("9"), value: uint!("9"), warm: false });
+        assert_eq!(parent.take_changes(), changes);
+    }
+
+    #[test]
+    fn reverts_to_a_checkpoint() {
+        let mut s = WorldState { backend: Some(backend()), ..Default::default() };
+        s.increase_balance(Address(uint!("0xC0")), uint!("8")).unwrap();
+
+        let outer = s.checkpoint();
+        s.store_slot(Address(uint!("0xC0")), uint!("1"), uint!("8")).unwrap();
+        let inner = s.checkpoint();
+        s.increase_balance(Address(uint!("0xC0")), uint!("1")).unwrap();
+        s.load_account(Address(uint!("0xC1"))).unwrap();
+        s.store_slot(Address(uint!("0xC0")), uint!("1"), uint!("9")).unwrap();
+        s.revert_checkpoint(inner);
+        assert_eq!(s.load_account(Address(uint!("0xC0"))).unwrap().value.balance, uint!("50"));
+        assert!(!s.load_account(Address(uint!("0xC1"))).unwrap().warm);
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("1")).unwrap().value, uint!("8"));
+
+        s.checkpoint();
+        s.store_slot(Address(uint!("0xC0")), uint!("2"), uint!("3")).unwrap();
+        s.commit_checkpoint();
+        s.revert_checkpoint(outer);
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("1")).unwrap(), StorageValue { original_value: uint!("7"), value: uint!("7"), warm: false });
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("2")).unwrap(), StorageValue { original_value: uint!("0"), value: uint!("0"), warm: false });
+        assert!(!s.load_account(Address(uint!("0xC1"))).unwrap().warm);
+        assert_eq!(s.load_account(Address(uint!("0xC0"))).unwrap().value.balance, uint!("50"));
+        assert!(s.journal.is_empty());
+    }
 
     #[test]
     fn decrease_balance() {
@@ -41,7 +496,8 @@ mod tests {
             original_value: Account::default(),
             value: Account {
                 balance: uint!("42"),
-                code: vec![],
+                code: Bytecode::default(),
+                nonce: 0,
             },
             warm: true,
         });
@@ -50,7 +506,42 @@ mod tests {
         assert!(s.decrease_balance(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), uint!("40")).is_ok());
         assert_eq!(s.accounts.load(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).value, Account {
             balance: uint!("2"),
-            code: vec![],
+            code: Bytecode::default(),
+            nonce: 0,
         });
     }
+
+    #[test]
+    fn load_code_follows_delegation() {
+        let mut s = WorldState::default();
+        let mut authority = Account::default();
+        authority.delegate_to(Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")));
+        s.accounts.store(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), authority);
+        s.accounts.0.insert(Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), StorageValue::<Account> {
+            original_value: Account::default(),
+            value: Account {
+                balance: uint!("0"),
+                code: vec![0x60, 0x42].into(),
+                nonce: 1,
+            },
+            warm: false,
+        });
+
+        assert_eq!(s.load_code(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).unwrap(), (vec![0x60, 0x42].into(), 2600));
+        assert!(s.accounts.load(Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044"))).warm);
+        assert_eq!(s.load_code(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).unwrap(), (vec![0x60, 0x42].into(), 100));
+        assert_eq!(s.load_code(Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044"))).unwrap(), (vec![0x60, 0x42].into(), 0));
+    }
+
+    #[test]
+    fn account_loads_share_the_code() {
+        let mut s = WorldState::default();
+        let code = Bytecode::from(vec![0x60; 24576]);
+        s.accounts.store(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: uint!("42"), code: code.clone(), nonce: 1 });
+
+        s.decrease_balance(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), uint!("40")).unwrap();
+        let account = s.accounts.load(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).value;
+        assert_eq!(account.balance, uint!("2"));
+        assert_eq!(account.code.padded().as_ptr(), code.padded().as_ptr());
+    }
 }
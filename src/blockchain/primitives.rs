@@ -1,13 +1,18 @@
 use ethnum::{u256, U256};
+use crate::blockchain::bytecode::Bytecode;
 use crate::blockchain::errors::Error;
 use crate::utils::Hash;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
 use rlp::RlpStream;
 use std::cmp::Ordering;
 
+const DELEGATION_DESIGNATOR: [u8; 3] = [0xEF, 0x01, 0x00];
+
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct Account {
     pub balance: u256,
-    pub code: Vec<u8>,
+    pub code: Bytecode,
+    pub nonce: usize,
 }
 
 impl Account {
@@ -17,9 +22,29 @@ impl Account {
             _ => Ok(self.balance - cost),
         }
     }
+
+    // EIP-7702 delegated accounts have their code set to `0xEF0100 || address`
+    pub fn delegation(&self) -> Option<Address> {
+        match &self.code[..] {
+            [0xEF, 0x01, 0x00, address @ ..] if address.len() == 20 => {
+                let mut bytes = [0u8; 32];
+                bytes[12..].copy_from_slice(address);
+                Some(Address(u256::from_be_bytes(bytes)))
+            },
+            _ => None,
+        }
+    }
+
+    pub fn delegate_to(&mut self, address: Address) {
+        self.code = if address.0 == U256::ZERO { Bytecode::default() } else { [DELEGATION_DESIGNATOR.as_slice(), &address.0.to_be_bytes()[12..]].concat().into() };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.balance == U256::ZERO && self.code.is_empty() && self.nonce == 0
+    }
 }
 
-#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Address(pub u256);
 
 impl std::fmt::Debug for Address {
@@ -36,7 +61,39 @@ impl TryInto::<Address> for u256 {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Debug, Clone)]
+pub struct Authorization {
+    pub address: Address,
+    pub chain_id: u256,
+    pub nonce: usize,
+    pub r: u256,
+    pub s: u256,
+    pub y_parity: u8,
+}
+
+impl Authorization {
+    pub fn signing_hash(&self) -> u256 { // keccak256(0x05 || rlp([chain_id, address, nonce]))
+        let chain_id = self.chain_id.to_be_bytes();
+        let mut stream = RlpStream::new_list(3);
+        stream
+            .append(&&chain_id[(self.chain_id.leading_zeros() as usize / 8)..])
+            .append(&&self.address.0.to_be_bytes()[12..])
+            .append(&(self.nonce as u64));
+        [vec![0x05], stream.out().to_vec()].concat().keccak256()
+    }
+
+    pub fn authority(&self) -> Option<Address> {
+        let signature = Signature::from_scalars(self.r.to_be_bytes(), self.s.to_be_bytes()).ok()?;
+        if signature.normalize_s().is_some() { return None; } // s must be lower than or equal to secp256k1n / 2
+        if self.y_parity > 1 { return None; }
+        let recovery_id = RecoveryId::from_byte(self.y_parity)?;
+        let key = VerifyingKey::recover_from_prehash(&self.signing_hash().to_be_bytes(), &signature, recovery_id).ok()?;
+        let hash = key.to_encoded_point(false).as_bytes()[1..].to_vec().keccak256();
+        Some(Address(hash & u256::from_str_hex("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap()))
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct Block {
     pub difficulty: u256,
     pub gas_limit: u256,
@@ -47,6 +104,7 @@ pub struct Block {
 
 #[derive(Default, Debug, Clone)]
 pub struct Transaction {
+    pub authorization_list: Vec<Authorization>,
     pub data: Vec<u8>,
     pub from: Address,
     pub gas: usize,
@@ -87,6 +145,7 @@ impl Transaction {
     pub fn intrinsic_gas_cost(&self) -> usize {
         21000 +
             if self.is_contract_creation() { 32000 } else { 0 } +
+            25000 * self.authorization_list.len() +
             self.data.iter().map(|b| if *b == 0 { 4 } else { 16 }).sum::<usize>()
     }
 }
@@ -94,23 +153,81 @@ impl Transaction {
 #[cfg(test)]
 mod tests {
     use ethnum::uint;
+    use k256::ecdsa::SigningKey;
     use super::*;
 
+    impl Authorization {
+        pub fn signed(self, key: &SigningKey) -> Self {
+            let (signature, recovery_id) = key.sign_prehash_recoverable(&self.signing_hash().to_be_bytes()).unwrap();
+            let bytes = signature.to_bytes();
+            Self {
+                r: u256::from_be_bytes(bytes[..32].try_into().unwrap()),
+                s: u256::from_be_bytes(bytes[32..].try_into().unwrap()),
+                y_parity: recovery_id.to_byte(),
+                ..self
+            }
+        }
+    }
+
     #[test]
     fn check_enough_funds() {
         assert_eq!(Account {
             balance: uint!("1"),
-            code: vec![],
+            code: Bytecode::default(),
+            nonce: 0,
         }.check_enough_funds(uint!("2")), Err(Error::InsufficientFunds(uint!("2"))));
         assert_eq!(Account {
             balance: uint!("5"),
-            code: vec![],
+            code: Bytecode::default(),
+            nonce: 0,
         }.check_enough_funds(uint!("2")), Ok(uint!("3")));
     }
 
+    #[test]
+    fn delegation() {
+        let mut account = Account::default();
+        assert_eq!(account.delegation(), None);
+
+        account.delegate_to(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")));
+        assert_eq!(account.code, hex::decode("EF0100F0490D46185BEC962CAC93120B52389748E99C0C").unwrap().into());
+        assert_eq!(account.delegation(), Some(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))));
+
+        account.delegate_to(Address::default());
+        assert_eq!(account.code, Bytecode::default());
+        assert_eq!(Account { balance: uint!("0"), code: hex::decode("EF0100F0490D46185BEC962CAC93120B52389748E99C").unwrap().into(), nonce: 0 }.delegation(), None);
+        assert_eq!(Account { balance: uint!("0"), code: hex::decode("EF0000F0490D46185BEC962CAC93120B52389748E99C0C").unwrap().into(), nonce: 0 }.delegation(), None);
+    }
+
+    #[test]
+    fn authority() {
+        let key = SigningKey::from_slice(&uint!("1").to_be_bytes()).unwrap();
+        let authorization = Authorization {
+            address: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            chain_id: uint!("1"),
+            nonce: 3,
+            ..Default::default()
+        }.signed(&key);
+
+        assert_eq!(authorization.authority(), Some(Address(uint!("0x7E5F4552091A69125D5DFCB7B8C2659029395BDF"))));
+        assert_ne!(Authorization { nonce: 4, ..authorization.clone() }.authority(), Some(Address(uint!("0x7E5F4552091A69125D5DFCB7B8C2659029395BDF"))));
+        assert_eq!(Authorization { r: uint!("0"), ..authorization.clone() }.authority(), None);
+        assert_eq!(Authorization { y_parity: authorization.y_parity + 2, ..authorization.clone() }.authority(), None);
+    }
+
+    #[test]
+    fn authority_rejects_high_s() {
+        let key = SigningKey::from_slice(&uint!("1").to_be_bytes()).unwrap();
+        let authorization = Authorization { chain_id: uint!("1"), ..Default::default() }.signed(&key);
+        let n = uint!("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141");
+
+        assert!(authorization.authority().is_some());
+        assert_eq!(Authorization { s: n - authorization.s, y_parity: authorization.y_parity ^ 1, ..authorization }.authority(), None);
+    }
+
     #[test]
     fn intrinsic_gas_cost() {
         let tx1 = Transaction {
+            authorization_list: vec![],
             data: hex::decode("4200").unwrap(),
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 1,
@@ -122,6 +239,7 @@ mod tests {
         assert_eq!(tx1.intrinsic_gas_cost(), 21020);
 
         let tx2 = Transaction {
+            authorization_list: vec![],
             data: hex::decode("42002025").unwrap(),
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 1,
@@ -131,11 +249,18 @@ mod tests {
             value: uint!("4"),
         };
         assert_eq!(tx2.intrinsic_gas_cost(), 53052);
+
+        let tx3 = Transaction {
+            authorization_list: vec![Authorization::default(), Authorization::default()],
+            ..tx1
+        };
+        assert_eq!(tx3.intrinsic_gas_cost(), 71020);
     }
 
     #[test]
     fn contract_address() {
         let transaction = Transaction {
+            authorization_list: vec![],
             data: Default::default(),
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 1,
@@ -150,6 +275,7 @@ mod tests {
     #[test]
     fn contract_address_creation() {
         let transaction = Transaction {
+            authorization_list: vec![],
             data: Default::default(),
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 1,
@@ -166,6 +292,7 @@ mod tests {
     #[test]
     fn is_contract_creation() {
         assert!(!Transaction {
+            authorization_list: vec![],
             data: Default::default(),
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 1,
@@ -175,6 +302,7 @@ mod tests {
             value: uint!("4"),
         }.is_contract_creation());
         assert!(Transaction {
+            authorization_list: vec![],
             data: Default::default(),
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 1,
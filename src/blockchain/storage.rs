@@ -7,7 +7,7 @@ pub struct StorageValue<V> {
     pub warm: bool,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Storage<K, V>(pub HashMap<K, StorageValue<V>>);
 
 impl<K, V> Storage<K, V> where K: Hash + Eq, V: Default + Clone {
@@ -26,6 +26,14 @@ impl<K, V> Storage<K, V> where K: Hash + Eq, V: Default + Clone {
         }
     }
 
+    // Stores a cold value that was loaded from elsewhere, unless the key is already stored
+    pub fn insert_missing(&mut self, key: K, value: impl FnOnce() -> V) {
+        self.0.entry(key).or_insert_with(|| {
+            let value = value();
+            StorageValue { original_value: value.clone(), value, warm: false }
+        });
+    }
+
     pub fn load(&mut self, key: K) -> StorageValue<V> {
         match self.0.get_mut(&key) {
             Some(v) => {
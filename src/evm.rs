@@ -1,42 +1,128 @@
 use ethnum::u256;
 use crate::blockchain::WorldState;
+use crate::blockchain::backend::{ChangeSet, StateBackend};
 use crate::blockchain::storage::Storage;
 use crate::blockchain::primitives::{Account, Address, Block, Transaction};
 use crate::machine::{ExecutionResult, Machine};
 use crate::machine::context::TransactionContext;
+use crate::machine::inspector::Inspector;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
 
-struct EvmParameters {
+// Initial world state of an Evm
+#[derive(Default)]
+pub struct EvmBuilder {
     accounts: HashMap::<Address, Account>,
+    backend: Option<Arc<dyn StateBackend>>,
     chain_id: u256,
     storage: HashMap<Address, HashMap::<u256, u256>>,
 }
 
-#[derive(Default)]
-struct Evm(WorldState);
+impl EvmBuilder {
+    pub fn account(mut self, address: Address, account: Account) -> Self {
+        self.accounts.insert(address, account);
+        self
+    }
 
-impl Evm {
-    fn new(parameters: EvmParameters) -> Self {
-        let accounts = Storage::new(parameters.accounts);
+    // Loads the accounts and slots that were not given to the builder from `backend`
+    pub fn backend(mut self, backend: Arc<dyn StateBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u256) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn storage(mut self, address: Address, key: u256, value: u256) -> Self {
+        self.storage.entry(address).or_default().insert(key, value);
+        self
+    }
+
+    pub fn build(self) -> Evm {
+        let codes = self.accounts.values().filter(|account| !account.code.is_empty()).map(|account| (account.code.hash(), account.code.clone())).collect();
+        let accounts = Storage::new(self.accounts);
         let mut storage = HashMap::<Address, Storage<u256, u256>>::default();
-        for (address, store) in parameters.storage {
+        for (address, store) in self.storage {
             storage.insert(address, Storage::new(store));
         }
-        let world_state = WorldState { accounts, chain_id: parameters.chain_id, storage };
+        let world_state = WorldState { accounts, backend: self.backend, chain_id: self.chain_id, codes, storage, ..Default::default() };
 
-        Self(world_state)
+        Evm(world_state)
     }
+}
 
-    fn run(&mut self, block: Block, tx: Transaction) -> ExecutionResult {
-        let tctx = TransactionContext { block, tx };
+#[derive(Default)]
+pub struct Evm(WorldState);
+
+impl Evm {
+    pub fn builder() -> EvmBuilder {
+        EvmBuilder::default()
+    }
+
+    pub fn state(&self) -> &WorldState {
+        &self.0
+    }
+
+    // Returns the accounts and slots changed since the last call, to be applied to the backend the state was loaded from
+    pub fn take_changes(&mut self) -> ChangeSet {
+        self.0.take_changes()
+    }
+
+    // Goes back to the state at the last call to take_changes
+    pub fn discard_changes(&mut self) {
+        self.0.discard_changes();
+    }
+
+    // Executes a transaction and keeps its changes, without committing it: the accounts and slots it accessed stay warm
+    // and keep their original values, so the next transaction is priced as if it continued this one. Use transact to
+    // execute transactions one after the other, and call to leave the state untouched
+    pub fn run(&mut self, block: Block, tx: Transaction) -> ExecutionResult {
+        let tctx = TransactionContext { block, inspector: None, tx };
         Machine::execute_transaction(&mut self.0, &tctx)
     }
+
+    // Executes a transaction and commits it, so that the next one starts with every account and slot cold
+    pub fn transact(&mut self, block: Block, tx: Transaction) -> ExecutionResult {
+        let result = self.run(block, tx);
+        self.0.commit();
+        result
+    }
+
+    // Executes and commits a transaction like transact, calling the hooks of `inspector` along the way
+    pub fn inspect(&mut self, block: Block, tx: Transaction, inspector: Rc<RefCell<dyn Inspector>>) -> ExecutionResult {
+        let tctx = TransactionContext { block, inspector: Some(inspector), tx };
+        let result = Machine::execute_transaction(&mut self.0, &tctx);
+        self.0.commit();
+        result
+    }
+
+    // Executes a transaction against a copy of the world state, which is left untouched
+    pub fn call(&self, block: Block, tx: Transaction) -> ExecutionResult {
+        let tctx = TransactionContext { block, inspector: None, tx };
+        Machine::execute_transaction(&mut self.0.clone(), &tctx)
+    }
+
+    // Executes the transactions of a block, on `threads` threads if there are more than one
+    pub fn transact_block(&mut self, block: &Block, txs: &[Transaction], threads: usize) -> Vec<ExecutionResult> {
+        match threads {
+            0 | 1 => Machine::execute_block(&mut self.0, block, txs),
+            _ => Machine::execute_block_parallel(&mut self.0, block, txs, threads),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use ethnum::uint;
+    use k256::ecdsa::SigningKey;
+    use crate::blockchain::backend::StateBackendMut;
+    use crate::blockchain::bytecode::Bytecode;
     use crate::blockchain::errors::Error;
+    use crate::blockchain::primitives::Authorization;
     use crate::blockchain::storage::StorageValue;
     use crate::machine::ExecutionOutput;
     use super::*;
@@ -63,12 +149,93 @@ mod tests {
         }
     }
 
+    #[test]
+    fn builds_calls_and_transacts() {
+        let mut evm = Evm::builder()
+            .account(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })
+            .account(Address(uint!("0xC0")), Account { balance: uint!("0"), code: vec![0x5F, 0x54, 0x60, 0x01, 0x01, 0x5F, 0x55, 0x00].into(), nonce: 1 }) // PUSH0 SLOAD PUSH1 1 ADD PUSH0 SSTORE STOP
+            .storage(Address(uint!("0xC0")), uint!("0"), uint!("41"))
+            .chain_id(uint!("1"))
+            .build();
+        let tx = Transaction {
+            authorization_list: vec![],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 50000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0xC0")),
+            value: uint!("0"),
+        };
+        let counter = |evm: &Evm| evm.state().storage.get(&Address(uint!("0xC0"))).unwrap().0.get(&uint!("0")).unwrap().value;
+
+        // 50000 - 21000 (intrinsic) - 2 - 2100 (cold SLOAD) - 3 - 3 - 2 - 2900 (SSTORE) = 23990
+        assert_eq!(evm.call(Block::default(), tx.clone()), Ok(ExecutionOutput { data: vec![], remaining_gas: 23990, revert: false }));
+        assert_eq!(counter(&evm), uint!("41"));
+        assert_eq!(evm.state().chain_id, uint!("1"));
+
+        // Every transaction starts with the slot cold and clean
+        assert_eq!(evm.transact(Block::default(), tx.clone()), Ok(ExecutionOutput { data: vec![], remaining_gas: 23990, revert: false }));
+        assert_eq!(evm.transact(Block::default(), Transaction { nonce: 1, ..tx.clone() }), Ok(ExecutionOutput { data: vec![], remaining_gas: 23990, revert: false }));
+        assert_eq!(counter(&evm), uint!("43"));
+
+        // Replaying a transaction fails the nonce check
+        assert_eq!(evm.transact(Block::default(), tx.clone()), Err(Error::InvalidNonce(2)));
+        assert_eq!(counter(&evm), uint!("43"));
+
+        let txs: Vec<_> = (2..5).map(|nonce| Transaction { nonce, ..tx.clone() }).collect();
+        assert!(evm.transact_block(&Block::default(), &txs, 2).iter().all(Result::is_ok));
+        assert_eq!(counter(&evm), uint!("46"));
+        assert_eq!(evm.state().accounts.0.get(&Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).unwrap().value.balance, uint!("30000000") - uint!("26010") * 5 * 50);
+    }
+
+    #[test]
+    fn forks_a_backend() {
+        let base = Evm::builder()
+            .account(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })
+            .account(Address(uint!("0xC0")), Account { balance: uint!("0"), code: vec![0x5F, 0x54, 0x60, 0x01, 0x01, 0x5F, 0x55, 0x00].into(), nonce: 1 })
+            .storage(Address(uint!("0xC0")), uint!("0"), uint!("41"))
+            .build();
+        let backend = Arc::new(base.0);
+        let code = Bytecode::from(vec![0x5F, 0x54, 0x60, 0x01, 0x01, 0x5F, 0x55, 0x00]);
+        assert_eq!(backend.code_by_hash(code.hash()), Ok(code));
+        let mut evm = Evm::builder().backend(backend.clone()).build();
+        let tx = Transaction {
+            authorization_list: vec![],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 50000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0xC0")),
+            value: uint!("0"),
+        };
+
+        assert_eq!(evm.transact(Block::default(), tx.clone()), Ok(ExecutionOutput { data: vec![], remaining_gas: 23990, revert: false }));
+        assert_eq!(StateBackend::storage(&evm.0, Address(uint!("0xC0")), uint!("0")).unwrap(), uint!("42"));
+        assert_eq!(StateBackend::storage(backend.as_ref(), Address(uint!("0xC0")), uint!("0")).unwrap(), uint!("41"));
+        assert_eq!(backend.accounts.0.get(&Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).unwrap().value.nonce, 0);
+
+        let changes = evm.take_changes();
+        assert_eq!(changes.storage, HashMap::from([(Address(uint!("0xC0")), HashMap::from([(uint!("0"), uint!("42"))]))]));
+        assert_eq!(changes.accounts.keys().copied().collect::<Vec<_>>(), [Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))]);
+
+        let mut base = (*backend).clone();
+        base.apply(changes).unwrap();
+        assert_eq!(StateBackend::storage(&base, Address(uint!("0xC0")), uint!("0")).unwrap(), uint!("42"));
+
+        evm.transact(Block::default(), Transaction { nonce: 1, ..tx }).unwrap();
+        evm.discard_changes();
+        assert_eq!(StateBackend::storage(&evm.0, Address(uint!("0xC0")), uint!("0")).unwrap(), uint!("42"));
+    }
+
     #[test]
     fn simple_add() {
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![0x60, 0x42, 0x60, 0xFF, 0x01], // PUSH1 0x42 PUSH1 0xFF ADD
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 53130,
@@ -82,11 +249,12 @@ mod tests {
     #[test]
     fn return_simple_add() {
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         // 0x42 + 0xFF = 321
         // 256 + 65 = 321
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![0x60, 0x42, 0x60, 0xFF, 0x01, 0x5F, 0x52, 0x60, 0x20, 0x5F, 0xF3], // PUSH1 0x42 PUSH1 0xFF ADD PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 59626,
@@ -100,9 +268,10 @@ mod tests {
     #[test]
     fn intrisic_gas_too_low() {
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![0x60, 0x42, 0x60, 0xFF, 0x01], // PUSH1 0x42 PUSH1 0xFF ADD
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 21000,
@@ -118,6 +287,7 @@ mod tests {
         let mut evm = Evm::default();
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![0x60, 0x42, 0x60, 0xFF, 0x01], // PUSH1 0x42 PUSH1 0xFF ADD
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 54000,
@@ -131,9 +301,10 @@ mod tests {
     #[test]
     fn out_of_gas() {
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![0x60, 0x42, 0x60, 0xFF, 0x01], // PUSH1 0x42 PUSH1 0xFF ADD
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 53082,
@@ -144,6 +315,71 @@ mod tests {
         }), Err(Error::OutOfGas));
     }
 
+    #[test]
+    fn out_of_gas_within_a_block() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
+
+        // The block's static gas is affordable but not the memory expansion, which must not fail before POP does
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: vec![0x60, 0x42, 0x5F, 0x52, 0x50], // PUSH1 0x42 PUSH0 MSTORE POP
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 53091,
+            nonce: 0,
+            gas_price: 50,
+            to: Address::default(),
+            value: uint!("0"),
+        }), Err(Error::EmptyStack));
+        assert_eq!(evm.0.accounts.load(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).value.balance, uint!("27345450"));
+    }
+
+    #[test]
+    fn gas_within_a_block() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
+
+        // 60000 - 53208 (intrinsic) - 11 - 2 = 6779
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x50, 0x5A, 0x5F, 0x52, 0x60, 0x20, 0x5F, 0xF3], // PUSH1 1 PUSH1 2 ADD POP GAS PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 60000,
+            nonce: 0,
+            gas_price: 50,
+            to: Address::default(),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: uint!("6779").to_be_bytes().to_vec(), remaining_gas: 366, revert: false }));
+    }
+
+    #[test]
+    fn point_evaluation_precompile() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
+
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: hex::decode("01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff000000001522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e98f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7a62ad71d14c5719385c0686f1871430475bf3a00f0aa3f7b8dd99a9abc2160744faf0070725e00b60ad9a026a15b1a8c").unwrap(), // versioned_hash | z | y | commitment | proof
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 74000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0x0A")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: hex::decode("000000000000000000000000000000000000000000000000000000000000100073eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001").unwrap(), remaining_gas: 12, revert: false }));
+
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: hex::decode("01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff000000001522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e88f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7a62ad71d14c5719385c0686f1871430475bf3a00f0aa3f7b8dd99a9abc2160744faf0070725e00b60ad9a026a15b1a8c").unwrap(), // the proof does not match y
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 74000,
+            gas_price: 50,
+            nonce: 1,
+            to: Address(uint!("0x0A")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: vec![], remaining_gas: 0, revert: true }));
+    }
+
     #[test]
     fn minimal_payable() {
         /*
@@ -154,9 +390,10 @@ mod tests {
          * }
          */
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![/* begin init code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x60, 0x3e, 0x80, 0x60, 0x0f, 0x5f, 0x39, 0x5f, 0xf3, 0xfe, /* end init code - begin runtime code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0x8b, 0xed, 0xd2, 0xa9, 0xf3, 0x84, 0x28, 0xfa, 0xa2, 0x5c, 0x83, 0xb9, 0x72, 0xe1, 0x98, 0xde, 0x6d, 0x27, 0xb2, 0xe5, 0x4f, 0x67, 0x72, 0xfc, 0x3b, 0x30, 0x34, 0x5c, 0x11, 0x20, 0x3d, 0x47, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33 /* end runtime code */],
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 66708,
@@ -177,9 +414,10 @@ mod tests {
          * }
          */
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![/* begin init code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x34, 0x80, 0x15, 0x60, 0x0e, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x60, 0x3e, 0x80, 0x60, 0x1a, 0x5f, 0x39, 0x5f, 0xf3, 0xfe, /* end init code - begin runtime code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0xb2, 0xff, 0x2a, 0x7f, 0x02, 0x82, 0x1b, 0x6b, 0xd9, 0xd0, 0x4d, 0x01, 0x4b, 0x86, 0x15, 0x65, 0x7f, 0x21, 0xda, 0xac, 0x71, 0xc6, 0x47, 0x5d, 0xcf, 0xb1, 0x97, 0xec, 0x74, 0x3d, 0x0a, 0xfd, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33 /* end runtime code */],
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 66884,
@@ -190,11 +428,12 @@ mod tests {
         }), Ok(ExecutionOutput { data: vec![0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0xb2, 0xff, 0x2a, 0x7f, 0x02, 0x82, 0x1b, 0x6b, 0xd9, 0xd0, 0x4d, 0x01, 0x4b, 0x86, 0x15, 0x65, 0x7f, 0x21, 0xda, 0xac, 0x71, 0xc6, 0x47, 0x5d, 0xcf, 0xb1, 0x97, 0xec, 0x74, 0x3d, 0x0a, 0xfd, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33], remaining_gas: 36, revert: false })); // `data` contains the runtime code
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![/* begin init code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x34, 0x80, 0x15, 0x60, 0x0e, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x60, 0x3e, 0x80, 0x60, 0x1a, 0x5f, 0x39, 0x5f, 0xf3, 0xfe, /* end init code - begin runtime code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0xb2, 0xff, 0x2a, 0x7f, 0x02, 0x82, 0x1b, 0x6b, 0xd9, 0xd0, 0x4d, 0x01, 0x4b, 0x86, 0x15, 0x65, 0x7f, 0x21, 0xda, 0xac, 0x71, 0xc6, 0x47, 0x5d, 0xcf, 0xb1, 0x97, 0xec, 0x74, 0x3d, 0x0a, 0xfd, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33 /* end runtime code */],
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas_price: 50,
             gas: 54484,
-            nonce: 0,
+            nonce: 1,
             to: Address::default(),
             value: uint!("1"), // we pay a non payable contract
         }), Ok(ExecutionOutput { data: vec![], remaining_gas: 57, revert: true })); // `data` is empty and the execution is reverted
@@ -213,9 +452,10 @@ mod tests {
          * }
          */
         let mut evm = Evm::default();
-        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] })]);
+        evm.with_accounts(&[(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 })]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![/* begin init code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x60, 0x40, 0x51, 0x60, 0xcd, 0x38, 0x03, 0x80, 0x60, 0xcd, 0x83, 0x39, 0x81, 0x81, 0x01, 0x60, 0x40, 0x52, 0x81, 0x01, 0x90, 0x60, 0x21, 0x91, 0x90, 0x60, 0x5e, 0x56, 0x5b, 0x80, 0x5f, 0x81, 0x90, 0x55, 0x50, 0x50, 0x60, 0x84, 0x56, 0x5b, 0x5f, 0x5f, 0xfd, 0x5b, 0x5f, 0x81, 0x90, 0x50, 0x91, 0x90, 0x50, 0x56, 0x5b, 0x60, 0x40, 0x81, 0x60, 0x30, 0x56, 0x5b, 0x81, 0x14, 0x60, 0x49, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x56, 0x5b, 0x5f, 0x81, 0x51, 0x90, 0x50, 0x60, 0x58, 0x81, 0x60, 0x39, 0x56, 0x5b, 0x92, 0x91, 0x50, 0x50, 0x56, 0x5b, 0x5f, 0x60, 0x20, 0x82, 0x84, 0x03, 0x12, 0x15, 0x60, 0x70, 0x57, 0x60, 0x6f, 0x60, 0x2c, 0x56, 0x5b, 0x5b, 0x5f, 0x60, 0x7b, 0x84, 0x82, 0x85, 0x01, 0x60, 0x4c, 0x56, 0x5b, 0x91, 0x50, 0x50, 0x92, 0x91, 0x50, 0x50, 0x56, 0x5b, 0x60, 0x3e, 0x80, 0x60, 0x8f, 0x5f, 0x39, 0x5f, 0xf3, 0xfe, /* end init code - begin runtime code */ 0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0x9a, 0xe1, 0xab, 0x8f, 0x3e, 0x0b, 0xe0, 0xe3, 0x7d, 0xe3, 0x35, 0xff, 0x4d, 0xed, 0x04, 0x6c, 0xf7, 0x7c, 0xe4, 0x5f, 0xd8, 0xb7, 0xfd, 0x61, 0x4f, 0x6a, 0x28, 0x4d, 0x5e, 0x41, 0xd3, 0xf1, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33, /* end runtime code - begin constructor arguments */ 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5 /* end constructor arguments */],
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 138796,
@@ -234,7 +474,7 @@ mod tests {
         );
         assert_eq!(
             evm.0.accounts.0.get(&Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044"))).unwrap().value,
-            Account { balance: uint!("10"), code: vec![0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0x9a, 0xe1, 0xab, 0x8f, 0x3e, 0x0b, 0xe0, 0xe3, 0x7d, 0xe3, 0x35, 0xff, 0x4d, 0xed, 0x04, 0x6c, 0xf7, 0x7c, 0xe4, 0x5f, 0xd8, 0xb7, 0xfd, 0x61, 0x4f, 0x6a, 0x28, 0x4d, 0x5e, 0x41, 0xd3, 0xf1, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33] },
+            Account { balance: uint!("10"), code: vec![0x60, 0x80, 0x60, 0x40, 0x52, 0x5f, 0x5f, 0xfd, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0x9a, 0xe1, 0xab, 0x8f, 0x3e, 0x0b, 0xe0, 0xe3, 0x7d, 0xe3, 0x35, 0xff, 0x4d, 0xed, 0x04, 0x6c, 0xf7, 0x7c, 0xe4, 0x5f, 0xd8, 0xb7, 0xfd, 0x61, 0x4f, 0x6a, 0x28, 0x4d, 0x5e, 0x41, 0xd3, 0xf1, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x00, 0x08, 0x1c, 0x00, 0x33].into(), nonce: 1 },
         );
     }
 
@@ -251,11 +491,12 @@ mod tests {
         let mut evm = Evm::default();
 
         evm.with_accounts(&[
-            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] }),
-            (Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), Account { balance: 0u32.into(), code: vec![0x60, 0x80, 0x60, 0x40, 0x52, 0x34, 0x80, 0x15, 0x60, 0xe, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x60, 0x4, 0x36, 0x10, 0x60, 0x26, 0x57, 0x5f, 0x35, 0x60, 0xe0, 0x1c, 0x80, 0x63, 0x40, 0x18, 0xd9, 0xaa, 0x14, 0x60, 0x2a, 0x57, 0x5b, 0x5f, 0x5f, 0xfd, 0x5b, 0x60, 0x40, 0x60, 0x4, 0x80, 0x36, 0x3, 0x81, 0x1, 0x90, 0x60, 0x3c, 0x91, 0x90, 0x60, 0x7d, 0x56, 0x5b, 0x60, 0x42, 0x56, 0x5b, 0x0, 0x5b, 0x80, 0x5f, 0x81, 0x90, 0x55, 0x50, 0x50, 0x56, 0x5b, 0x5f, 0x5f, 0xfd, 0x5b, 0x5f, 0x81, 0x90, 0x50, 0x91, 0x90, 0x50, 0x56, 0x5b, 0x60, 0x5f, 0x81, 0x60, 0x4f, 0x56, 0x5b, 0x81, 0x14, 0x60, 0x68, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x56, 0x5b, 0x5f, 0x81, 0x35, 0x90, 0x50, 0x60, 0x77, 0x81, 0x60, 0x58, 0x56, 0x5b, 0x92, 0x91, 0x50, 0x50, 0x56, 0x5b, 0x5f, 0x60, 0x20, 0x82, 0x84, 0x3, 0x12, 0x15, 0x60, 0x8f, 0x57, 0x60, 0x8e, 0x60, 0x4b, 0x56, 0x5b, 0x5b, 0x5f, 0x60, 0x9a, 0x84, 0x82, 0x85, 0x1, 0x60, 0x6b, 0x56, 0x5b, 0x91, 0x50, 0x50, 0x92, 0x91, 0x50, 0x50, 0x56, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0x62, 0x58, 0x4a, 0x4b, 0x66, 0x87, 0xdb, 0x1d, 0xe8, 0x3d, 0xa4, 0xe2, 0xfc, 0xd8, 0x21, 0x6b, 0xd7, 0x7e, 0x9a, 0xe0, 0x6, 0x44, 0x89, 0xf2, 0x3, 0x80, 0xcf, 0xc6, 0x53, 0x20, 0x3, 0x85, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x0, 0x8, 0x1c, 0x0, 0x33] }) // `code` contains the runtime code
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), Account { balance: 0u32.into(), code: vec![0x60, 0x80, 0x60, 0x40, 0x52, 0x34, 0x80, 0x15, 0x60, 0xe, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x60, 0x4, 0x36, 0x10, 0x60, 0x26, 0x57, 0x5f, 0x35, 0x60, 0xe0, 0x1c, 0x80, 0x63, 0x40, 0x18, 0xd9, 0xaa, 0x14, 0x60, 0x2a, 0x57, 0x5b, 0x5f, 0x5f, 0xfd, 0x5b, 0x60, 0x40, 0x60, 0x4, 0x80, 0x36, 0x3, 0x81, 0x1, 0x90, 0x60, 0x3c, 0x91, 0x90, 0x60, 0x7d, 0x56, 0x5b, 0x60, 0x42, 0x56, 0x5b, 0x0, 0x5b, 0x80, 0x5f, 0x81, 0x90, 0x55, 0x50, 0x50, 0x56, 0x5b, 0x5f, 0x5f, 0xfd, 0x5b, 0x5f, 0x81, 0x90, 0x50, 0x91, 0x90, 0x50, 0x56, 0x5b, 0x60, 0x5f, 0x81, 0x60, 0x4f, 0x56, 0x5b, 0x81, 0x14, 0x60, 0x68, 0x57, 0x5f, 0x5f, 0xfd, 0x5b, 0x50, 0x56, 0x5b, 0x5f, 0x81, 0x35, 0x90, 0x50, 0x60, 0x77, 0x81, 0x60, 0x58, 0x56, 0x5b, 0x92, 0x91, 0x50, 0x50, 0x56, 0x5b, 0x5f, 0x60, 0x20, 0x82, 0x84, 0x3, 0x12, 0x15, 0x60, 0x8f, 0x57, 0x60, 0x8e, 0x60, 0x4b, 0x56, 0x5b, 0x5b, 0x5f, 0x60, 0x9a, 0x84, 0x82, 0x85, 0x1, 0x60, 0x6b, 0x56, 0x5b, 0x91, 0x50, 0x50, 0x92, 0x91, 0x50, 0x50, 0x56, 0xfe, 0xa2, 0x64, 0x69, 0x70, 0x66, 0x73, 0x58, 0x22, 0x12, 0x20, 0x62, 0x58, 0x4a, 0x4b, 0x66, 0x87, 0xdb, 0x1d, 0xe8, 0x3d, 0xa4, 0xe2, 0xfc, 0xd8, 0x21, 0x6b, 0xd7, 0x7e, 0x9a, 0xe0, 0x6, 0x44, 0x89, 0xf2, 0x3, 0x80, 0xcf, 0xc6, 0x53, 0x20, 0x3, 0x85, 0x64, 0x73, 0x6f, 0x6c, 0x63, 0x43, 0x0, 0x8, 0x1c, 0x0, 0x33].into(), nonce: 0 }) // `code` contains the runtime code
         ]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![/* begin function selector */ 0x40, 0x18, 0xd9, 0xaa, /* end function selector - begin function arguments */ 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a /* end function arguments */],
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 138796,
@@ -283,12 +524,13 @@ mod tests {
         let mut evm = Evm::default();
 
         evm.with_accounts(&[
-            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: vec![] }),
-            (Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), Account { balance: 0u32.into(), code: hex::decode("6080604052348015600e575f5ffd5b50600436106026575f3560e01c80630c55699c14602a575b5f5ffd5b60306044565b604051603b9190605f565b60405180910390f35b5f5481565b5f819050919050565b6059816049565b82525050565b5f60208201905060705f8301846052565b9291505056fea2646970667358221220171f35b11c38603a11e2912f5d707b49fcc171fd56709fceac65c92f674d6e0f64736f6c634300081c0033").unwrap() }) // `code` contains the runtime code
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), Account { balance: 0u32.into(), code: hex::decode("6080604052348015600e575f5ffd5b50600436106026575f3560e01c80630c55699c14602a575b5f5ffd5b60306044565b604051603b9190605f565b60405180910390f35b5f5481565b5f819050919050565b6059816049565b82525050565b5f60208201905060705f8301846052565b9291505056fea2646970667358221220171f35b11c38603a11e2912f5d707b49fcc171fd56709fceac65c92f674d6e0f64736f6c634300081c0033").unwrap().into(), nonce: 0 }) // `code` contains the runtime code
         ]);
         evm.with_storage(&[(Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), (uint!("0"), uint!("0x0F")))]);
 
         assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
             data: vec![/* begin function selector */ 0x0c, 0x55, 0x69, 0x9c /* end function selector */],
             from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
             gas: 138796,
@@ -298,4 +540,192 @@ mod tests {
             value: uint!("0"),
         }), Ok(ExecutionOutput { data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0F], remaining_gas: 115330, revert: false }));
     }
+
+    #[test]
+    fn delegated_eoa() {
+        let key = SigningKey::from_slice(&uint!("1").to_be_bytes()).unwrap();
+        let authority = Address(uint!("0x7E5F4552091A69125D5DFCB7B8C2659029395BDF"));
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+            (authority, Account { balance: uint!("1"), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), Account { balance: 0u32.into(), code: vec![0x30, 0x5F, 0x52, 0x60, 0x20, 0x5F, 0xF3].into(), nonce: 1 }), // ADDRESS PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+        ]);
+
+        let authorization = Authorization {
+            address: Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")),
+            chain_id: uint!("0"),
+            nonce: 0,
+            ..Default::default()
+        }.signed(&key);
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![authorization.clone()],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 50000,
+            gas_price: 50,
+            nonce: 0,
+            to: authority,
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: authority.0.to_be_bytes().to_vec(), remaining_gas: 11108, revert: false })); // 48615 gas used, including 2600 to access the cold delegate, 9723 refunded
+        assert_eq!(evm.0.refund, 12500); // the counter, before it gets capped to a fifth of the gas used
+        assert_eq!(evm.0.accounts.0.get(&authority).unwrap().value, Account {
+            balance: uint!("1"),
+            code: hex::decode("EF0100DBCD4009C9B9D36CC85256A8377A034C24CE0044").unwrap().into(),
+            nonce: 1,
+        });
+        assert_eq!(
+            evm.0.accounts.0.get(&Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).unwrap().value.balance,
+            uint!("30000000") - uint!("50") * uint!("38892"),
+        );
+
+        // Replaying the authorization fails the nonce check, the existing delegation is kept and nothing is refunded
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![authorization],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 50000,
+            gas_price: 50,
+            nonce: 1,
+            to: authority,
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: authority.0.to_be_bytes().to_vec(), remaining_gas: 3885, revert: false }));
+        assert_eq!(evm.0.accounts.0.get(&authority).unwrap().value.nonce, 1);
+    }
+
+    #[test]
+    fn call_to_delegated_eoa() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xA0")), Account { balance: uint!("100000"), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0xC0")), Account { balance: uint!("0"), code: hex::decode("5F5F5F5F5F60AA5AF100").unwrap().into(), nonce: 1 }), // CALL 0xAA
+            (Address(uint!("0xAA")), Account { balance: uint!("0"), code: hex::decode("EF010000000000000000000000000000000000000000DD").unwrap().into(), nonce: 1 }),
+            (Address(uint!("0xDD")), Account { balance: uint!("0"), code: vec![0x00].into(), nonce: 1 }), // STOP
+        ]);
+
+        // 15 gas for the pushes, 2600 to access 0xAA and 2600 more for its cold delegate
+        assert_eq!(evm.run(Block::default(), Transaction {
+            from: Address(uint!("0xA0")),
+            gas: 100000,
+            gas_price: 1,
+            to: Address(uint!("0xC0")),
+            ..Default::default()
+        }), Ok(ExecutionOutput { data: vec![], remaining_gas: 100000 - 21000 - 15 - 2600 - 2600, revert: false }));
+    }
+
+    #[test]
+    fn eof_extcall() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0x1000000000000000000000000000000000000000")), Account { balance: 0u32.into(), code: hex::decode("ef00010100040200010022ff000000008000045f5f5f73dbcd4009c9b9d36cc85256a8377a034c24ce0044f8505ff75f5260205ff3").unwrap().into(), nonce: 1 }), // EXTCALL the callee and return RETURNDATALOAD(0)
+            (Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044")), Account { balance: 0u32.into(), code: hex::decode("ef00010100040200010008ff00000000800002602a5f5260205ff3").unwrap().into(), nonce: 1 }), // return 42
+        ]);
+
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 50000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0x1000000000000000000000000000000000000000")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: uint!("42").to_be_bytes().to_vec(), remaining_gas: 26355, revert: false }));
+    }
+
+    #[test]
+    fn eof_contract_creation() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+        ]);
+
+        // The initcode RETURNCONTRACTs its runtime subcontainer, which returns 42
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: hex::decode("ef000101000402000100040300010000001bff000000008000025f5fee00ef00010100040200010008ff00000000800002602a5f5260205ff3").unwrap(),
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 100000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0x0")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: hex::decode("ef00010100040200010008ff00000000800002602a5f5260205ff3").unwrap(), remaining_gas: 40960, revert: false }));
+        let address = Address(uint!("0xDBCD4009C9B9D36CC85256A8377A034C24CE0044"));
+        assert_eq!(evm.0.accounts.0.get(&address).unwrap().value.nonce, 1);
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 30000,
+            gas_price: 50,
+            nonce: 1,
+            to: address,
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: uint!("42").to_be_bytes().to_vec(), remaining_gas: 8984, revert: false }));
+    }
+
+    #[test]
+    fn eof_factory() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0x1000000000000000000000000000000000000000")), Account { balance: 0u32.into(), code: hex::decode("ef0001010004020001000c03000100000039ff000000008000045f5f5f5fec005f5260205ff3ef000101000402000100040300010000001bff000000008000025f5fee00ef00010100040200010008ff00000000800002602a5f5260205ff3").unwrap().into(), nonce: 1 }), // EOFCREATE the initcode and return the address
+        ]);
+
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: vec![],
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 100000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0x1000000000000000000000000000000000000000")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: uint!("0x5D5FD8A1ACB9D4343235B9974415225B7F73EAE7").to_be_bytes().to_vec(), remaining_gas: 41563, revert: false }));
+        assert_eq!(evm.0.accounts.0.get(&Address(uint!("0x5D5FD8A1ACB9D4343235B9974415225B7F73EAE7"))).unwrap().value, Account {
+            balance: uint!("0"),
+            code: hex::decode("ef00010100040200010008ff00000000800002602a5f5260205ff3").unwrap().into(),
+            nonce: 1,
+        });
+    }
+
+    #[test]
+    fn invalid_eof_initcode() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+        ]);
+
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: hex::decode("ef0001010004020001000100ff00000000800000fe").unwrap(), // missing data section header
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 100000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0x0")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: vec![], remaining_gas: 0, revert: true }));
+    }
+
+    #[test]
+    fn legacy_creation_of_ef_code() {
+        let mut evm = Evm::default();
+        evm.with_accounts(&[
+            (Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")), Account { balance: 30000000u32.into(), code: Bytecode::default(), nonce: 0 }),
+        ]);
+
+        assert_eq!(evm.run(Block::default(), Transaction {
+            authorization_list: vec![],
+            data: hex::decode("60EF5F5360015FF3").unwrap(), // PUSH1 0xEF PUSH0 MSTORE8 PUSH1 0x01 PUSH0 RETURN
+            from: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            gas: 100000,
+            gas_price: 50,
+            nonce: 0,
+            to: Address(uint!("0x0")),
+            value: uint!("0"),
+        }), Ok(ExecutionOutput { data: vec![0xEF], remaining_gas: 0, revert: true }));
+    }
 }
@@ -0,0 +1,29 @@
+pub(crate) mod blockchain;
+pub(crate) mod evm;
+pub(crate) mod machine;
+mod utils;
+
+pub use ethnum::u256;
+pub use blockchain::{AccessLog, JournalEntry, StateKey, WorldState};
+pub use blockchain::backend::{AccountInfo, BackendError, ChangeSet, StateBackend, StateBackendMut};
+pub use blockchain::bytecode::Bytecode;
+pub use blockchain::errors::Error;
+pub use blockchain::primitives::{Account, Address, Authorization, Block, Transaction};
+pub use blockchain::storage::{Storage, StorageValue};
+pub use evm::{Evm, EvmBuilder};
+pub use machine::{ExecutionOutput, ExecutionResult};
+pub use machine::context::{CallContext, CallContextContract, Halt, Log, MAX_CALL_DEPTH, TransactionContext};
+pub use machine::debugger::{Breakpoint, Debugger};
+pub use machine::eof::{CodeType, Container, ContainerKind};
+pub use machine::inspector::Inspector;
+pub use machine::instructions::{InstructionOutput, InstructionResult};
+pub use machine::memory::{Memory, ReadWriteOperation};
+pub use machine::opcode::{Instruction, OpCode};
+pub use machine::precompiles::kzg::load_trusted_setup_file;
+pub use machine::stack::{Stack, STACK_LIMIT};
+pub use machine::tracers::call::{CallFrame, CallTracer};
+pub use machine::tracers::eip3155::Eip3155Tracer;
+pub use machine::tracers::flamegraph::FlamegraphTracer;
+pub use machine::tracers::prestate::{AccountState, PrestateTracer, StateTrace};
+pub use machine::tracers::profiler::{GasProfiler, GasStats};
+pub use machine::transient::Transient;
@@ -1,10 +1,29 @@
 use ethnum::u256;
+use std::cell::RefCell;
+use std::rc::Rc;
 use crate::blockchain::WorldState;
+use crate::blockchain::bytecode::Bytecode;
+use crate::blockchain::errors::Error;
 use crate::blockchain::primitives::{Address, Block, Transaction};
+use crate::machine::eof::Container;
+use crate::machine::inspector::Inspector;
 use crate::machine::memory::Memory;
+use crate::machine::opcode::OpCode;
 use crate::machine::stack::Stack;
 use crate::machine::transient::Transient;
 
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+// Why a context reverted. Only REVERT leaves the remaining gas to the caller
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Halt {
+    InvalidCode, // EIP-3541 code starting with 0xEF
+    InvalidInitcode, // EOF initcode that does not validate
+    InvalidOpcode(OpCode),
+    PrecompileFailure,
+    Revert,
+}
+
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
 pub struct Log {
     pub data: Vec<u8>,
@@ -15,7 +34,8 @@ pub struct Log {
 pub struct CallContextContract {
     pub address: Address,
     pub caller: Address,
-    pub code: Vec<u8>,
+    pub code: Bytecode,
+    pub eof: Option<Container>,
     pub gas: usize,
     pub input: Vec<u8>,
     pub logs: Vec<Log>,
@@ -25,45 +45,93 @@ pub struct CallContextContract {
 #[derive(Default)]
 pub struct TransactionContext {
     pub block: Block,
+    pub inspector: Option<Rc<RefCell<dyn Inspector>>>,
     pub tx: Transaction,
 }
 
+impl TransactionContext {
+    // Calls a hook of the inspector, if any
+    pub fn inspect(&self, hook: impl FnOnce(&mut dyn Inspector)) {
+        if let Some(inspector) = &self.inspector {
+            hook(&mut *inspector.borrow_mut());
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct CallContext {
     pub contract: CallContextContract,
+    pub depth: usize,
+    // Set along with `revert`
+    pub halt: Option<Halt>,
     pub memory: Memory,
     pub pc: usize,
+    pub prepaid_gas: usize,
     pub r#return: Vec<u8>,
+    pub return_stack: Vec<usize>,
     pub returndata: Vec<u8>,
     pub revert: bool,
     pub stack: Stack,
+    pub r#static: bool,
     pub stop: bool,
     pub transient: Transient,
 }
 
 impl CallContext {
-    pub fn from_transaction(s: &mut WorldState, tx: &Transaction) -> Self {
-        let contract_address = tx.contract_address();
-        let contract_input = &tx.data;
-        let contract = CallContextContract {
-            address: contract_address,
-            caller: tx.from,
-            code: if tx.is_contract_creation() { contract_input.clone() } else { s.accounts.load(contract_address).value.code },
-            gas: tx.gas,
-            input: contract_input.clone(),
-            logs: Vec::default(),
-            value: tx.value,
-        };
+    pub fn new(contract: CallContextContract) -> Self {
         Self {
+            pc: contract.eof.as_ref().map_or(0, |eof| eof.code_sections[0].start),
             contract,
+            depth: 0,
+            halt: None,
             memory: Memory::new(),
-            pc: 0,
+            prepaid_gas: 0,
             r#return: Vec::default(),
+            return_stack: Vec::default(),
             returndata: Vec::default(),
             revert: false,
             stack: Stack::new(),
+            r#static: false,
             stop: false,
             transient: Transient::new(),
         }
     }
+
+    pub fn opcode(&self) -> OpCode {
+        OpCode(self.contract.code.padded()[self.pc])
+    }
+
+    // Gas left, including the gas prepaid for the rest of the current basic block
+    pub fn available_gas(&self) -> usize {
+        self.contract.gas + self.prepaid_gas
+    }
+
+    // Also returns the cost of accessing the account that the destination delegates to, if any
+    pub fn from_transaction(s: &mut WorldState, tx: &Transaction) -> Result<(Self, usize), Error> {
+        let contract_address = tx.contract_address();
+        let (code, input, eof, delegation_cost) = match (tx.is_contract_creation(), Container::is_eof(&tx.data)) {
+            // EOF initcode is followed by its calldata, and leaves the code empty when invalid
+            (true, true) => match Container::validate_initcode(&tx.data) {
+                Ok(eof) => (Bytecode::uncached(&tx.data[..eof.size()]), tx.data[eof.size()..].to_vec(), Some(eof), 0),
+                Err(_) => (Bytecode::default(), vec![], None, 0),
+            },
+            (true, false) => (Bytecode::uncached(&tx.data), tx.data.clone(), None, 0),
+            (false, _) => {
+                let (code, delegation_cost) = s.load_code(contract_address)?;
+                let eof = Container::from_code(&code);
+                (code, tx.data.clone(), eof, delegation_cost)
+            },
+        };
+        let cctx = CallContext::new(CallContextContract {
+            address: contract_address,
+            caller: tx.from,
+            code,
+            eof,
+            gas: tx.gas,
+            input,
+            logs: Vec::default(),
+            value: tx.value,
+        });
+        Ok((cctx, delegation_cost))
+    }
 }
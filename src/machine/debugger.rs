@@ -0,0 +1,325 @@
+use ethnum::u256;
+use std::io::{self, BufRead, StdinLock, Stdout, Write};
+
+use crate::blockchain::WorldState;
+use crate::blockchain::errors::Error;
+use crate::machine::context::CallContext;
+use crate::machine::inspector::Inspector;
+use crate::machine::opcode::OpCode;
+
+// Instructions shown before and after the current one
+const DISASSEMBLY_CONTEXT: usize = 4;
+
+const HELP: &str = "\
+s, step                 run the instruction and pause at the next one
+n, next                 same, but run calls made by the instruction without pausing
+o, out                  run until the current frame returns
+c, continue             run until a breakpoint
+b pc <pc>               pause at a program counter
+b op <name|opcode>      pause at an opcode
+b sstore [key]          pause before writing a slot, or any slot
+d <index>               delete a breakpoint
+i, info                 list the breakpoints
+p, print                show the current instruction again
+h, help                 show this help";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Breakpoint {
+    Opcode(OpCode),
+    Pc(usize),
+    // Any slot when None
+    Sstore(Option<u256>),
+}
+
+impl Breakpoint {
+    fn hits(&self, cctx: &CallContext) -> bool {
+        match self {
+            Breakpoint::Opcode(opcode) => cctx.opcode() == *opcode,
+            Breakpoint::Pc(pc) => cctx.pc == *pc,
+            Breakpoint::Sstore(key) => cctx.opcode().0 == 0x55 && key.is_none_or(|key| cctx.stack.items().last() == Some(&key)),
+        }
+    }
+
+    fn parse(kind: &str, argument: Option<&str>) -> Option<Self> {
+        match (kind, argument) {
+            ("pc", Some(pc)) => parse_number(pc)?.try_into().ok().map(Breakpoint::Pc),
+            ("op", Some(opcode)) => match parse_number(opcode) {
+                Some(opcode) => u8::try_from(opcode).ok().map(|opcode| Breakpoint::Opcode(OpCode(opcode))),
+                None => (0..=u8::MAX).map(OpCode).find(|op| op.to_string().eq_ignore_ascii_case(opcode)).map(Breakpoint::Opcode),
+            },
+            ("sstore", key) => match key {
+                Some(key) => parse_number(key).map(|key| Breakpoint::Sstore(Some(key))),
+                None => Some(Breakpoint::Sstore(None)),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Opcode(opcode) => write!(f, "op {}", opcode),
+            Breakpoint::Pc(pc) => write!(f, "pc {:#x}", pc),
+            Breakpoint::Sstore(Some(key)) => write!(f, "sstore {:#x}", key),
+            Breakpoint::Sstore(None) => write!(f, "sstore"),
+        }
+    }
+}
+
+// Numbers are decimal, or hexadecimal when prefixed with 0x
+fn parse_number(number: &str) -> Option<u256> {
+    match number.strip_prefix("0x") {
+        Some(hex) => u256::from_str_radix(hex, 16).ok(),
+        None => u256::from_str_radix(number, 10).ok(),
+    }
+}
+
+// When to pause next, besides breakpoints
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Resume {
+    Continue,
+    // At the next step at most this deep
+    Over(usize),
+    // At the next step less deep than this
+    Out(usize),
+    Step,
+}
+
+// Pauses before instructions to show the state of the current frame, and reads commands from `input` until told to
+// resume. Reaching the end of the input, or failing to write the output, lets the execution run to its end
+pub struct Debugger<R: BufRead, W: Write> {
+    pub breakpoints: Vec<Breakpoint>,
+    input: R,
+    output: W,
+    resume: Resume,
+}
+
+impl Debugger<StdinLock<'static>, Stdout> {
+    pub fn terminal() -> Self {
+        Debugger::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    // Pauses at the first instruction
+    pub fn new(input: R, output: W) -> Self {
+        Self { breakpoints: vec![], input, output, resume: Resume::Step }
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    fn detach(&mut self) {
+        self.breakpoints.clear();
+        self.resume = Resume::Continue;
+    }
+
+    fn show(&mut self, s: &WorldState, cctx: &CallContext) -> io::Result<()> {
+        let w = &mut self.output;
+        writeln!(w, "[depth {}] {:#042x} pc {:#x} gas {}", cctx.depth, cctx.contract.address.0, cctx.pc, cctx.available_gas())?;
+
+        let instructions = disassemble(cctx);
+        let current = instructions.iter().position(|(pc, _, _)| *pc >= cctx.pc).unwrap_or(instructions.len());
+        for (pc, opcode, immediate) in &instructions[current.saturating_sub(DISASSEMBLY_CONTEXT)..(current + DISASSEMBLY_CONTEXT + 1).min(instructions.len())] {
+            let marker = if *pc == cctx.pc { ">" } else { " " };
+            match immediate.is_empty() {
+                true => writeln!(w, "{} {:>6}  {}", marker, format!("{:#x}", pc), opcode)?,
+                false => writeln!(w, "{} {:>6}  {} 0x{}", marker, format!("{:#x}", pc), opcode, hex::encode(immediate))?,
+            }
+        }
+
+        writeln!(w, "stack ({} items, top first)", cctx.stack.len())?;
+        for (i, item) in cctx.stack.items().iter().rev().enumerate() {
+            writeln!(w, "  {:>4}  {:#x}", i, item)?;
+        }
+
+        writeln!(w, "memory ({} bytes)", cctx.memory.size())?;
+        for (i, row) in cctx.memory.data().chunks(32).enumerate() {
+            writeln!(w, "  {:>6}  {}", format!("{:#x}", i * 32), hex::encode(row))?;
+        }
+
+        // Slots of the current account that the transaction accessed
+        let mut slots: Vec<_> = s.storage.get(&cctx.contract.address).map_or(vec![], |storage| {
+            storage.0.iter().filter(|(_, slot)| slot.warm || slot.value != slot.original_value).collect()
+        });
+        slots.sort_by_key(|(key, _)| **key);
+        writeln!(w, "storage ({} touched slots)", slots.len())?;
+        for (key, slot) in slots {
+            writeln!(w, "  {:#x} => {:#x} (was {:#x})", key, slot.value, slot.original_value)?;
+        }
+
+        writeln!(w, "returndata 0x{}", hex::encode(&cctx.returndata))
+    }
+
+    // Reads commands until one resumes the execution
+    fn prompt(&mut self, s: &WorldState, cctx: &CallContext) -> io::Result<()> {
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                self.detach();
+                return Ok(());
+            }
+
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (None | Some("s" | "step"), _, _) => self.resume = Resume::Step,
+                (Some("n" | "next"), _, _) => self.resume = Resume::Over(cctx.depth),
+                (Some("o" | "out"), _, _) => self.resume = Resume::Out(cctx.depth),
+                (Some("c" | "continue"), _, _) => self.resume = Resume::Continue,
+                (Some("b" | "break"), Some(kind), argument) => {
+                    match Breakpoint::parse(kind, argument) {
+                        Some(breakpoint) => {
+                            self.breakpoints.push(breakpoint);
+                            writeln!(self.output, "breakpoint {}: {}", self.breakpoints.len() - 1, breakpoint)?;
+                        },
+                        None => writeln!(self.output, "invalid breakpoint")?,
+                    }
+                    continue;
+                },
+                (Some("d" | "delete"), Some(index), _) => {
+                    match index.parse::<usize>().ok().filter(|index| *index < self.breakpoints.len()) {
+                        Some(index) => { self.breakpoints.remove(index); },
+                        None => writeln!(self.output, "no breakpoint {}", index)?,
+                    }
+                    continue;
+                },
+                (Some("i" | "info"), _, _) => {
+                    for (i, breakpoint) in self.breakpoints.iter().enumerate() {
+                        writeln!(self.output, "breakpoint {}: {}", i, breakpoint)?;
+                    }
+                    continue;
+                },
+                (Some("p" | "print"), _, _) => {
+                    self.show(s, cctx)?;
+                    continue;
+                },
+                _ => {
+                    writeln!(self.output, "{}", HELP)?;
+                    continue;
+                },
+            }
+            return Ok(());
+        }
+    }
+}
+
+// Instructions of the code section that pc is in, with their immediates
+fn disassemble(cctx: &CallContext) -> Vec<(usize, OpCode, &[u8])> {
+    let code = cctx.contract.code.padded();
+    let eof = cctx.contract.eof.as_ref();
+    let section = eof.and_then(|eof| eof.code_sections.iter().find(|section| section.contains(&cctx.pc)).cloned())
+        .unwrap_or(0..cctx.contract.code.len());
+
+    let mut instructions = vec![];
+    let mut pc = section.start;
+    while pc < section.end {
+        let opcode = OpCode(code[pc]);
+        // Only PUSH has an immediate in legacy code
+        let size = match (eof, opcode.0) {
+            (Some(_), 0xE2) => opcode.immediate_size() + 2 * (code[pc + 1] as usize + 1),
+            (Some(_), _) | (None, 0x60..=0x7F) => opcode.immediate_size(),
+            (None, _) => 0,
+        };
+        instructions.push((pc, opcode, &code[pc + 1..(pc + 1 + size).min(code.len())]));
+        pc += 1 + size;
+    }
+    // Past the end of the code, which stops
+    if cctx.pc >= section.end {
+        instructions.push((cctx.pc, cctx.opcode(), &[][..]));
+    }
+    instructions
+}
+
+impl<R: BufRead, W: Write> Inspector for Debugger<R, W> {
+    fn step(&mut self, s: &WorldState, cctx: &CallContext) {
+        let pause = match self.resume {
+            Resume::Continue => false,
+            Resume::Over(depth) => cctx.depth <= depth,
+            Resume::Out(depth) => cctx.depth < depth,
+            Resume::Step => true,
+        };
+        if !pause && !self.breakpoints.iter().any(|breakpoint| breakpoint.hits(cctx)) { return; }
+
+        if self.show(s, cctx).and_then(|_| self.prompt(s, cctx)).is_err() {
+            self.detach();
+        }
+    }
+
+    // Execution stops when the transaction ends, so a later one starts paused again
+    fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, _result: &Result<(), Error>) {
+        if cctx.depth == 0 && self.resume != Resume::Continue { self.resume = Resume::Step; }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::Evm;
+    use crate::machine::tracers::tests::inspect;
+
+    // Runs a transaction with the given commands, and returns the output along with the depth and pc of every pause
+    fn debug(commands: &str) -> (String, Vec<(usize, usize)>) {
+        // PUSH1 42 PUSH0 SSTORE PUSH0 PUSH0 PUSH0 PUSH0 PUSH1 0xC1 GAS STATICCALL POP STOP, then PUSH1 1 STOP at 0xC1
+        let debugger = Debugger::new(io::Cursor::new(commands.to_string()), vec![]);
+        let (debugger, result) = inspect(Evm::builder(), &["602A5F555F5F5F5F60C15AFA5000", "600100"], &[], debugger);
+        result.unwrap();
+        let output = String::from_utf8(debugger.into_output()).unwrap();
+        let pauses = output.match_indices("[depth").map(|(i, _)| {
+            let words: Vec<_> = output[i..].split_whitespace().collect();
+            (words[1].trim_end_matches(']').parse().unwrap(), usize::from_str_radix(words[4].trim_start_matches("0x"), 16).unwrap())
+        }).collect();
+        (output, pauses)
+    }
+
+    #[test]
+    fn steps_into_over_and_out_of_calls() {
+        let (_, pauses) = debug("s\nb op staticcall\nc\ns\no\nc\n");
+        assert_eq!(pauses, [(0, 0), (0, 2), (0, 11), (1, 0), (0, 12)]);
+        let (_, pauses) = debug("b pc 0xb\nc\nn\nc\n");
+        assert_eq!(pauses, [(0, 0), (0, 11), (0, 12)]);
+    }
+
+    #[test]
+    fn pauses_before_storage_writes() {
+        let (_, pauses) = debug("b sstore 1\nc\n");
+        assert_eq!(pauses, [(0, 0)]);
+        let (output, pauses) = debug("b sstore 0\ni\nd 0\nc\nn\nc\n");
+        assert_eq!(pauses, [(0, 0)]);
+        assert!(output.contains("breakpoint 0: sstore 0x0"));
+        let (output, pauses) = debug("b sstore\nc\nn\nc\n");
+        assert_eq!(pauses, [(0, 0), (0, 3), (0, 4)]);
+        assert!(output.contains("storage (1 touched slots)\n  0x0 => 0x2a (was 0x0)"));
+    }
+
+    #[test]
+    fn shows_the_frame() {
+        let (output, _) = debug("b pc 12\nc\nc\n");
+        let view = &output[output.rfind("[depth").unwrap()..];
+        assert_eq!(view.lines().take(12).collect::<Vec<_>>(), [
+            "[depth 0] 0x00000000000000000000000000000000000000c0 pc 0xc gas 54279",
+            "     0x7  PUSH0",
+            "     0x8  PUSH1 0xc1",
+            "     0xa  GAS",
+            "     0xb  STATICCALL",
+            ">    0xc  POP",
+            "     0xd  STOP",
+            "stack (1 items, top first)",
+            "     0  0x1",
+            "memory (0 bytes)",
+            "storage (1 touched slots)",
+            "  0x0 => 0x2a (was 0x0)",
+        ]);
+        assert!(view.contains("returndata 0x\n"));
+    }
+
+    #[test]
+    fn runs_to_the_end_without_input() {
+        let (output, pauses) = debug("x\n");
+        assert_eq!(pauses, [(0, 0)]);
+        assert!(output.contains("b pc <pc>"));
+    }
+}
@@ -0,0 +1,383 @@
+use std::cmp::{max, min};
+use std::ops::Range;
+use crate::blockchain::errors::Error;
+use crate::machine::opcode::{EOF_INSTRUCTIONS, OpCode};
+use crate::machine::stack::STACK_LIMIT;
+
+pub const MAGIC: [u8; 2] = [0xEF, 0x00];
+const VERSION: u8 = 0x01;
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_CONTAINER: u8 = 0x03;
+const KIND_DATA: u8 = 0xFF;
+const TERMINATOR: u8 = 0x00;
+const NON_RETURNING: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CodeType {
+    pub inputs: u8,
+    pub max_stack_increase: u16,
+    pub outputs: u8,
+}
+
+impl CodeType {
+    pub fn is_returning(&self) -> bool {
+        self.outputs != NON_RETURNING
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerKind {
+    Initcode,
+    Runtime,
+}
+
+// Sections are stored as ranges of the container bytes so that the program counter stays absolute
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Container {
+    pub code_sections: Vec<Range<usize>>,
+    pub container_sections: Vec<Range<usize>>,
+    pub data_section: Range<usize>,
+    pub data_size: usize,
+    pub data_size_offset: usize,
+    pub types: Vec<CodeType>,
+}
+
+fn read(code: &[u8], offset: usize, size: usize) -> Result<usize, Error> {
+    let bytes = code.get(offset..offset + size).ok_or(Error::InvalidContainer)?;
+    Ok(bytes.iter().fold(0, |acc, b| (acc << 8) | usize::from(*b)))
+}
+
+fn expect(code: &[u8], offset: usize, value: u8) -> Result<(), Error> {
+    if code.get(offset) == Some(&value) { Ok(()) } else { Err(Error::InvalidContainer) }
+}
+
+fn read_sizes(code: &[u8], offset: usize, count: usize, size: usize) -> Result<Vec<usize>, Error> {
+    (0..count).map(|i| match read(code, offset + i * size, size)? {
+        0 => Err(Error::InvalidContainer),
+        x => Ok(x),
+    }).collect()
+}
+
+fn ranges(start: usize, sizes: &[usize]) -> Vec<Range<usize>> {
+    sizes.iter().scan(start, |offset, size| {
+        *offset += size;
+        Some(*offset - size..*offset)
+    }).collect()
+}
+
+impl Container {
+    pub fn is_eof(code: &[u8]) -> bool {
+        code.starts_with(&MAGIC)
+    }
+
+    // Deployed code has been validated at creation time
+    pub fn from_code(code: &[u8]) -> Option<Self> {
+        if Container::is_eof(code) { Container::parse(code).ok() } else { None }
+    }
+
+    // Declared size of the container, which is larger than its actual size when the data section is truncated
+    pub fn size(&self) -> usize {
+        self.data_section.start + self.data_size
+    }
+
+    // Parses the header and the types section without validating the code
+    pub fn parse(code: &[u8]) -> Result<Self, Error> {
+        if !Container::is_eof(code) { return Err(Error::InvalidContainer); }
+        expect(code, 2, VERSION)?;
+
+        expect(code, 3, KIND_TYPES)?;
+        let types_size = read(code, 4, 2)?;
+
+        expect(code, 6, KIND_CODE)?;
+        let num_code_sections = read(code, 7, 2)?;
+        if num_code_sections == 0 || num_code_sections > 1024 || types_size != 4 * num_code_sections { return Err(Error::InvalidContainer); }
+        let code_sizes = read_sizes(code, 9, num_code_sections, 2)?;
+        let mut offset = 9 + 2 * num_code_sections;
+
+        let container_sizes = if code.get(offset) == Some(&KIND_CONTAINER) {
+            let num_container_sections = read(code, offset + 1, 2)?;
+            if num_container_sections == 0 || num_container_sections > 256 { return Err(Error::InvalidContainer); }
+            let sizes = read_sizes(code, offset + 3, num_container_sections, 4)?;
+            offset += 3 + 4 * num_container_sections;
+            sizes
+        } else {
+            vec![]
+        };
+
+        expect(code, offset, KIND_DATA)?;
+        let data_size_offset = offset + 1;
+        let data_size = read(code, data_size_offset, 2)?;
+        expect(code, offset + 3, TERMINATOR)?;
+
+        let types_offset = offset + 4;
+        let types = (0..num_code_sections).map(|i| {
+            let offset = types_offset + 4 * i;
+            Ok(CodeType {
+                inputs: read(code, offset, 1)? as u8,
+                max_stack_increase: read(code, offset + 2, 2)? as u16,
+                outputs: read(code, offset + 1, 1)? as u8,
+            })
+        }).collect::<Result<Vec<_>, Error>>()?;
+        if types[0].inputs != 0 || types[0].is_returning() { return Err(Error::InvalidContainer); }
+        if types.iter().any(|t| t.inputs > 0x7F || (t.outputs > 0x7F && t.is_returning()) || t.max_stack_increase > 0x03FF) { return Err(Error::InvalidContainer); }
+
+        let code_sections = ranges(types_offset + types_size, &code_sizes);
+        let container_sections = ranges(code_sections.last().unwrap().end, &container_sizes);
+        let data_start = container_sections.last().unwrap_or(code_sections.last().unwrap()).end;
+        if code.len() < data_start { return Err(Error::InvalidContainer); }
+
+        Ok(Self {
+            code_sections,
+            container_sections,
+            data_section: data_start..min(code.len(), data_start + data_size),
+            data_size,
+            data_size_offset,
+            types,
+        })
+    }
+
+    // Only runtime subcontainers may have a truncated data section, which RETURNCONTRACT completes with its auxiliary data
+    pub fn validate(code: &[u8], kind: ContainerKind) -> Result<Self, Error> {
+        let container = Container::parse(code)?;
+        if code.len() > container.size() || (code.len() < container.size() && kind != ContainerKind::Runtime) { return Err(Error::InvalidContainer); }
+
+        let mut subcontainers: Vec<Option<ContainerKind>> = vec![None; container.container_sections.len()];
+        for index in 0..container.code_sections.len() {
+            container.validate_code_section(code, index, kind, &mut subcontainers)?;
+        }
+        for (range, kind) in container.container_sections.iter().zip(subcontainers) {
+            Container::validate(&code[range.clone()], kind.ok_or(Error::InvalidContainer)?)?;
+        }
+
+        Ok(container)
+    }
+
+    // Validates an initcontainer followed by its calldata, as found in a creation transaction
+    pub fn validate_initcode(data: &[u8]) -> Result<Self, Error> {
+        let size = Container::parse(data)?.size();
+        Container::validate(data.get(..size).ok_or(Error::InvalidContainer)?, ContainerKind::Initcode)
+    }
+
+    fn validate_code_section(&self, code: &[u8], index: usize, kind: ContainerKind, subcontainers: &mut [Option<ContainerKind>]) -> Result<(), Error> {
+        let section = &code[self.code_sections[index].clone()];
+        let code_type = self.types[index];
+
+        // Decodes the instructions and checks the opcodes and their immediates
+        let mut instructions = Vec::<usize>::new();
+        let mut is_instruction = vec![false; section.len()];
+        let mut jumps = Vec::<(usize, Vec<usize>)>::new();
+        let mut pc = 0;
+        while pc < section.len() {
+            let opcode = OpCode(section[pc]);
+            if !opcode.is_valid_in_eof() { return Err(Error::InvalidContainer); }
+            match (kind, opcode.0) {
+                (ContainerKind::Initcode, 0x00 | 0xF3) | (ContainerKind::Runtime, 0xEE) => return Err(Error::InvalidContainer),
+                _ => (),
+            }
+
+            let size = 1 + opcode.immediate_size() + if opcode.0 == 0xE2 { 2 * (read(section, pc + 1, 1)? + 1) } else { 0 };
+            if pc + size > section.len() { return Err(Error::InvalidContainer); }
+            let immediate = |offset: usize, size: usize| read(section, pc + 1 + offset, size);
+
+            match opcode.0 {
+                0xD1 if immediate(0, 2)? + 32 > self.data_size => return Err(Error::InvalidContainer),
+                0xE0 | 0xE1 => jumps.push((pc, vec![immediate(0, 2)?])),
+                0xE2 => jumps.push((pc, (0..=immediate(0, 1)?).map(|i| immediate(1 + 2 * i, 2)).collect::<Result<_, _>>()?)),
+                0xE3 if !self.types.get(immediate(0, 2)?).ok_or(Error::InvalidContainer)?.is_returning() => return Err(Error::InvalidContainer),
+                0xE5 if immediate(0, 2)? >= self.types.len() => return Err(Error::InvalidContainer),
+                0xEC | 0xEE => {
+                    let referenced = subcontainers.get_mut(immediate(0, 1)?).ok_or(Error::InvalidContainer)?;
+                    let referenced_kind = if opcode.0 == 0xEC { ContainerKind::Initcode } else { ContainerKind::Runtime };
+                    if referenced.is_some_and(|k| k != referenced_kind) { return Err(Error::InvalidContainer); }
+                    *referenced = Some(referenced_kind);
+                },
+                _ => (),
+            }
+
+            instructions.push(pc);
+            is_instruction[pc] = true;
+            pc += size;
+        }
+
+        // Relative jumps are relative to the end of the instruction and must land on an instruction
+        let mut targets = vec![Vec::<usize>::new(); section.len()];
+        for (pc, offsets) in jumps {
+            let next = pc + 1 + OpCode(section[pc]).immediate_size() + if section[pc] == 0xE2 { 2 * offsets.len() } else { 0 };
+            for offset in offsets {
+                let target = next as isize + isize::from(offset as u16 as i16);
+                if target < 0 || target as usize >= section.len() || !is_instruction[target as usize] { return Err(Error::InvalidContainer); }
+                targets[pc].push(target as usize);
+            }
+        }
+
+        // Stack heights are tracked as a range, which backward jumps must preserve exactly
+        let inputs = usize::from(code_type.inputs);
+        let mut heights: Vec<Option<(usize, usize)>> = vec![None; section.len()];
+        heights[0] = Some((inputs, inputs));
+        let mut max_height = inputs;
+        let mut returning = false;
+        for pc in instructions {
+            let (opcode, info) = (OpCode(section[pc]), &EOF_INSTRUCTIONS.0[usize::from(section[pc])]);
+            let (low, high) = heights[pc].ok_or(Error::InvalidContainer)?;
+            let immediate = |size: usize| read(section, pc + 1, size);
+            let (pops, pushes) = match opcode.0 {
+                0xE3 => {
+                    let target = self.types[immediate(2)?];
+                    if high + usize::from(target.max_stack_increase) > STACK_LIMIT { return Err(Error::InvalidContainer); }
+                    (usize::from(target.inputs), usize::from(target.outputs))
+                },
+                0xE4 => {
+                    returning = true;
+                    if low != high || low != usize::from(code_type.outputs) { return Err(Error::InvalidContainer); }
+                    (0, 0)
+                },
+                0xE5 => {
+                    let target = self.types[immediate(2)?];
+                    if high + usize::from(target.max_stack_increase) > STACK_LIMIT { return Err(Error::InvalidContainer); }
+                    if target.is_returning() {
+                        returning = true;
+                        if code_type.outputs < target.outputs || low != high || low + usize::from(target.outputs) != usize::from(code_type.outputs) + usize::from(target.inputs) { return Err(Error::InvalidContainer); }
+                    }
+                    (usize::from(target.inputs), 0)
+                },
+                0xE6 => { let n = immediate(1)?; (n + 1, n + 2) },
+                0xE7 => { let n = immediate(1)?; (n + 2, n + 2) },
+                0xE8 => { let x = immediate(1)?; ((x >> 4) + (x & 0x0F) + 3, (x >> 4) + (x & 0x0F) + 3) },
+                _ => info.stack_io.unwrap(),
+            };
+            if low < pops { return Err(Error::InvalidContainer); }
+            let (low, high) = (low - pops + pushes, high - pops + pushes);
+            if high > STACK_LIMIT { return Err(Error::InvalidContainer); }
+            max_height = max(max_height, high);
+
+            let next = pc + 1 + info.immediate_size + if opcode.0 == 0xE2 { 2 * targets[pc].len() } else { 0 };
+            let mut successors = targets[pc].clone();
+            if !info.terminating && opcode.0 != 0xE0 {
+                if next >= section.len() { return Err(Error::InvalidContainer); }
+                successors.push(next);
+            }
+            for successor in successors {
+                if successor > pc {
+                    heights[successor] = Some(match heights[successor] {
+                        Some((l, h)) => (min(l, low), max(h, high)),
+                        None => (low, high),
+                    });
+                } else if heights[successor] != Some((low, high)) {
+                    return Err(Error::InvalidContainer);
+                }
+            }
+        }
+
+        if returning != code_type.is_returning() || max_height - inputs != usize::from(code_type.max_stack_increase) { return Err(Error::InvalidContainer); }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a container from (inputs, outputs, max_stack_increase, code) sections, subcontainers and data
+    pub fn container(sections: &[(u8, u8, u16, &str)], subcontainers: &[&[u8]], data: &str, data_size: Option<u16>) -> Vec<u8> {
+        let data = hex::decode(data).unwrap();
+        let codes: Vec<Vec<u8>> = sections.iter().map(|(_, _, _, code)| hex::decode(code).unwrap()).collect();
+        let mut res = vec![0xEF, 0x00, 0x01, 0x01];
+        res.extend((4 * sections.len() as u16).to_be_bytes());
+        res.push(0x02);
+        res.extend((codes.len() as u16).to_be_bytes());
+        for code in &codes { res.extend((code.len() as u16).to_be_bytes()); }
+        if !subcontainers.is_empty() {
+            res.push(0x03);
+            res.extend((subcontainers.len() as u16).to_be_bytes());
+            for subcontainer in subcontainers { res.extend((subcontainer.len() as u32).to_be_bytes()); }
+        }
+        res.push(0xFF);
+        res.extend(data_size.unwrap_or(data.len() as u16).to_be_bytes());
+        res.push(0x00);
+        for (inputs, outputs, max_stack_increase, _) in sections {
+            res.extend([*inputs, *outputs]);
+            res.extend(max_stack_increase.to_be_bytes());
+        }
+        for code in codes { res.extend(code); }
+        for subcontainer in subcontainers { res.extend(*subcontainer); }
+        res.extend(data);
+        res
+    }
+
+    #[test]
+    fn parses_a_container() {
+        let code = container(&[(0, 0x80, 3, "5f5f5f00"), (1, 1, 0, "e4")], &[], "aabb", None);
+        let container = Container::parse(&code).unwrap();
+
+        assert_eq!(container.types, vec![
+            CodeType { inputs: 0, max_stack_increase: 3, outputs: 0x80 },
+            CodeType { inputs: 1, max_stack_increase: 0, outputs: 1 },
+        ]);
+        assert_eq!(container.code_sections, vec![25..29, 29..30]);
+        assert_eq!(container.container_sections, vec![]);
+        assert_eq!(container.data_section, 30..32);
+        assert_eq!(container.data_size, 2);
+        assert_eq!(&code[container.data_size_offset..container.data_size_offset + 2], &[0x00, 0x02]);
+        assert_eq!(container.size(), code.len());
+    }
+
+    #[test]
+    fn fails_to_parse_an_invalid_header() {
+        assert_eq!(Container::parse(&hex::decode("6000").unwrap()), Err(Error::InvalidContainer));
+        assert_eq!(Container::parse(&hex::decode("ef00020100040200010001ff00000000800000fe").unwrap()), Err(Error::InvalidContainer)); // version
+        assert_eq!(Container::parse(&hex::decode("ef00010100080200010001ff00000000800000fe").unwrap()), Err(Error::InvalidContainer)); // types size
+        assert_eq!(Container::parse(&hex::decode("ef00010100040200010000ff00000000800000").unwrap()), Err(Error::InvalidContainer)); // empty code section
+        assert_eq!(Container::parse(&hex::decode("ef00010100040200010001ff000000").unwrap()), Err(Error::InvalidContainer)); // missing body
+        assert_eq!(Container::parse(&hex::decode("ef00010100040200010001ff00000001000000fe").unwrap()), Err(Error::InvalidContainer)); // first section with inputs
+        assert!(Container::parse(&hex::decode("ef00010100040200010001ff00000000800000fe").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn validates_a_container() {
+        assert!(Container::validate(&container(&[(0, 0x80, 0, "00")], &[], "", None), ContainerKind::Runtime).is_ok());
+        assert!(Container::validate(&container(&[(0, 0x80, 2, "e30001e50002"), (0, 2, 2, "5f5fe4"), (0, 0x80, 0, "00")], &[], "", None), ContainerKind::Runtime).is_ok());
+        assert!(Container::validate(&container(&[(0, 0x80, 1, "5fe10003e0000000")], &[], "", None), ContainerKind::Runtime).is_ok());
+        assert!(Container::validate(&container(&[(0, 0x80, 1, "5fe201000000010000")], &[], "", None), ContainerKind::Runtime).is_ok());
+        assert!(Container::validate(&container(&[(0, 0x80, 1, "d1000000")], &[], "00000000000000000000000000000000000000000000000000000000000000aa", None), ContainerKind::Runtime).is_ok());
+    }
+
+    #[test]
+    fn fails_to_validate_invalid_code() {
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 1, "5800")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // PC is removed
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 1, "6100")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // truncated immediate
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "e0fffe00")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // jump into an immediate
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "0000")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // unreachable code
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 1, "5f")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // falls off the section
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "0100")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // stack underflow
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 3, "5f5f00")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // wrong max stack increase
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 1, "5fe0fffc")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // backward jump with a different height
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "d1000000")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // DATALOADN out of bounds
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "e30001"), (0, 0x80, 0, "00")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // CALLF to a non-returning section
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "00"), (0, 0, 0, "00")], &[], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // returning section without RETF
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "00")], &[], "", None), ContainerKind::Initcode), Err(Error::InvalidContainer)); // STOP in initcode
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "fe")], &[], "aa", Some(2)), ContainerKind::Initcode), Err(Error::InvalidContainer)); // truncated data
+        assert!(Container::validate(&container(&[(0, 0x80, 0, "fe")], &[], "aa", Some(2)), ContainerKind::Runtime).is_ok());
+    }
+
+    #[test]
+    fn validates_subcontainers() {
+        let runtime = container(&[(0, 0x80, 0, "00")], &[], "", None);
+        let initcode = container(&[(0, 0x80, 2, "5f5fee00")], &[&runtime], "", None);
+
+        assert!(Container::validate(&initcode, ContainerKind::Initcode).is_ok());
+        assert!(Container::validate(&container(&[(0, 0x80, 4, "5f5f5f5fec0000")], &[&initcode], "", None), ContainerKind::Runtime).is_ok());
+        assert_eq!(Container::validate(&initcode, ContainerKind::Runtime), Err(Error::InvalidContainer)); // RETURNCONTRACT in runtime code
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 0, "00")], &[&runtime], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // unreferenced subcontainer
+        assert_eq!(Container::validate(&container(&[(0, 0x80, 4, "5f5f5f5fec0000")], &[&runtime], "", None), ContainerKind::Runtime), Err(Error::InvalidContainer)); // STOP in an initcontainer
+    }
+
+    #[test]
+    fn validates_initcode_followed_by_calldata() {
+        let runtime = container(&[(0, 0x80, 0, "00")], &[], "", None);
+        let initcode = container(&[(0, 0x80, 2, "5f5fee00")], &[&runtime], "", None);
+
+        assert_eq!(Container::validate_initcode(&[initcode.clone(), vec![0x42, 0x43]].concat()).unwrap().size(), initcode.len());
+        assert_eq!(Container::validate_initcode(&initcode[..initcode.len() - 1]), Err(Error::InvalidContainer));
+    }
+}
@@ -0,0 +1,117 @@
+use ethnum::u256;
+
+use crate::blockchain::WorldState;
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+use crate::blockchain::storage::StorageValue;
+use crate::machine::context::{CallContext, Log};
+use crate::machine::opcode::OpCode;
+
+// Observes an execution through hooks that do nothing by default, except for creates which are reported as calls. Each hook gets the call context it happens in,
+// whose stack, memory, pc and gas are those of the current instruction. While a transaction is inspected, `s.log`
+// records the accounts and slots it accessed
+pub trait Inspector {
+    // Before an instruction is paid for and runs
+    fn step(&mut self, _s: &WorldState, _cctx: &CallContext) {}
+
+    // After it, once pc has moved on. `cost` is the gas it was charged, which for calls and creates includes the gas
+    // that the callee used, and is 0 if it failed
+    fn step_end(&mut self, _s: &WorldState, _cctx: &CallContext, _cost: usize, _result: &Result<(), Error>) {}
+
+    // Before and after a call, where `cctx` is the context of the callee and `opcode` the instruction that made it.
+    // `target` is the account whose code runs, which is not the callee for DELEGATECALL and CALLCODE.
+    // The transaction itself counts as a CALL or a CREATE
+    fn call(&mut self, _s: &WorldState, _cctx: &CallContext, _opcode: OpCode, _target: Address) {}
+
+    fn call_end(&mut self, _s: &WorldState, _cctx: &CallContext, _result: &Result<(), Error>) {}
+
+    // Before and after a create, which are hooks of their own for inspectors that tell them apart from calls. `cctx` is
+    // the context of the initcode, whose address is the one of the new account
+    fn create(&mut self, s: &WorldState, cctx: &CallContext, opcode: OpCode) {
+        self.call(s, cctx, opcode, cctx.contract.address);
+    }
+
+    fn create_end(&mut self, s: &WorldState, cctx: &CallContext, result: &Result<(), Error>) {
+        self.call_end(s, cctx, result);
+    }
+
+    fn log(&mut self, _cctx: &CallContext, _log: &Log) {}
+
+    // `previous` holds the value of the slot before the store, along with its value at the start of the transaction
+    fn sstore(&mut self, _cctx: &CallContext, _key: u256, _value: u256, _previous: &StorageValue<u256>) {}
+
+    fn selfdestruct(&mut self, _cctx: &CallContext, _beneficiary: Address, _balance: u256) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::blockchain::primitives::{Account, Block, Transaction};
+    use crate::evm::Evm;
+    use crate::machine::ExecutionOutput;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        gas: Vec<usize>,
+        steps: Vec<(usize, usize)>,
+        step_ends: usize,
+    }
+
+    impl Inspector for Recorder {
+        fn step(&mut self, _s: &WorldState, cctx: &CallContext) {
+            self.steps.push((cctx.depth, cctx.pc));
+            self.gas.push(cctx.available_gas());
+        }
+
+        fn step_end(&mut self, _s: &WorldState, _cctx: &CallContext, _cost: usize, _result: &Result<(), Error>) {
+            self.step_ends += 1;
+        }
+
+        fn call(&mut self, _s: &WorldState, cctx: &CallContext, opcode: OpCode, _target: Address) {
+            self.events.push(format!("{} {:?} {}", opcode, cctx.contract.address, cctx.depth));
+        }
+
+        fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, result: &Result<(), Error>) {
+            self.events.push(format!("end {:?} {:?}", cctx.contract.address, result));
+        }
+
+        fn log(&mut self, _cctx: &CallContext, log: &Log) {
+            self.events.push(format!("log {:?}", log.data));
+        }
+
+        fn sstore(&mut self, _cctx: &CallContext, key: u256, value: u256, previous: &StorageValue<u256>) {
+            self.events.push(format!("sstore {} {} {}", key, value, previous.value));
+        }
+    }
+
+    #[test]
+    fn calls_the_hooks() {
+        // PUSH1 42 PUSH0 SSTORE PUSH0 PUSH0 LOG0 PUSH0 PUSH0 PUSH0 PUSH0 PUSH1 4 GAS STATICCALL POP STOP, where 0x04 has no code
+        let code = hex::decode("602A5F555F5FA05F5F5F5F60045AFA5000").unwrap();
+        let mut evm = Evm::builder()
+            .account(Address(uint!("0xA0")), Account { balance: uint!("100000000"), ..Default::default() })
+            .account(Address(uint!("0xC0")), Account { balance: uint!("0"), code: code.into(), nonce: 1 })
+            .build();
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        let tx = Transaction { from: Address(uint!("0xA0")), gas: 100000, gas_price: 1, to: Address(uint!("0xC0")), ..Default::default() };
+
+        let result = evm.inspect(Block::default(), tx, recorder.clone());
+        assert!(matches!(result, Ok(ExecutionOutput { revert: false, .. })));
+        let recorder = recorder.borrow();
+        assert_eq!(recorder.events, [
+            "CALL Address(0xC0) 0",
+            "sstore 0 42 0",
+            "log []",
+            "STATICCALL Address(0x4) 1",
+            "end Address(0x4) Ok(())",
+            "end Address(0xC0) Ok(())",
+        ]);
+        assert_eq!(recorder.steps, [(0, 0), (0, 2), (0, 3), (0, 4), (0, 5), (0, 6), (0, 7), (0, 8), (0, 9), (0, 10), (0, 11), (0, 13), (0, 14), (1, 0), (0, 15), (0, 16)]);
+        assert_eq!(recorder.step_ends, recorder.steps.len());
+        assert_eq!(recorder.gas[..3], [79000, 78997, 78995]);
+    }
+}
@@ -1,8 +1,17 @@
 use ethnum::{u256, AsU256, U256};
+use std::cmp::{max, min};
 use crate::blockchain::WorldState;
+use crate::blockchain::bytecode::Bytecode;
 use crate::blockchain::errors::Error;
-use crate::machine::context::{CallContext, Log, TransactionContext};
+use crate::blockchain::primitives::{Account, Address};
+use crate::blockchain::storage::StorageValue;
+use crate::machine::Machine;
+use crate::machine::context::{CallContext, CallContextContract, Halt, Log, MAX_CALL_DEPTH, TransactionContext};
+use crate::machine::eof::Container;
 use crate::machine::memory::ReadWriteOperation;
+use crate::machine::opcode::OpCode;
+use crate::machine::precompiles::Precompile;
+use crate::machine::stack::STACK_LIMIT;
 use crate::utils::{Hash, IsNeg, NeededSizeInBytes, WrappingBigPow, WrappingSignedDiv, WrappingSignedRem};
 
 #[derive(Debug, Eq, PartialEq)]
@@ -17,18 +26,34 @@ pub struct Instructions {}
 
 impl Instructions {
     fn pop_or_fail<const N: usize>(cctx: &mut CallContext) -> Result<[u256; N], Error> {
-        let mut res = [U256::ZERO; N];
-        for i in 0..N {
-            res[i] = if let Some(x) = cctx.stack.pop() { x } else { return Err(Error::EmptyStack) }
-        }
-        Ok(res)
+        cctx.stack.pop_array()
     }
 
     fn push_rev_or_fail<const N: usize>(cctx: &mut CallContext, values: [u256; N]) -> Result<(), Error> {
-        for i in (0..N).rev() {
-            cctx.stack.push(values[i])?;
+        cctx.stack.push_array(values)
+    }
+
+    fn immediate(cctx: &CallContext, offset: usize, size: usize) -> usize {
+        (0..size).fold(0, |acc, i| (acc << 8) | usize::from(*cctx.contract.code.get(cctx.pc + 1 + offset + i).unwrap_or(&0)))
+    }
+
+    // Only called from EOF code, whose container is parsed when the code is loaded
+    fn eof(cctx: &CallContext) -> &Container {
+        cctx.contract.eof.as_ref().unwrap()
+    }
+
+    fn relative_jump(cctx: &mut CallContext, next: usize, offset: usize) {
+        cctx.pc = (next as isize + isize::from(offset as u16 as i16)) as usize;
+    }
+
+    fn word_or_zero(bytes: &[u8], offset: u256) -> u256 {
+        let offset = TryInto::<usize>::try_into(offset).unwrap_or(usize::MAX);
+        let mut res = U256::ZERO;
+        for i in 0..32usize {
+            res <<= 8;
+            res |= u256::from(*bytes.get(offset.saturating_add(i)).unwrap_or(&0u8));
         }
-        Ok(())
+        res
     }
 
     fn jump_or_fail(cctx: &mut CallContext, counter: u256) -> Result<(), Error> {
@@ -36,10 +61,9 @@ impl Instructions {
             Ok(x) => x,
             _ => return Err(Error::InvalidJumpDest),
         };
-        match cctx.contract.code.get(counter) {
-            Some(x) => if *x == 0x5B { cctx.pc = counter; Ok(()) } else { Err(Error::InvalidJumpDest) },
-            _ => Err(Error::InvalidJumpDest),
-        }
+        if !cctx.contract.code.is_jumpdest(counter) { return Err(Error::InvalidJumpDest); }
+        cctx.pc = counter;
+        Ok(())
     }
 
     pub fn stop(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
@@ -225,7 +249,8 @@ impl Instructions {
     pub fn keccak256(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let [offset, size] = Instructions::pop_or_fail(cctx)?;
         let ReadWriteOperation { size, extension_cost, result, .. } = cctx.memory.load(offset, size)?;
-        Instructions::push_rev_or_fail(cctx, [result.keccak256()])?;
+        let hash = result.keccak256();
+        Instructions::push_rev_or_fail(cctx, [hash])?;
         Ok(InstructionOutput { cost: 30 + 6 * ((size + 31) >> 5) + extension_cost, jump: 1 })
     }
 
@@ -236,7 +261,7 @@ impl Instructions {
 
     pub fn balance(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let [address] = Instructions::pop_or_fail(cctx)?;
-        let account = s.accounts.load(address.try_into()?);
+        let account = s.load_account(address.try_into()?)?;
         Instructions::push_rev_or_fail(cctx, [account.value.balance])?;
         Ok(InstructionOutput { cost: if account.warm { 100 } else { 2600 }, jump: 1 })
     }
@@ -281,7 +306,7 @@ impl Instructions {
         let [dest_offset, offset, size] = Instructions::pop_or_fail(cctx)?;
         let (calldata_offset, calldata_size): (usize, usize) = (offset.try_into().unwrap(), size.try_into().unwrap()); // TODO (fguerin - 11/12/2024) Handle calldata out of bounds
         let value = &cctx.contract.input[calldata_offset..std::cmp::min(cctx.contract.input.len(), calldata_offset + calldata_size)];
-        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value.to_vec())?;
+        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value)?;
         Ok(InstructionOutput { cost: 3 + 3 * ((size + 31) >> 5) + extension_cost, jump: 1 })
     }
 
@@ -294,7 +319,7 @@ impl Instructions {
         let [dest_offset, offset, size] = Instructions::pop_or_fail(cctx)?;
         let (code_offset, code_size): (usize, usize) = (offset.try_into().unwrap(), size.try_into().unwrap()); // TODO (fguerin - 11/12/2024) Handle code out of bounds
         let value = &cctx.contract.code[code_offset..std::cmp::min(cctx.contract.code.len(), code_offset + code_size)];
-        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value.to_vec())?;
+        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value)?;
         Ok(InstructionOutput { cost: 3 + 3 * ((size + 31) >> 5) + extension_cost, jump: 1 })
     }
 
@@ -305,17 +330,17 @@ impl Instructions {
 
     pub fn extcodesize(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let [address] = Instructions::pop_or_fail(cctx)?;
-        let account = s.accounts.load(address.try_into()?);
+        let account = s.load_account(address.try_into()?)?;
         Instructions::push_rev_or_fail(cctx, [account.value.code.len().as_u256()])?;
         Ok(InstructionOutput { cost: if account.warm { 100 } else { 2600 }, jump: 1 })
     }
 
     pub fn extcodecopy(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let [address, dest_offset, offset, size] = Instructions::pop_or_fail(cctx)?;
-        let account = s.accounts.load(address.try_into()?);
+        let account = s.load_account(address.try_into()?)?;
         let (code_offset, code_size): (usize, usize) = (offset.try_into().unwrap(), size.try_into().unwrap()); // TODO (fguerin - 13/12/2024) Handle code out of bounds
         let value = &account.value.code[code_offset..std::cmp::min(account.value.code.len(), code_offset + code_size)];
-        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value.to_vec())?;
+        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value)?;
         Ok(InstructionOutput { cost: 3 * ((size + 31) >> 5) + extension_cost + if account.warm { 100 } else { 2600 }, jump: 1 })
     }
 
@@ -328,20 +353,23 @@ impl Instructions {
         let [dest_offset, offset, size] = Instructions::pop_or_fail(cctx)?;
         let (returndata_offset, returndata_size): (usize, usize) = (offset.try_into().unwrap(), size.try_into().unwrap()); // TODO (fguerin - 13/12/2024) Handle returndata out of bounds
         let value = &cctx.returndata[returndata_offset..std::cmp::min(cctx.returndata.len(), returndata_offset + returndata_size)];
-        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value.to_vec())?;
+        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value)?;
         Ok(InstructionOutput { cost: 3 + 3 * ((size + 31) >> 5) + extension_cost, jump: 1 })
     }
 
     pub fn extcodehash(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         // TODO (fguerin - 22/02/2025) Implement other subtleties
         let [address] = Instructions::pop_or_fail(cctx)?;
-        let account = s.accounts.load(address.try_into()?);
-        Instructions::push_rev_or_fail(cctx, [account.value.code.keccak256()])?;
+        let account = s.load_account(address.try_into()?)?;
+        Instructions::push_rev_or_fail(cctx, [account.value.code.hash()])?;
         Ok(InstructionOutput { cost: if account.warm { 100 } else { 2600 }, jump: 1 })
     }
 
-    pub fn blockhash(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
-        todo!();
+    pub fn blockhash(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [number] = Instructions::pop_or_fail(cctx)?;
+        let available = number < tctx.block.number && tctx.block.number - number <= 256; // only the last 256 blocks
+        Instructions::push_rev_or_fail(cctx, [if available { s.block_hash(number) } else { U256::ZERO }])?;
+        Ok(InstructionOutput { cost: 20, jump: 1 })
     }
 
     pub fn coinbase(_s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
@@ -376,7 +404,7 @@ impl Instructions {
 
     pub fn selfbalance(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         // TODO (fguerin - 13/12/2024) Test whether it should warm the storage
-        let account = s.accounts.load(cctx.contract.address);
+        let account = s.load_account(cctx.contract.address)?;
         Instructions::push_rev_or_fail(cctx, [account.value.balance])?;
         Ok(InstructionOutput { cost: 5, jump: 1 })
     }
@@ -419,20 +447,18 @@ impl Instructions {
 
     pub fn sload(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let [key] = Instructions::pop_or_fail(cctx)?;
-        let storage = s.storage.entry(cctx.contract.address).or_default();
-        let result = storage.load(key);
+        let result = s.load_slot(cctx.contract.address, key)?;
         Instructions::push_rev_or_fail(cctx, [result.value])?;
         Ok(InstructionOutput { cost: if result.warm { 100 } else { 2100 }, jump: 1 })
     }
 
-    pub fn sstore(s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+    pub fn sstore(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         // TODO (fguerin - 14/12/2024) Add gas refund
+        if cctx.r#static { return Err(Error::StaticStateChange); }
         let [key, value] = Instructions::pop_or_fail(cctx)?;
-        let storage = s.storage.entry(cctx.contract.address).or_default();
-        let (current_value, original_value, warm) = match storage.store(key, value) {
-            Some(v) => (v.value, v.original_value, v.warm),
-            None => (U256::ZERO, U256::ZERO, false),
-        };
+        let previous = s.store_slot(cctx.contract.address, key, value)?.unwrap_or(StorageValue { original_value: U256::ZERO, value: U256::ZERO, warm: false });
+        tctx.inspect(|inspector| inspector.sstore(cctx, key, value, &previous));
+        let StorageValue { value: current_value, original_value, warm } = previous;
         let base_cost: usize =
             if value == current_value { 100 }     // the value does not change
         else if current_value == original_value { // the storage slot is clean ...
@@ -485,6 +511,7 @@ impl Instructions {
     }
 
     pub fn tstore(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        if cctx.r#static { return Err(Error::StaticStateChange); }
         let [key, value] = Instructions::pop_or_fail(cctx)?;
         cctx.transient.store(key, value);
         Ok(InstructionOutput { cost: 100, jump: 1 })
@@ -492,100 +519,402 @@ impl Instructions {
 
     pub fn mcopy(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let [dest_offset, offset, size] = Instructions::pop_or_fail(cctx)?;
-        let ReadWriteOperation { result, extension_cost: read_extension_cost, .. } = cctx.memory.load(offset, size)?;
-        let ReadWriteOperation { size, extension_cost: write_extension_cost, .. } = cctx.memory.store(dest_offset, size, result)?;
-        Ok(InstructionOutput { cost: 3 + 3 * ((size + 31) >> 5) + read_extension_cost + write_extension_cost, jump: 1 })
+        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.copy(dest_offset, offset, size)?;
+        Ok(InstructionOutput { cost: 3 + 3 * ((size + 31) >> 5) + extension_cost, jump: 1 })
     }
 
     pub fn push<const N: usize>(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         let mut res = U256::ZERO;
-        for i in 0..N {
-            res <<= 8;
-            res |= u256::from(*cctx.contract.code.get(cctx.pc + i + 1).unwrap_or(&0u8));
-        };
+        for byte in &cctx.contract.code.padded()[cctx.pc + 1..cctx.pc + 1 + N] {
+            res = (res << 8) | u256::from(*byte);
+        }
         Instructions::push_rev_or_fail(cctx, [res])?;
         Ok(InstructionOutput { cost: if N == 0 { 2 } else { 3 }, jump: N + 1 })
     }
 
     pub fn dup<const N: usize>(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
-        let values = Instructions::pop_or_fail::<N>(cctx)?;
-        Instructions::push_rev_or_fail(cctx, values)?;
-        Instructions::push_rev_or_fail(cctx, [values[N - 1]])?;
+        cctx.stack.dup(N)?;
         Ok(InstructionOutput { cost: 3, jump: 1 })
     }
 
     pub fn swap<const N: usize>(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
-        let mut values = Instructions::pop_or_fail::<N>(cctx)?;
-        values.swap(0, N - 1);
-        Instructions::push_rev_or_fail(cctx, values)?;
+        cctx.stack.swap(N - 1)?;
         Ok(InstructionOutput { cost: 3, jump: 1 })
     }
 
-    pub fn log<const N: usize>(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+    pub fn log<const N: usize>(_s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        if cctx.r#static { return Err(Error::StaticStateChange); }
         let [offset, size] = Instructions::pop_or_fail(cctx)?;
         let topics = Instructions::pop_or_fail::<N>(cctx)?;
         let ReadWriteOperation { result: data, extension_cost, size, .. } = cctx.memory.load(offset, size)?;
-        cctx.contract.logs.push(Log {
-            data,
+        let log = Log {
+            data: data.to_vec(),
             topics: [
-                topics.get(0).cloned(),
+                topics.first().cloned(),
                 topics.get(1).cloned(),
                 topics.get(2).cloned(),
                 topics.get(3).cloned(),
             ],
-        });
+        };
+        tctx.inspect(|inspector| inspector.log(cctx, &log));
+        cctx.contract.logs.push(log);
         Ok(InstructionOutput { cost: 375 * (N + 1) + (size << 3) + extension_cost, jump: 1 })
     }
 
+    pub fn dataload(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [offset] = Instructions::pop_or_fail(cctx)?;
+        let value = Instructions::word_or_zero(&cctx.contract.code[Instructions::eof(cctx).data_section.clone()], offset);
+        Instructions::push_rev_or_fail(cctx, [value])?;
+        Ok(InstructionOutput { cost: 4, jump: 1 })
+    }
+
+    pub fn dataloadn(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let offset = Instructions::immediate(cctx, 0, 2);
+        let value = Instructions::word_or_zero(&cctx.contract.code[Instructions::eof(cctx).data_section.clone()], offset.as_u256());
+        Instructions::push_rev_or_fail(cctx, [value])?;
+        Ok(InstructionOutput { cost: 3, jump: 3 })
+    }
+
+    pub fn datasize(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::push_rev_or_fail(cctx, [Instructions::eof(cctx).data_section.len().as_u256()])?;
+        Ok(InstructionOutput { cost: 2, jump: 1 })
+    }
+
+    pub fn datacopy(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [dest_offset, offset, size] = Instructions::pop_or_fail(cctx)?;
+        let data = &cctx.contract.code[Instructions::eof(cctx).data_section.clone()];
+        let value = &data[min(TryInto::<usize>::try_into(offset).unwrap_or(usize::MAX), data.len())..];
+        let ReadWriteOperation { size, extension_cost, .. } = cctx.memory.store(dest_offset, size, value)?;
+        Ok(InstructionOutput { cost: 3 + 3 * ((size + 31) >> 5) + extension_cost, jump: 1 })
+    }
+
+    pub fn rjump(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::relative_jump(cctx, cctx.pc + 3, Instructions::immediate(cctx, 0, 2));
+        Ok(InstructionOutput { cost: 2, jump: 0 })
+    }
+
+    pub fn rjumpi(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [condition] = Instructions::pop_or_fail(cctx)?;
+        let jump = match condition {
+            U256::ZERO => 3,
+            _ => { Instructions::relative_jump(cctx, cctx.pc + 3, Instructions::immediate(cctx, 0, 2)); 0 },
+        };
+        Ok(InstructionOutput { cost: 4, jump })
+    }
+
+    pub fn rjumpv(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [case] = Instructions::pop_or_fail(cctx)?;
+        let max_index = Instructions::immediate(cctx, 0, 1);
+        let size = 2 + 2 * (max_index + 1);
+        let jump = match TryInto::<usize>::try_into(case) {
+            Ok(case) if case <= max_index => { Instructions::relative_jump(cctx, cctx.pc + size, Instructions::immediate(cctx, 1 + 2 * case, 2)); 0 },
+            _ => size,
+        };
+        Ok(InstructionOutput { cost: 4, jump })
+    }
+
+    pub fn callf(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let index = Instructions::immediate(cctx, 0, 2);
+        let (start, code_type) = (Instructions::eof(cctx).code_sections[index].start, Instructions::eof(cctx).types[index]);
+        if cctx.stack.len() + usize::from(code_type.max_stack_increase) > STACK_LIMIT || cctx.return_stack.len() == STACK_LIMIT { return Err(Error::StackOverflow); }
+        cctx.return_stack.push(cctx.pc + 3);
+        cctx.pc = start;
+        Ok(InstructionOutput { cost: 5, jump: 0 })
+    }
+
+    pub fn retf(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        cctx.pc = cctx.return_stack.pop().ok_or(Error::EmptyStack)?;
+        Ok(InstructionOutput { cost: 3, jump: 0 })
+    }
+
+    pub fn jumpf(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let index = Instructions::immediate(cctx, 0, 2);
+        let (start, code_type) = (Instructions::eof(cctx).code_sections[index].start, Instructions::eof(cctx).types[index]);
+        if cctx.stack.len() + usize::from(code_type.max_stack_increase) > STACK_LIMIT { return Err(Error::StackOverflow); }
+        cctx.pc = start;
+        Ok(InstructionOutput { cost: 5, jump: 0 })
+    }
+
+    pub fn dupn(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let n = Instructions::immediate(cctx, 0, 1);
+        cctx.stack.dup(n + 1)?;
+        Ok(InstructionOutput { cost: 3, jump: 2 })
+    }
+
+    pub fn swapn(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let n = Instructions::immediate(cctx, 0, 1);
+        cctx.stack.swap(n + 1)?;
+        Ok(InstructionOutput { cost: 3, jump: 2 })
+    }
+
+    pub fn exchange(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let immediate = Instructions::immediate(cctx, 0, 1);
+        let (n, m) = ((immediate >> 4) + 1, (immediate & 0x0F) + 1);
+        cctx.stack.exchange(n, n + m)?;
+        Ok(InstructionOutput { cost: 3, jump: 2 })
+    }
+
+    pub fn eofcreate(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        if cctx.r#static { return Err(Error::StaticStateChange); }
+        let [value, salt, input_offset, input_size] = Instructions::pop_or_fail(cctx)?;
+        let initcontainer = Bytecode::uncached(&cctx.contract.code[Instructions::eof(cctx).container_sections[Instructions::immediate(cctx, 0, 1)].clone()]);
+        let ReadWriteOperation { result: input, extension_cost, .. } = cctx.memory.load(input_offset, input_size)?;
+        let input = input.to_vec();
+        let cost = 32000 + 6 * ((initcontainer.len() + 31) >> 5) + extension_cost;
+        if cctx.contract.gas < cost { return Err(Error::OutOfGas); }
+        cctx.returndata = vec![];
+
+        let creator = s.load_account(cctx.contract.address)?.value;
+        if cctx.depth >= MAX_CALL_DEPTH || creator.balance < value {
+            Instructions::push_rev_or_fail(cctx, [U256::ZERO])?;
+            return Ok(InstructionOutput { cost, jump: 2 });
+        }
+        s.store_account(cctx.contract.address, Account { nonce: creator.nonce + 1, ..creator })?;
+
+        // keccak256(0xFF || sender || salt || keccak256(initcontainer))[12:]
+        let hash = [vec![0xFF], cctx.contract.address.0.to_be_bytes()[12..].to_vec(), salt.to_be_bytes().to_vec(), initcontainer.hash().to_be_bytes().to_vec()].concat().keccak256();
+        let address = Address(hash & u256::from_str_hex("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        let available = cctx.contract.gas - cost;
+        let gas = available - available / 64;
+        let account = s.load_account(address)?.value;
+        if account.nonce != 0 || !account.code.is_empty() { // address collision
+            Instructions::push_rev_or_fail(cctx, [U256::ZERO])?;
+            return Ok(InstructionOutput { cost: cost + gas, jump: 2 });
+        }
+
+        let eof = Container::parse(&initcontainer).ok(); // validated along with the current container
+        let child = &mut CallContext {
+            depth: cctx.depth + 1,
+            memory: cctx.memory.enter_frame(),
+            transient: std::mem::take(&mut cctx.transient),
+            ..CallContext::new(CallContextContract { address, caller: cctx.contract.address, code: initcontainer, eof, gas, input, logs: vec![], value })
+        };
+        let result = Machine::execute_subcontext(s, tctx, child, value, None, OpCode(0xEC), address);
+        if let Err(Error::Backend(error)) = result { return Err(Error::Backend(error)); } // the state is missing, not the call failing
+        cctx.memory.leave_frame(std::mem::take(&mut child.memory));
+        cctx.transient = std::mem::take(&mut child.transient);
+        if result.is_ok() && !child.revert {
+            let account = s.load_account(address)?.value;
+            s.store_account(address, Account { code: std::mem::take(&mut child.r#return).into(), nonce: 1, ..account })?;
+            cctx.contract.logs.append(&mut child.contract.logs);
+            Instructions::push_rev_or_fail(cctx, [address.0])?;
+        } else {
+            cctx.returndata = std::mem::take(&mut child.r#return);
+            Instructions::push_rev_or_fail(cctx, [U256::ZERO])?;
+        }
+        Ok(InstructionOutput { cost: cost + gas - child.contract.gas, jump: 2 })
+    }
+
+    pub fn returncontract(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        cctx.stop = true;
+        let [aux_data_offset, aux_data_size] = Instructions::pop_or_fail(cctx)?;
+        let subcontainer = cctx.contract.code[Instructions::eof(cctx).container_sections[Instructions::immediate(cctx, 0, 1)].clone()].to_vec();
+        let ReadWriteOperation { result: aux_data, extension_cost, .. } = cctx.memory.load(aux_data_offset, aux_data_size)?;
+
+        // The auxiliary data is appended to the data section, which must end up complete
+        let mut code = [&subcontainer, aux_data].concat();
+        let container = Container::parse(&code)?;
+        let data_size = code.len() - container.data_section.start;
+        if data_size < container.data_size || data_size > 0xFFFF { return Err(Error::InvalidContainer); }
+        code[container.data_size_offset..container.data_size_offset + 2].copy_from_slice(&(data_size as u16).to_be_bytes());
+
+        let cost = extension_cost + 200 * code.len(); // code deposit cost
+        cctx.r#return = code;
+        Ok(InstructionOutput { cost, jump: 0 })
+    }
+
     pub fn create(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
         todo!();
     }
 
-    pub fn call(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
-        todo!();
+    pub fn call(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::legacy_call::<false, false, false>(s, tctx, cctx)
     }
 
-    pub fn callcode(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
-        todo!();
+    pub fn callcode(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::legacy_call::<true, false, false>(s, tctx, cctx)
     }
 
     pub fn r#return(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         cctx.stop = true;
         let [offset, size] = Instructions::pop_or_fail(cctx)?;
         let ReadWriteOperation { result: data, extension_cost, .. } = cctx.memory.load(offset, size)?;
-        cctx.r#return = data;
+        cctx.r#return = data.to_vec();
         Ok(InstructionOutput { cost: extension_cost, jump: 0 })
     }
 
-    pub fn delegatecall(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
-        todo!();
+    pub fn delegatecall(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::legacy_call::<true, true, false>(s, tctx, cctx)
     }
 
     pub fn create2(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
         todo!();
     }
 
-    pub fn staticcall(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
-        todo!();
+    pub fn returndataload(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [offset] = Instructions::pop_or_fail(cctx)?;
+        Instructions::push_rev_or_fail(cctx, [Instructions::word_or_zero(&cctx.returndata, offset)])?;
+        Ok(InstructionOutput { cost: 3, jump: 1 })
+    }
+
+    // Legacy calls push 1 on success and 0 otherwise. CALLCODE and DELEGATECALL run the target code on the current account
+    fn legacy_call<const CODE: bool, const DELEGATE: bool, const STATIC: bool>(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [gas, target] = Instructions::pop_or_fail(cctx)?;
+        let [value] = if DELEGATE || STATIC { [U256::ZERO] } else { Instructions::pop_or_fail(cctx)? };
+        let [input_offset, input_size, output_offset, output_size] = Instructions::pop_or_fail(cctx)?;
+        let target: Address = target.try_into()?;
+        if cctx.r#static && !CODE && value != U256::ZERO { return Err(Error::StaticStateChange); }
+        let ReadWriteOperation { result: input, extension_cost: input_extension_cost, .. } = cctx.memory.load(input_offset, input_size)?;
+        let input = input.to_vec();
+        let ReadWriteOperation { extension_cost: output_extension_cost, .. } = cctx.memory.load(output_offset, output_size)?;
+        let account = s.load_account(target)?;
+        let (code, delegation_cost) = s.load_code(target)?;
+        let cost = input_extension_cost + output_extension_cost + if account.warm { 100 } else { 2600 } + delegation_cost + match value {
+            U256::ZERO => 0,
+            _ => 9000 + if !CODE && account.value.is_empty() { 25000 } else { 0 },
+        };
+        if cctx.contract.gas < cost { return Err(Error::OutOfGas); }
+        cctx.returndata = vec![];
+
+        // EIP-150: all but one 64th of the remaining gas can be forwarded
+        let available = cctx.contract.gas - cost;
+        let gas = min(gas.try_into().unwrap_or(usize::MAX), available - available / 64);
+        let stipend = if value == U256::ZERO { 0 } else { 2300 };
+        let balance = s.load_account(cctx.contract.address)?.value.balance;
+        if cctx.depth >= MAX_CALL_DEPTH || balance < value {
+            Instructions::push_rev_or_fail(cctx, [U256::ZERO])?;
+            return Ok(InstructionOutput { cost, jump: 1 });
+        }
+
+        let eof = Container::from_code(&code);
+        let contract = match (CODE, DELEGATE) {
+            (_, true) => CallContextContract { address: cctx.contract.address, caller: cctx.contract.caller, code, eof, gas, input, logs: vec![], value: cctx.contract.value },
+            (true, false) => CallContextContract { address: cctx.contract.address, caller: cctx.contract.address, code, eof, gas: gas + stipend, input, logs: vec![], value },
+            (false, false) => CallContextContract { address: target, caller: cctx.contract.address, code, eof, gas: gas + stipend, input, logs: vec![], value },
+        };
+        let child = &mut CallContext {
+            depth: cctx.depth + 1,
+            r#static: cctx.r#static || STATIC,
+            memory: cctx.memory.enter_frame(),
+            transient: std::mem::take(&mut cctx.transient),
+            ..CallContext::new(contract)
+        };
+        let opcode = OpCode(match (CODE, DELEGATE, STATIC) { (_, true, _) => 0xF4, (true, false, _) => 0xF2, (false, false, true) => 0xFA, (false, false, false) => 0xF1 });
+        let result = Machine::execute_subcontext(s, tctx, child, if DELEGATE { U256::ZERO } else { value }, Precompile::at(target), opcode, target);
+        if let Err(Error::Backend(error)) = result { return Err(Error::Backend(error)); } // the state is missing, not the call failing
+        cctx.memory.leave_frame(std::mem::take(&mut child.memory));
+        cctx.transient = std::mem::take(&mut child.transient);
+        cctx.returndata = std::mem::take(&mut child.r#return);
+        let size = min(output_size, cctx.returndata.len().as_u256());
+        cctx.memory.store(output_offset, size, &cctx.returndata)?;
+        let success = result.is_ok() && !child.revert;
+        if success {
+            cctx.contract.logs.append(&mut child.contract.logs);
+        }
+        Instructions::push_rev_or_fail(cctx, [u256::from(success)])?;
+        Ok(InstructionOutput { cost: cost + gas - child.contract.gas, jump: 1 })
+    }
+
+    // EIP-7069 calls push 0 on success, 1 on revert or when the call could not be made, and 2 on failure
+    fn ext_call<const DELEGATE: bool, const STATIC: bool>(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        let [target, input_offset, input_size] = Instructions::pop_or_fail(cctx)?;
+        let [value] = if DELEGATE || STATIC { [U256::ZERO] } else { Instructions::pop_or_fail(cctx)? };
+        let target: Address = target.try_into()?;
+        if cctx.r#static && value != U256::ZERO { return Err(Error::StaticStateChange); }
+        let ReadWriteOperation { result: input, extension_cost, .. } = cctx.memory.load(input_offset, input_size)?;
+        let input = input.to_vec();
+        let account = s.load_account(target)?;
+        let (code, delegation_cost) = s.load_code(target)?;
+        let cost = extension_cost + if account.warm { 100 } else { 2600 } + delegation_cost + match value {
+            U256::ZERO => 0,
+            _ => 9000 + if account.value.is_empty() { 25000 } else { 0 },
+        };
+        if cctx.contract.gas < cost { return Err(Error::OutOfGas); }
+        cctx.returndata = vec![];
+
+        let available = cctx.contract.gas - cost;
+        let gas = available.saturating_sub(max(available / 64, 5000));
+        let balance = s.load_account(cctx.contract.address)?.value.balance;
+        let eof = Container::from_code(&code);
+        if gas < 2300 || cctx.depth >= MAX_CALL_DEPTH || balance < value || (DELEGATE && eof.is_none()) {
+            Instructions::push_rev_or_fail(cctx, [U256::ONE])?;
+            return Ok(InstructionOutput { cost, jump: 1 });
+        }
+
+        let contract = match DELEGATE {
+            true => CallContextContract { address: cctx.contract.address, caller: cctx.contract.caller, code, eof, gas, input, logs: vec![], value: cctx.contract.value },
+            false => CallContextContract { address: target, caller: cctx.contract.address, code, eof, gas, input, logs: vec![], value },
+        };
+        let child = &mut CallContext {
+            depth: cctx.depth + 1,
+            r#static: cctx.r#static || STATIC,
+            memory: cctx.memory.enter_frame(),
+            transient: std::mem::take(&mut cctx.transient),
+            ..CallContext::new(contract)
+        };
+        let opcode = OpCode(match (DELEGATE, STATIC) { (true, _) => 0xF9, (false, true) => 0xFB, (false, false) => 0xF8 });
+        let result = Machine::execute_subcontext(s, tctx, child, value, if DELEGATE { None } else { Precompile::at(target) }, opcode, target);
+        if let Err(Error::Backend(error)) = result { return Err(Error::Backend(error)); } // the state is missing, not the call failing
+        cctx.memory.leave_frame(std::mem::take(&mut child.memory));
+        cctx.transient = std::mem::take(&mut child.transient);
+        cctx.returndata = std::mem::take(&mut child.r#return);
+        let status: u8 = match result {
+            Err(_) => 2,
+            Ok(_) if child.revert => 1,
+            Ok(_) => { cctx.contract.logs.append(&mut child.contract.logs); 0 },
+        };
+        Instructions::push_rev_or_fail(cctx, [u256::from(status)])?;
+        Ok(InstructionOutput { cost: cost + gas - child.contract.gas, jump: 1 })
+    }
+
+    pub fn extcall(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::ext_call::<false, false>(s, tctx, cctx)
+    }
+
+    pub fn extdelegatecall(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::ext_call::<true, false>(s, tctx, cctx)
+    }
+
+    pub fn staticcall(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::legacy_call::<false, false, true>(s, tctx, cctx)
+    }
+
+    pub fn extstaticcall(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        Instructions::ext_call::<false, true>(s, tctx, cctx)
     }
 
     pub fn revert(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         cctx.stop = true;
         cctx.revert = true;
+        cctx.halt = Some(Halt::Revert);
         let [offset, size] = Instructions::pop_or_fail(cctx)?;
         let ReadWriteOperation { result: data, extension_cost, .. } = cctx.memory.load(offset, size)?;
-        cctx.r#return = data;
+        cctx.r#return = data.to_vec();
         Ok(InstructionOutput { cost: extension_cost, jump: 0 })
     }
 
     pub fn invalid(_s: &mut WorldState, _tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
         cctx.stop = true;
         cctx.revert = true;
+        cctx.halt = Some(Halt::InvalidOpcode(cctx.opcode()));
         Ok(InstructionOutput { cost: cctx.contract.gas, jump: 0 })
     }
 
-    pub fn selfdestruct(_s: &mut WorldState, _tctx: &TransactionContext, _cctx: &mut CallContext) -> InstructionResult {
-        todo!();
+    // EIP-6780: only accounts created in the same transaction get deleted, which is not tracked yet, so the balance is only moved
+    pub fn selfdestruct(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
+        if cctx.r#static { return Err(Error::StaticStateChange); }
+        cctx.stop = true;
+        let [beneficiary] = Instructions::pop_or_fail(cctx)?;
+        let beneficiary: Address = beneficiary.try_into()?;
+        let account = s.load_account(beneficiary)?;
+        let balance = s.load_account(cctx.contract.address)?.value.balance;
+        tctx.inspect(|inspector| inspector.selfdestruct(cctx, beneficiary, balance));
+        if beneficiary != cctx.contract.address {
+            s.decrease_balance(cctx.contract.address, balance)?;
+            s.increase_balance(beneficiary, balance)?;
+        }
+        let cost = 5000 + if account.warm { 0 } else { 2600 } + if balance != U256::ZERO && account.value.is_empty() { 25000 } else { 0 };
+        Ok(InstructionOutput { cost, jump: 0 })
     }
 }
 
@@ -593,6 +922,8 @@ impl Instructions {
 mod tests {
     use ethnum::uint;
     use super::*;
+    use std::sync::Arc;
+    use crate::blockchain::backend::{AccountInfo, BackendError, StateBackend};
     use crate::blockchain::primitives::{Account, Address, Block, Transaction};
     use crate::blockchain::storage::StorageValue;
     use crate::machine::context::CallContextContract;
@@ -614,14 +945,14 @@ mod tests {
         }
 
         fn with_memory(&mut self, memory: &str) {
-            self.memory = Memory(hex::decode(memory).unwrap());
+            self.memory = Memory::from(hex::decode(memory).unwrap());
         }
 
 
         fn with_transient<T: Into::<u256> + Copy>(&mut self, transient: &[(T, T)]) {
             self.transient = Default::default();
             for (key, value) in transient {
-                self.transient.0.insert(Into::<u256>::into(*key), Into::<u256>::into(*value));
+                self.transient.slots.insert(Into::<u256>::into(*key), Into::<u256>::into(*value));
             }
         }
 
@@ -632,6 +963,13 @@ mod tests {
         fn with_returndata(&mut self, memory: &str) {
             self.returndata = hex::decode(memory).unwrap();
         }
+
+        fn with_eof(&mut self, code: &str) {
+            let code = hex::decode(code).unwrap();
+            self.contract.eof = Container::parse(&code).ok();
+            self.contract.code = code.into();
+            self.pc = self.contract.eof.as_ref().unwrap().code_sections[0].start;
+        }
     }
 
     impl TransactionContext {
@@ -1203,7 +1541,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(uint!("0xF778B86FA74E846C4F0A1FBD1335FE81C00A0C91")),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1218,7 +1557,7 @@ mod tests {
         let state = &mut WorldState::default();
         let cctx = &mut CallContext::default();
 
-        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: uint!("125985"), code: vec![] })]);
+        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: uint!("125985"), code: Bytecode::default(), nonce: 0 })]);
 
         cctx.with_stack(vec![uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")]);
         assert_eq!(Instructions::balance(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2600, jump: 1 }));
@@ -1246,6 +1585,7 @@ mod tests {
         let tctx = &mut TransactionContext::default();
 
         tctx.with_transaction(Transaction {
+            authorization_list: vec![],
             data: vec![],
             from: Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")),
             gas: 0,
@@ -1255,7 +1595,7 @@ mod tests {
             value: U256::ZERO,
         });
 
-        assert_eq!(Instructions::origin(&mut WorldState::default(), &tctx, cctx), Ok(InstructionOutput { cost: 2, jump: 1 }));
+        assert_eq!(Instructions::origin(&mut WorldState::default(), tctx, cctx), Ok(InstructionOutput { cost: 2, jump: 1 }));
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")]);
     }
 
@@ -1266,7 +1606,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(uint!("0xF778B86FA74E846C4F0A1FBD1335FE81C00A0C91")),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1283,7 +1624,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1300,7 +1642,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap(),
             logs: vec![],
@@ -1323,7 +1666,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap(),
             logs: vec![],
@@ -1341,7 +1685,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap(),
             logs: vec![],
@@ -1350,11 +1695,11 @@ mod tests {
 
         cctx.with_stack(vec![0u8, 0, 32]);
         assert_eq!(Instructions::calldatacopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
 
         cctx.with_stack(vec![0u8, 31, 8]);
         assert_eq!(Instructions::calldatacopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FF00000000000000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FF00000000000000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
     }
 
     #[test]
@@ -1364,7 +1709,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap(),
+            code: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap().into(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1382,7 +1728,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap(),
+            code: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap().into(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1391,11 +1738,11 @@ mod tests {
 
         cctx.with_stack(vec![0u8, 0, 32]);
         assert_eq!(Instructions::codecopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
 
         cctx.with_stack(vec![0u8, 31, 8]);
         assert_eq!(Instructions::codecopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FF00000000000000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FF00000000000000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
     }
 
     #[test]
@@ -1404,6 +1751,7 @@ mod tests {
         let tctx = &mut TransactionContext::default();
 
         tctx.with_transaction(Transaction {
+            authorization_list: vec![],
             data: vec![],
             from: Address(U256::ZERO),
             gas: 0,
@@ -1413,7 +1761,7 @@ mod tests {
             value: U256::ZERO,
         });
 
-        assert_eq!(Instructions::gasprice(&mut WorldState::default(), &tctx, cctx), Ok(InstructionOutput { cost: 2, jump: 1 }));
+        assert_eq!(Instructions::gasprice(&mut WorldState::default(), tctx, cctx), Ok(InstructionOutput { cost: 2, jump: 1 }));
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [15]);
     }
 
@@ -1422,7 +1770,7 @@ mod tests {
         let state = &mut WorldState::default();
         let cctx = &mut CallContext::default();
 
-        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: U256::ZERO, code: hex::decode("FF0F4C").unwrap() })]);
+        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: U256::ZERO, code: hex::decode("FF0F4C").unwrap().into(), nonce: 0 })]);
 
         cctx.with_stack(vec![uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")]);
         assert_eq!(Instructions::extcodesize(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2600, jump: 1 }));
@@ -1449,15 +1797,15 @@ mod tests {
         let state = &mut WorldState::default();
         let cctx = &mut CallContext::default();
 
-        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: U256::ZERO, code: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap() })]);
+        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: U256::ZERO, code: hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap().into(), nonce: 0 })]);
 
         cctx.with_stack(vec![uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8"), U256::ZERO, U256::ZERO, uint!("32")]);
         assert_eq!(Instructions::extcodecopy(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2606, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
 
         cctx.with_stack(vec![uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8"), U256::ZERO, uint!("31"), uint!("8")]);
         assert_eq!(Instructions::extcodecopy(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 103, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FF00000000000000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FF00000000000000FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
     }
 
     #[test]
@@ -1478,11 +1826,11 @@ mod tests {
 
         cctx.with_stack(vec![0u8, 0, 32]);
         assert_eq!(Instructions::returndatacopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF").unwrap());
 
         cctx.with_stack(vec![32u8, 31, 1]);
         assert_eq!(Instructions::returndatacopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF00000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF00000000000000000000000000000000000000000000000000000000000000").unwrap());
     }
 
     #[test]
@@ -1491,8 +1839,8 @@ mod tests {
         let cctx = &mut CallContext::default();
 
         state.with_accounts(&[
-            (Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: uint!("125985"), code: vec![] }),
-            (Address(uint!("0xF778B86FA74E846C4F0A1FBD1335FE81C00A0C91")), Account { balance: uint!("125985"), code: vec![0xF0, 0xBD, 0x5A, 0x61, 0x9C, 0xAD, 0x26, 0x29] }),
+            (Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: uint!("125985"), code: Bytecode::default(), nonce: 0 }),
+            (Address(uint!("0xF778B86FA74E846C4F0A1FBD1335FE81C00A0C91")), Account { balance: uint!("125985"), code: vec![0xF0, 0xBD, 0x5A, 0x61, 0x9C, 0xAD, 0x26, 0x29].into(), nonce: 0 }),
         ]);
 
         cctx.with_stack(vec![uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")]);
@@ -1511,6 +1859,36 @@ mod tests {
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0xC5D2460186F7233C927E7DB2DCC703C0E500B653CA82273B7BFAD8045D85A470")]);
     }
 
+    #[test]
+    fn blockhash() {
+        struct Hashes;
+
+        impl StateBackend for Hashes {
+            fn basic(&self, _address: Address) -> Result<Option<AccountInfo>, BackendError> { Ok(None) }
+            fn code_by_hash(&self, _hash: u256) -> Result<Bytecode, BackendError> { Ok(Bytecode::default()) }
+            fn storage(&self, _address: Address, _key: u256) -> Result<u256, BackendError> { Ok(U256::ZERO) }
+            fn block_hash(&self, number: u256) -> u256 { number + 1000 }
+        }
+
+        let state = &mut WorldState { backend: Some(Arc::new(Hashes)), ..Default::default() };
+        let cctx = &mut CallContext::default();
+        let tctx = &mut TransactionContext::default();
+
+        tctx.with_block(Block {
+            difficulty: U256::ZERO,
+            gas_limit: U256::ZERO,
+            miner: Address(U256::ZERO),
+            number: uint!("300"),
+            time: U256::ZERO,
+        });
+
+        for (number, hash) in [(uint!("299"), uint!("1299")), (uint!("44"), uint!("1044")), (uint!("43"), uint!("0")), (uint!("300"), uint!("0"))] {
+            cctx.with_stack(vec![number]);
+            assert_eq!(Instructions::blockhash(state, tctx, cctx), Ok(InstructionOutput { cost: 20, jump: 1 }));
+            assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [hash]);
+        }
+    }
+
     #[test]
     fn coinbase() {
         let cctx = &mut CallContext::default();
@@ -1612,11 +1990,12 @@ mod tests {
         let state = &mut WorldState::default();
         let cctx = &mut CallContext::default();
 
-        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: uint!("125985"), code: vec![] })]);
+        state.with_accounts(&[(Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")), Account { balance: uint!("125985"), code: Bytecode::default(), nonce: 0 })]);
         cctx.with_contract(CallContextContract {
             address: Address(uint!("0x9BBFED6889322E016E0A02EE459D306FC19545D8")),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1647,25 +2026,25 @@ mod tests {
         cctx.with_stack(vec![0u8]);
         assert_eq!(Instructions::mload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 1 }));
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0x4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A02369")]);
-        assert_eq!(cctx.memory.0, hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A02369").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A02369").unwrap());
 
         cctx.with_memory("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A02369");
         cctx.with_stack(vec![2u8]);
         assert_eq!(Instructions::mload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0xB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000")]);
-        assert_eq!(cctx.memory.0, hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000000000000000000000000000000000000000000000000000000000000000").unwrap());
 
         cctx.with_memory("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A02369");
         cctx.with_stack(vec![30u8]);
         assert_eq!(Instructions::mload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0x2369000000000000000000000000000000000000000000000000000000000000")]);
-        assert_eq!(cctx.memory.0, hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000000000000000000000000000000000000000000000000000000000000000").unwrap());
 
         cctx.with_memory("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A02369");
         cctx.with_stack(vec![500u16]);
         assert_eq!(Instructions::mload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 51, jump: 1 }));
         assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [0]);
-        assert_eq!(cctx.memory.0, hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("4DBDB8BE3125A5DE53A0236934525103F67CF6E94DBDB8BE3125A5DE53A023690000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000").unwrap());
     }
 
     #[test]
@@ -1675,20 +2054,20 @@ mod tests {
         cctx.with_memory("");
         cctx.with_stack(vec![0u8, 0xFF]);
         assert_eq!(Instructions::mstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("00000000000000000000000000000000000000000000000000000000000000FF").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("00000000000000000000000000000000000000000000000000000000000000FF").unwrap());
         cctx.with_stack(vec![1u8, 0xFF]);
         assert_eq!(Instructions::mstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("0000000000000000000000000000000000000000000000000000000000000000FF00000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("0000000000000000000000000000000000000000000000000000000000000000FF00000000000000000000000000000000000000000000000000000000000000").unwrap());
 
         cctx.with_memory("");
         cctx.with_stack(vec![3u8, 0xFF]);
         assert_eq!(Instructions::mstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("00000000000000000000000000000000000000000000000000000000000000000000FF0000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("00000000000000000000000000000000000000000000000000000000000000000000FF0000000000000000000000000000000000000000000000000000000000").unwrap());
 
         cctx.with_memory("");
         cctx.with_stack(vec![500u16, 0xABFF]);
         assert_eq!(Instructions::mstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 54, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ABFF000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ABFF000000000000000000000000").unwrap());
     }
 
     #[test]
@@ -1698,10 +2077,10 @@ mod tests {
         cctx.with_memory("");
         cctx.with_stack(vec![0u16, 0xFFAB]);
         assert_eq!(Instructions::mstore8(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("AB00000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("AB00000000000000000000000000000000000000000000000000000000000000").unwrap());
         cctx.with_stack(vec![31u16, 0xFFAB]);
         assert_eq!(Instructions::mstore8(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("AB000000000000000000000000000000000000000000000000000000000000AB").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("AB000000000000000000000000000000000000000000000000000000000000AB").unwrap());
     }
 
     #[test]
@@ -1713,7 +2092,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(uint!("0xF778B86FA74E846C4F0A1FBD1335FE81C00A0C91")),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1744,7 +2124,8 @@ mod tests {
             address: Address(uint!("0xF778B86FA74E846C4F0A1FBD1335FE81C00A0C91")),
             caller: Address(U256::ZERO),
             gas: 0,
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             input: vec![],
             logs: vec![],
             value: U256::ZERO,
@@ -1800,7 +2181,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: hex::decode("00005B00").unwrap(),
+            code: hex::decode("00005B00").unwrap().into(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1823,6 +2205,11 @@ mod tests {
         cctx.with_stack(vec![2u8]);
         assert_eq!(Instructions::jump(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 8, jump: 0 }));
         assert_eq!(cctx.pc, 2);
+
+        cctx.contract.code = hex::decode("605B5B").unwrap().into();
+        cctx.with_stack(vec![1u8]);
+        assert_eq!(Instructions::jump(&mut WorldState::default(), &TransactionContext::default(), cctx), Err(Error::InvalidJumpDest)); // inside PUSH1 data
+        assert_eq!(cctx.pc, 2);
     }
 
     #[test]
@@ -1832,7 +2219,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: hex::decode("00005B00").unwrap(),
+            code: hex::decode("00005B00").unwrap().into(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1888,7 +2276,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 5,
             input: vec![],
             logs: vec![],
@@ -1902,7 +2291,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 3,
             input: vec![],
             logs: vec![],
@@ -1915,7 +2305,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 1,
             input: vec![],
             logs: vec![],
@@ -1928,7 +2319,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -1965,7 +2357,7 @@ mod tests {
 
         cctx.with_stack(vec![1u8, 55]);
         assert_eq!(Instructions::tstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 100, jump: 1 }));
-        assert_eq!(cctx.transient.0.get(&uint!("1")), Some(&uint!("55")));
+        assert_eq!(cctx.transient.slots.get(&uint!("1")), Some(&uint!("55")));
     }
 
     #[test]
@@ -1976,17 +2368,17 @@ mod tests {
 
         cctx.with_stack(vec![0u8, 32, 32]);
         assert_eq!(Instructions::mcopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("00010203040506070809101112131415161718192021222324252627282930310001020304050607080910111213141516171819202122232425262728293031").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("00010203040506070809101112131415161718192021222324252627282930310001020304050607080910111213141516171819202122232425262728293031").unwrap());
 
         cctx.with_stack(vec![4u8, 8, 16]);
         assert_eq!(Instructions::mcopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 6, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("00010203080910111213141516171819202122232021222324252627282930310001020304050607080910111213141516171819202122232425262728293031").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("00010203080910111213141516171819202122232021222324252627282930310001020304050607080910111213141516171819202122232425262728293031").unwrap());
 
         cctx.with_memory("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F");
 
         cctx.with_stack(vec![100u8, 4, 40]);
         assert_eq!(Instructions::mcopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 21, jump: 1 }));
-        assert_eq!(cctx.memory.0, hex::decode("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F0000000000000000000000000000000000000000000000000000000000000000").unwrap());
+        assert_eq!(cctx.memory.data(), hex::decode("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F0000000000000000000000000000000000000000000000000000000000000000").unwrap());
     }
 
     #[test]
@@ -1996,7 +2388,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: hex::decode("015936D2A1C5C3AF2EEB3155B96B3001A347D6FE75E51859EBBA8155131A8E0556").unwrap(),
+            code: hex::decode("015936D2A1C5C3AF2EEB3155B96B3001A347D6FE75E51859EBBA8155131A8E0556").unwrap().into(),
+            eof: None,
             gas: 0,
             input: vec![],
             logs: vec![],
@@ -2286,7 +2679,7 @@ mod tests {
         cctx.with_stack(vec![0u8, 2]);
 
         assert!(!cctx.stop);
-        assert_eq!(*cctx.returndata, vec![]);
+        assert!(cctx.returndata.is_empty());
         assert_eq!(Instructions::r#return(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 0, jump: 0 }));
         assert!(cctx.stop);
         assert_eq!(cctx.r#return, vec![0xFF, 1]);
@@ -2301,7 +2694,7 @@ mod tests {
 
         assert!(!cctx.stop);
         assert!(!cctx.revert);
-        assert_eq!(*cctx.returndata, vec![]);
+        assert!(cctx.returndata.is_empty());
         assert_eq!(Instructions::revert(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 0, jump: 0 }));
         assert!(cctx.stop);
         assert!(cctx.revert);
@@ -2315,7 +2708,8 @@ mod tests {
         cctx.with_contract(CallContextContract {
             address: Address(U256::ZERO),
             caller: Address(U256::ZERO),
-            code: vec![],
+            code: Bytecode::default(),
+            eof: None,
             gas: 25,
             input: vec![],
             logs: vec![],
@@ -2324,9 +2718,389 @@ mod tests {
 
         assert!(!cctx.stop);
         assert!(!cctx.revert);
-        assert_eq!(*cctx.returndata, vec![]);
+        assert!(cctx.returndata.is_empty());
         assert_eq!(Instructions::invalid(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 25, jump: 0 }));
         assert!(cctx.stop);
         assert!(cctx.revert);
     }
+
+    // Sections: DATALOADN 1 CALLF 1 JUMPF 1 STOP | PUSH0 RETF - data: AABBCC
+    const EOF_CONTAINER: &str = "ef0001010008020002000a0002ff0003000080000100010001d10001e30001e50001005fe4aabbcc";
+
+    #[test]
+    fn dataload() {
+        let cctx = &mut CallContext::default();
+        cctx.with_eof(EOF_CONTAINER);
+
+        cctx.with_stack(vec![1u8]);
+        assert_eq!(Instructions::dataload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0xBBCC000000000000000000000000000000000000000000000000000000000000")]);
+
+        cctx.with_stack(vec![uint!("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF")]);
+        assert_eq!(Instructions::dataload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0")]);
+    }
+
+    #[test]
+    fn dataloadn() {
+        let cctx = &mut CallContext::default();
+        cctx.with_eof(EOF_CONTAINER);
+
+        assert_eq!(Instructions::dataloadn(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 3 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0xBBCC000000000000000000000000000000000000000000000000000000000000")]);
+    }
+
+    #[test]
+    fn datasize() {
+        let cctx = &mut CallContext::default();
+        cctx.with_eof(EOF_CONTAINER);
+
+        assert_eq!(Instructions::datasize(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("3")]);
+    }
+
+    #[test]
+    fn datacopy() {
+        let cctx = &mut CallContext::default();
+        cctx.with_eof(EOF_CONTAINER);
+
+        cctx.with_stack(vec![0u8, 1, 4]);
+        assert_eq!(Instructions::datacopy(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9, jump: 1 }));
+        assert_eq!(cctx.memory.data(), hex::decode("BBCC000000000000000000000000000000000000000000000000000000000000").unwrap());
+    }
+
+    #[test]
+    fn rjump() {
+        let cctx = &mut CallContext::default();
+
+        cctx.with_contract(CallContextContract {
+            address: Address(U256::ZERO),
+            caller: Address(U256::ZERO),
+            code: hex::decode("E000020000E0FFFB").unwrap().into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+        assert_eq!(Instructions::rjump(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2, jump: 0 }));
+        assert_eq!(cctx.pc, 5);
+        assert_eq!(Instructions::rjump(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2, jump: 0 }));
+        assert_eq!(cctx.pc, 3);
+    }
+
+    #[test]
+    fn rjumpi() {
+        let cctx = &mut CallContext::default();
+
+        cctx.with_contract(CallContextContract {
+            address: Address(U256::ZERO),
+            caller: Address(U256::ZERO),
+            code: hex::decode("E100020000").unwrap().into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+        cctx.with_stack(vec![0u8]);
+        assert_eq!(Instructions::rjumpi(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 3 }));
+        assert_eq!(cctx.pc, 0);
+
+        cctx.with_stack(vec![2u8]);
+        assert_eq!(Instructions::rjumpi(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 0 }));
+        assert_eq!(cctx.pc, 5);
+    }
+
+    #[test]
+    fn rjumpv() {
+        let cctx = &mut CallContext::default();
+
+        cctx.with_contract(CallContextContract {
+            address: Address(U256::ZERO),
+            caller: Address(U256::ZERO),
+            code: hex::decode("E20100010003000000").unwrap().into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+        cctx.with_stack(vec![0u8]);
+        assert_eq!(Instructions::rjumpv(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 0 }));
+        assert_eq!(cctx.pc, 7);
+
+        cctx.with_pc(0);
+        cctx.with_stack(vec![1u8]);
+        assert_eq!(Instructions::rjumpv(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 0 }));
+        assert_eq!(cctx.pc, 9);
+
+        cctx.with_pc(0);
+        cctx.with_stack(vec![2u8]);
+        assert_eq!(Instructions::rjumpv(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 4, jump: 6 })); // out of the jump table
+        assert_eq!(cctx.pc, 0);
+    }
+
+    #[test]
+    fn callf_and_retf() {
+        let cctx = &mut CallContext::default();
+        cctx.with_eof(EOF_CONTAINER);
+        cctx.with_pc(28);
+
+        assert_eq!(Instructions::callf(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 5, jump: 0 }));
+        assert_eq!(cctx.pc, 35);
+        assert_eq!(cctx.return_stack, vec![31]);
+        assert_eq!(Instructions::retf(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 0 }));
+        assert_eq!(cctx.pc, 31);
+        assert!(cctx.return_stack.is_empty());
+
+        cctx.with_pc(28);
+        cctx.with_stack(vec![0u8; 1024]);
+        assert_eq!(Instructions::callf(&mut WorldState::default(), &TransactionContext::default(), cctx), Err(Error::StackOverflow));
+    }
+
+    #[test]
+    fn jumpf() {
+        let cctx = &mut CallContext::default();
+        cctx.with_eof(EOF_CONTAINER);
+        cctx.with_pc(31);
+
+        assert_eq!(Instructions::jumpf(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 5, jump: 0 }));
+        assert_eq!(cctx.pc, 35);
+        assert!(cctx.return_stack.is_empty());
+    }
+
+    #[test]
+    fn dupn() {
+        let cctx = &mut CallContext::default();
+        cctx.with_contract(CallContextContract {
+            address: Address(U256::ZERO),
+            caller: Address(U256::ZERO),
+            code: hex::decode("E601").unwrap().into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+
+        cctx.with_stack(vec![1u8, 2, 3]);
+        assert_eq!(Instructions::dupn(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 2 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [2, 1, 2, 3]);
+
+        cctx.with_stack(vec![1u8]);
+        assert_eq!(Instructions::dupn(&mut WorldState::default(), &TransactionContext::default(), cctx), Err(Error::EmptyStack));
+    }
+
+    #[test]
+    fn swapn() {
+        let cctx = &mut CallContext::default();
+        cctx.with_contract(CallContextContract {
+            address: Address(U256::ZERO),
+            caller: Address(U256::ZERO),
+            code: hex::decode("E701").unwrap().into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+
+        cctx.with_stack(vec![1u8, 2, 3]);
+        assert_eq!(Instructions::swapn(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 2 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [3, 2, 1]);
+    }
+
+    #[test]
+    fn exchange() {
+        let cctx = &mut CallContext::default();
+        cctx.with_contract(CallContextContract {
+            address: Address(U256::ZERO),
+            caller: Address(U256::ZERO),
+            code: hex::decode("E812").unwrap().into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+
+        cctx.with_stack(vec![0u8, 1, 2, 3, 4, 5]);
+        assert_eq!(Instructions::exchange(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 2 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [0, 1, 5, 3, 4, 2]);
+    }
+
+    #[test]
+    fn returndataload() {
+        let cctx = &mut CallContext::default();
+        cctx.with_returndata("AABB");
+
+        cctx.with_stack(vec![1u8]);
+        assert_eq!(Instructions::returndataload(&mut WorldState::default(), &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 3, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [uint!("0xBB00000000000000000000000000000000000000000000000000000000000000")]);
+    }
+
+    #[test]
+    fn static_state_changes() {
+        let cctx = &mut CallContext::default();
+        cctx.r#static = true;
+
+        cctx.with_stack(vec![0u8, 1]);
+        assert_eq!(Instructions::sstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Err(Error::StaticStateChange));
+        cctx.with_stack(vec![0u8, 1]);
+        assert_eq!(Instructions::tstore(&mut WorldState::default(), &TransactionContext::default(), cctx), Err(Error::StaticStateChange));
+        cctx.with_stack(vec![0u8, 0]);
+        assert_eq!(Instructions::log::<0>(&mut WorldState::default(), &TransactionContext::default(), cctx), Err(Error::StaticStateChange));
+    }
+
+    fn caller_contract() -> CallContextContract {
+        CallContextContract {
+            address: Address(uint!("0x1000000000000000000000000000000000000000")),
+            caller: Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C")),
+            code: Bytecode::default(),
+            eof: None,
+            gas: 100000,
+            input: vec![],
+            logs: vec![],
+            value: uint!("5"),
+        }
+    }
+
+    #[test]
+    fn call() {
+        let state = &mut WorldState::default();
+        let cctx = &mut CallContext::default();
+
+        state.with_accounts(&[
+            (Address(uint!("0x1000000000000000000000000000000000000000")), Account { balance: uint!("1"), code: Bytecode::default(), nonce: 1 }),
+            (Address(uint!("0x2000000000000000000000000000000000000000")), Account { balance: U256::ZERO, code: hex::decode("602A5F5260205FF3").unwrap().into(), nonce: 1 }), // return 42
+            (Address(uint!("0x3000000000000000000000000000000000000000")), Account { balance: U256::ZERO, code: hex::decode("FE").unwrap().into(), nonce: 1 }),
+        ]);
+        cctx.with_contract(caller_contract());
+
+        cctx.with_stack(vec![uint!("0xFFFF"), uint!("0x2000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0"), uint!("0"), uint!("32")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2619, jump: 1 })); // cold account - memory expansion - 16 gas used by the callee
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [1]);
+        assert_eq!(cctx.memory.data(), uint!("42").to_be_bytes().to_vec());
+        assert_eq!(cctx.returndata, uint!("42").to_be_bytes().to_vec());
+
+        // The forwarded gas is capped at all but one 64th of the remaining gas
+        cctx.with_stack(vec![uint!("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), uint!("0x3000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2600 + 95879, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [0]);
+        assert!(cctx.returndata.is_empty());
+
+        // The stipend is given on top of the forwarded gas, and returned to the caller when unused
+        cctx.with_stack(vec![uint!("0"), uint!("0x2000000000000000000000000000000000000000"), uint!("1"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 100 + 9000 + 16 - 2300, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [1]);
+        assert_eq!(state.accounts.0.get(&Address(uint!("0x2000000000000000000000000000000000000000"))).unwrap().value.balance, uint!("1"));
+
+        // Not enough balance to transfer the value
+        cctx.with_stack(vec![uint!("0"), uint!("0x2000000000000000000000000000000000000000"), uint!("1"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 9100, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [0]);
+
+        cctx.r#static = true;
+        cctx.with_stack(vec![uint!("0"), uint!("0x2000000000000000000000000000000000000000"), uint!("1"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Err(Error::StaticStateChange));
+    }
+
+    #[test]
+    fn call_depth_limit() {
+        let state = &mut WorldState::default();
+        let cctx = &mut CallContext::default();
+
+        state.with_accounts(&[
+            (Address(uint!("0x2000000000000000000000000000000000000000")), Account { balance: U256::ZERO, code: hex::decode("602A5F5260205FF3").unwrap().into(), nonce: 1 }), // return 42
+        ]);
+        cctx.with_contract(caller_contract());
+        cctx.depth = MAX_CALL_DEPTH - 1;
+
+        cctx.with_stack(vec![uint!("0xFFFF"), uint!("0x2000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2616, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [1]);
+
+        // The call fails without consuming the forwarded gas, and the caller keeps running
+        cctx.depth = MAX_CALL_DEPTH;
+        cctx.with_stack(vec![uint!("0xFFFF"), uint!("0x2000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::call(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 100, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [0]);
+        assert!(!cctx.stop);
+
+        cctx.with_stack(vec![uint!("0x2000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::extcall(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 100, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [1]);
+    }
+
+    #[test]
+    fn delegatecall() {
+        let state = &mut WorldState::default();
+        let cctx = &mut CallContext::default();
+
+        state.with_accounts(&[
+            (Address(uint!("0x2000000000000000000000000000000000000000")), Account { balance: U256::ZERO, code: hex::decode("33345F5260205260405FF3").unwrap().into(), nonce: 1 }), // return CALLER and CALLVALUE
+        ]);
+        cctx.with_contract(caller_contract());
+
+        cctx.with_stack(vec![uint!("0xFFFF"), uint!("0x2000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0"), uint!("64")]);
+        assert_eq!(Instructions::delegatecall(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2600 + 6 + 26, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [1]);
+        assert_eq!(cctx.memory.data(), [uint!("5").to_be_bytes(), uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C").to_be_bytes()].concat());
+    }
+
+    #[test]
+    fn staticcall() {
+        let state = &mut WorldState::default();
+        let cctx = &mut CallContext::default();
+
+        state.with_accounts(&[
+            (Address(uint!("0x2000000000000000000000000000000000000000")), Account { balance: U256::ZERO, code: hex::decode("60015F55").unwrap().into(), nonce: 1 }), // SSTORE 1 at 0
+        ]);
+        cctx.with_contract(caller_contract());
+
+        cctx.with_stack(vec![uint!("0xFFFF"), uint!("0x2000000000000000000000000000000000000000"), uint!("0"), uint!("0"), uint!("0"), uint!("0")]);
+        assert_eq!(Instructions::staticcall(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 2600 + 0xFFFF, jump: 1 }));
+        assert_eq!(Instructions::pop_or_fail(cctx).unwrap(), [0]);
+        assert!(!state.storage.contains_key(&Address(uint!("0x2000000000000000000000000000000000000000"))));
+    }
+
+    #[test]
+    fn selfdestruct() {
+        let state = &mut WorldState::default();
+        let cctx = &mut CallContext::default();
+
+        state.with_accounts(&[
+            (Address(uint!("0xC0")), Account { balance: uint!("100"), code: vec![0xFF].into(), nonce: 1 }),
+            (Address(uint!("0xB0")), Account { balance: uint!("1"), code: Bytecode::default(), nonce: 0 }),
+        ]);
+        cctx.with_contract(CallContextContract {
+            address: Address(uint!("0xC0")),
+            caller: Address(U256::ZERO),
+            code: vec![0xFF].into(),
+            eof: None,
+            gas: 0,
+            input: vec![],
+            logs: vec![],
+            value: U256::ZERO,
+        });
+        cctx.with_stack(vec![uint!("0xB0")]);
+
+        assert_eq!(Instructions::selfdestruct(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 7600, jump: 0 }));
+        assert!(cctx.stop);
+        assert_eq!(state.accounts.load(Address(uint!("0xC0"))).value.balance, uint!("0"));
+        assert_eq!(state.accounts.load(Address(uint!("0xC0"))).value.code, vec![0xFF].into());
+        assert_eq!(state.accounts.load(Address(uint!("0xB0"))).value.balance, uint!("101"));
+
+        state.increase_balance(Address(uint!("0xC0")), uint!("5")).unwrap();
+        cctx.with_stack(vec![uint!("0xB1")]);
+
+        assert_eq!(Instructions::selfdestruct(state, &TransactionContext::default(), cctx), Ok(InstructionOutput { cost: 32600, jump: 0 }));
+        assert_eq!(state.accounts.load(Address(uint!("0xB1"))).value.balance, uint!("5"));
+
+        cctx.with_stack(vec![uint!("0xB1")]);
+        cctx.r#static = true;
+
+        assert_eq!(Instructions::selfdestruct(state, &TransactionContext::default(), cctx), Err(Error::StaticStateChange));
+    }
 }
@@ -0,0 +1,786 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap};
+use std::mem::{offset_of, ManuallyDrop};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::isa::TargetFrontendConfig;
+use cranelift_codegen::ir::{types, AbiParam, Block, BlockArg, FuncRef, InstBuilder, MemFlagsData, Value};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Switch};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+use ethnum::{u256, AsU256};
+
+use crate::blockchain::WorldState;
+use crate::blockchain::bytecode::Bytecode;
+use crate::blockchain::errors::Error;
+use crate::machine::Machine;
+use crate::machine::context::{CallContext, TransactionContext};
+use crate::machine::opcode::{OpCode, INSTRUCTIONS};
+use crate::machine::stack::STACK_LIMIT;
+
+// Number of runs after which some code gets compiled
+const HOT_THRESHOLD: usize = 16;
+
+// Code longer than the EIP-3860 initcode limit is left to the interpreter
+const MAX_CODE_SIZE: usize = 49152;
+
+// Compiled code kept per thread, and code whose runs are counted, before starting over
+const MAX_COMPILED: usize = 256;
+const MAX_COUNTED: usize = 4096;
+
+thread_local! {
+    // Dropped with its thread, which frees the compiled code that is not running anymore
+    static JIT: RefCell<Option<Jit>> = const { RefCell::new(None) };
+}
+
+// What compiled code runs against. It reads the pointers to the gas, pc and stack of the call context from here to
+// work on them in place, and hands the host back to `step`
+#[repr(C)]
+pub struct Host<'a> {
+    cctx: *mut CallContext,
+    // Gas that compiled code charged since the sender last paid for it
+    charged: usize,
+    gas: *mut usize,
+    len: *mut usize,
+    panic: Option<Box<dyn Any + Send>>,
+    pc: *mut usize,
+    prepaid: *mut usize,
+    result: Result<(), Error>,
+    s: &'a mut WorldState,
+    stack: *mut u256,
+    tctx: &'a TransactionContext,
+}
+
+impl Host<'_> {
+    // At depth 0, the sender pays for gas as it gets charged. Compiled code leaves that to the next instruction that
+    // runs in the interpreter, or to its return, as none of its own instructions can read a balance
+    fn settle(&mut self) -> Result<(), Error> {
+        let charged = std::mem::take(&mut self.charged);
+        // SAFETY: the call context outlives the run, and compiled code is not running while the host is used
+        if charged != 0 && unsafe { (*self.cctx).depth } == 0 {
+            self.s.decrease_balance(self.tctx.tx.from, (charged * self.tctx.tx.gas_price).as_u256())?;
+        }
+        Ok(())
+    }
+}
+
+// Runs the instruction at pc in the interpreter, with `prepaid` gas left from its basic block, and returns whether
+// compiled code goes on. It does not once the code halts or fails, or when the interpreter gave the prepaid gas back
+// to meter the rest of the block one instruction at a time. Panics cannot unwind through compiled code, so they are
+// resumed once back in Rust
+extern "C" fn step(host: &mut Host, pc: usize, prepaid: usize) -> bool {
+    if let Err(error) = host.settle() {
+        host.result = Err(error);
+        return false;
+    }
+    // SAFETY: see `settle`
+    let cctx = unsafe { &mut *host.cctx };
+    let info = &INSTRUCTIONS.0[cctx.contract.code.padded()[pc] as usize];
+    (cctx.pc, cctx.prepaid_gas) = (pc, prepaid);
+    let (s, tctx) = (&mut *host.s, host.tctx);
+    match panic::catch_unwind(AssertUnwindSafe(|| Machine::execute_instruction(s, tctx, cctx, info))) {
+        Ok(Ok(())) => !cctx.stop && cctx.prepaid_gas == prepaid - info.static_gas,
+        Ok(Err(error)) => {
+            host.result = Err(error);
+            false
+        },
+        Err(payload) => {
+            host.panic = Some(payload);
+            false
+        },
+    }
+}
+
+// Owns the memory of some compiled code, which JITModule does not free when dropped
+struct CodeMemory(ManuallyDrop<JITModule>);
+
+impl Drop for CodeMemory {
+    fn drop(&mut self) {
+        // SAFETY: the code is only called through a CompiledCode, which keeps its memory alive
+        unsafe { ManuallyDrop::take(&mut self.0).free_memory() };
+    }
+}
+
+#[derive(Clone)]
+pub struct CompiledCode {
+    function: extern "C" fn(&mut Host),
+    _memory: Rc<CodeMemory>,
+}
+
+impl CompiledCode {
+    // Runs from pc until the code halts or fails, or until it leaves an instruction to the interpreter, which carries
+    // on from the pc, prepaid gas and stack that it is left with
+    pub fn run(&self, s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> Result<(), Error> {
+        let (stack, len) = cctx.stack.raw_parts();
+        let (gas, pc, prepaid) = (&raw mut cctx.contract.gas, &raw mut cctx.pc, &raw mut cctx.prepaid_gas);
+        let mut host = Host { cctx, charged: 0, gas, len, panic: None, pc, prepaid, result: Ok(()), s, stack, tctx };
+        (self.function)(&mut host);
+        if let Some(payload) = host.panic.take() { panic::resume_unwind(payload); }
+        let settled = host.settle();
+        host.result.and(settled)
+    }
+}
+
+#[derive(Default)]
+struct Jit {
+    compiled: HashMap<u256, Option<CompiledCode>>,
+    runs: HashMap<u256, usize>,
+}
+
+impl Jit {
+    // Each code gets its own module, so that it can be freed on its own
+    fn compile(code: &Bytecode) -> Option<CompiledCode> {
+        // Compiled code reads stack items as two native halves, and pc, gas and stack sizes as 64-bit integers
+        if code.is_empty() || code.len() > MAX_CODE_SIZE || cfg!(target_endian = "big") || usize::BITS != 64 { return None; }
+
+        let mut builder = JITBuilder::new(default_libcall_names()).expect("the host machine is supported by Cranelift");
+        builder.symbol("step", step as *const u8);
+        let mut module = JITModule::new(builder);
+        let function = Jit::define(&mut module, code);
+        let memory = Rc::new(CodeMemory(ManuallyDrop::new(module)));
+        Some(CompiledCode { function: function?, _memory: memory })
+    }
+
+    fn define(module: &mut JITModule, code: &Bytecode) -> Option<extern "C" fn(&mut Host)> {
+        let mut step_signature = module.make_signature();
+        step_signature.params.extend([AbiParam::new(types::I64); 3]);
+        step_signature.returns.push(AbiParam::new(types::I8));
+        let step_id = module.declare_function("step", Linkage::Import, &step_signature).ok()?;
+
+        let mut ctx = module.make_context();
+        ctx.func.signature.params.push(AbiParam::new(types::I64));
+        let mut builder_context = FunctionBuilderContext::new();
+        let builder = FunctionBuilder::new(&mut ctx.func, &mut builder_context);
+        let step = module.declare_func_in_func(step_id, builder.func);
+        Translator::new(builder, code, step).translate(module.target_config());
+
+        let id = module.declare_anonymous_function(&ctx.func.signature).ok()?;
+        module.define_function(id, &mut ctx).ok()?;
+        module.clear_context(&mut ctx);
+        module.finalize_definitions().ok()?;
+        let function = module.get_finalized_function(id);
+        // SAFETY: the function was compiled with the signature of CompiledCode
+        Some(unsafe { std::mem::transmute::<*const u8, extern "C" fn(&mut Host)>(function) })
+    }
+}
+
+fn push_size(opcode: u8) -> usize {
+    if (0x60..=0x7F).contains(&opcode) { usize::from(opcode - 0x5F) } else { 0 }
+}
+
+// A stack item held in registers as its low and high halves, along with its value if known at compile time
+#[derive(Clone, Copy)]
+struct Item {
+    constant: Option<u256>,
+    // Whether the stack does not hold it yet
+    dirty: bool,
+    hi: Value,
+    lo: Value,
+}
+
+// Compiles legacy code one basic block at a time. A block checks on entry that the stack has the items it pops and
+// room for the ones it pushes, and pays its static gas like the interpreter does, or else leaves the whole block to the
+// interpreter. Its stack, arithmetic, comparison, bitwise and jump instructions then run natively on items kept in
+// registers, which get written back to the stack before any other instruction calls back into the interpreter
+struct Translator<'a> {
+    // Address of the item right above the stack on entry to the current block
+    base: Value,
+    builder: FunctionBuilder<'a>,
+    code: &'a Bytecode,
+    // Items pushed by the current block so far, minus the ones it popped
+    delta: i64,
+    exit: Block,
+    // Items that the current block read or wrote, by position from `base`
+    items: BTreeMap<i64, Item>,
+    host: Value,
+    // Shared by the jumps whose destination is not known at compile time
+    jumps: Block,
+    labels: BTreeMap<usize, Block>,
+    // Stack size on entry to the current block
+    len: Value,
+    pointers: [Value; 5],
+    step: FuncRef,
+}
+
+const GAS: usize = 0;
+const LEN: usize = 1;
+const PC: usize = 2;
+const PREPAID: usize = 3;
+const STACK: usize = 4;
+
+impl<'a> Translator<'a> {
+    fn new(mut builder: FunctionBuilder<'a>, code: &'a Bytecode, step: FuncRef) -> Self {
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let host = builder.block_params(entry)[0];
+        let pointers = [offset_of!(Host, gas), offset_of!(Host, len), offset_of!(Host, pc), offset_of!(Host, prepaid), offset_of!(Host, stack)]
+            .map(|offset| builder.ins().load(types::I64, MemFlagsData::trusted(), host, offset as i32));
+
+        // Destination, pc, prepaid gas, number of popped operands, then the destination and the condition as halves
+        let jumps = builder.create_block();
+        for ty in [types::I64, types::I64, types::I64, types::I64, types::I128, types::I128, types::I128, types::I128] {
+            builder.append_block_param(jumps, ty);
+        }
+        let labels = code.blocks().iter().map(|start| (*start, builder.create_block())).collect();
+        let exit = builder.create_block();
+        Self { base: host, builder, code, delta: 0, exit, items: BTreeMap::new(), host, jumps, labels, len: host, pointers, step }
+    }
+
+    // Compiled code is entered at the start of a basic block, and returns right away from any other pc
+    fn translate(mut self, config: TargetFrontendConfig) {
+        let pc = self.load(self.pointers[PC]);
+        let mut switch = Switch::new();
+        for (start, label) in &self.labels {
+            switch.set_entry(*start as u128, *label);
+        }
+        switch.emit(&mut self.builder, pc, self.exit);
+
+        let starts: Vec<usize> = self.labels.keys().copied().collect();
+        for (i, start) in starts.iter().enumerate() {
+            self.block(*start, starts.get(i + 1).copied().unwrap_or(self.code.len()));
+        }
+        self.dispatch_jumps();
+
+        self.builder.switch_to_block(self.exit);
+        self.builder.ins().return_(&[]);
+        self.builder.seal_all_blocks();
+        self.builder.finalize(config);
+    }
+
+    fn block(&mut self, start: usize, end: usize) {
+        self.builder.switch_to_block(self.labels[&start]);
+        let mut instructions = vec![];
+        let mut pc = start;
+        while pc < end {
+            instructions.push((pc, self.code[pc]));
+            pc += 1 + push_size(self.code[pc]);
+        }
+        let Some((needed, pushed)) = Translator::stack_bounds(&instructions) else { return self.exit_at(start, 0) };
+
+        let block_gas = self.code.block_gas(start);
+        let len = self.load(self.pointers[LEN]);
+        let gas = self.load(self.pointers[GAS]);
+        let too_few = self.builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, len, needed as i64);
+        let too_many = self.builder.ins().icmp_imm_u(IntCC::UnsignedGreaterThan, len, (STACK_LIMIT - pushed) as i64);
+        let too_poor = self.builder.ins().icmp_imm_u(IntCC::UnsignedLessThan, gas, block_gas as i64);
+        let stack_fails = self.builder.ins().bor(too_few, too_many);
+        let fails = self.builder.ins().bor(stack_fails, too_poor);
+        let (bail, body) = (self.builder.create_block(), self.builder.create_block());
+        self.builder.ins().brif(fails, bail, &[], body, &[]);
+        self.builder.switch_to_block(bail);
+        self.exit_at(start, 0);
+
+        self.builder.switch_to_block(body);
+        if block_gas != 0 {
+            let gas = self.builder.ins().iadd_imm_s(gas, -(block_gas as i64));
+            self.builder.ins().store(MemFlagsData::trusted(), gas, self.pointers[GAS], 0);
+            let charged = self.builder.ins().load(types::I64, MemFlagsData::trusted(), self.host, offset_of!(Host, charged) as i32);
+            let charged = self.builder.ins().iadd_imm_s(charged, block_gas as i64);
+            self.builder.ins().store(MemFlagsData::trusted(), charged, self.host, offset_of!(Host, charged) as i32);
+        }
+        let offset = self.builder.ins().ishl_imm_u(len, 5);
+        (self.base, self.len, self.delta) = (self.builder.ins().iadd(self.pointers[STACK], offset), len, 0);
+        self.items.clear();
+
+        // Gas prepaid for the instructions left in the block, which the interpreter expects to find
+        let mut prepaid = block_gas;
+        for (pc, opcode) in instructions {
+            match opcode {
+                0x56 | 0x57 => return self.jump(pc, prepaid, end, opcode == 0x57),
+                _ if self.native(pc, opcode) => {},
+                _ => self.call_back(pc, opcode, prepaid),
+            }
+            prepaid -= OpCode(opcode).static_gas();
+        }
+        self.flush();
+        self.fall_through(end);
+    }
+
+    // Items that the instructions need on the stack, and the most that they have pushed at any point, unless one of
+    // them is undefined
+    fn stack_bounds(instructions: &[(usize, u8)]) -> Option<(usize, usize)> {
+        let (mut depth, mut needed, mut pushed) = (0i64, 0i64, 0i64);
+        for (_, opcode) in instructions {
+            let (pops, pushes) = OpCode(*opcode).stack_io()?;
+            needed = max(needed, pops as i64 - depth);
+            depth += pushes as i64 - pops as i64;
+            pushed = max(pushed, depth);
+        }
+        (pushed as usize <= STACK_LIMIT).then_some((needed as usize, pushed as usize))
+    }
+
+    fn native(&mut self, pc: usize, opcode: u8) -> bool {
+        match opcode {
+            0x01 => self.binary(Translator::add),
+            0x02 => self.binary(Translator::mul),
+            0x03 => self.binary(Translator::sub),
+            0x10 => self.binary(|t, a, b| t.less(a, b, false)),
+            0x11 => self.binary(|t, a, b| t.less(b, a, false)),
+            0x12 => self.binary(|t, a, b| t.less(a, b, true)),
+            0x13 => self.binary(|t, a, b| t.less(b, a, true)),
+            0x14 => self.binary(|t, a, b| {
+                let (lo, hi) = (t.builder.ins().icmp(IntCC::Equal, a.lo, b.lo), t.builder.ins().icmp(IntCC::Equal, a.hi, b.hi));
+                let equal = t.builder.ins().band(lo, hi);
+                t.boolean(equal)
+            }),
+            0x15 => {
+                let a = self.pop();
+                let any = self.builder.ins().bor(a.lo, a.hi);
+                let zero = self.wide(0);
+                let zero = self.builder.ins().icmp(IntCC::Equal, any, zero);
+                let (lo, hi) = self.boolean(zero);
+                self.push(lo, hi, None);
+            },
+            0x16 => self.binary(|t, a, b| (t.builder.ins().band(a.lo, b.lo), t.builder.ins().band(a.hi, b.hi))),
+            0x17 => self.binary(|t, a, b| (t.builder.ins().bor(a.lo, b.lo), t.builder.ins().bor(a.hi, b.hi))),
+            0x18 => self.binary(|t, a, b| (t.builder.ins().bxor(a.lo, b.lo), t.builder.ins().bxor(a.hi, b.hi))),
+            0x19 => {
+                let a = self.pop();
+                let (lo, hi) = (self.builder.ins().bnot(a.lo), self.builder.ins().bnot(a.hi));
+                self.push(lo, hi, None);
+            },
+            0x50 => { self.pop(); },
+            0x58 => self.constant(pc.as_u256()),
+            0x5B => {},
+            0x5F..=0x7F => {
+                let size = push_size(opcode);
+                let mut bytes = [0; 32];
+                bytes[32 - size..].copy_from_slice(&self.code.padded()[pc + 1..pc + 1 + size]);
+                self.constant(u256::from_be_bytes(bytes));
+            },
+            0x80..=0x8F => {
+                let item = self.peek(usize::from(opcode - 0x80));
+                self.push(item.lo, item.hi, item.constant);
+            },
+            0x90..=0x9F => {
+                let n = i64::from(opcode - 0x8F);
+                let (top, other) = (self.peek(0), self.peek(n as usize));
+                self.items.insert(self.delta - 1, Item { dirty: true, ..other });
+                self.items.insert(self.delta - 1 - n, Item { dirty: true, ..top });
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    // Writes the items back to the stack, and runs the instruction in the interpreter
+    fn call_back(&mut self, pc: usize, opcode: u8, prepaid: usize) {
+        self.flush();
+        let (pc, prepaid) = (self.builder.ins().iconst(types::I64, pc as i64), self.builder.ins().iconst(types::I64, prepaid as i64));
+        let call = self.builder.ins().call(self.step, &[self.host, pc, prepaid]);
+        let proceed = self.builder.inst_results(call)[0];
+        let next = self.builder.create_block();
+        self.builder.ins().brif(proceed, next, &[], self.exit, &[]);
+        self.builder.switch_to_block(next);
+
+        self.items.clear();
+        let (pops, pushes) = OpCode(opcode).stack_io().unwrap();
+        self.delta += pushes as i64 - pops as i64;
+    }
+
+    // Takes jumps to destinations known at compile time directly, and the others through `jumps`
+    fn jump(&mut self, pc: usize, prepaid: usize, end: usize, conditional: bool) {
+        let destination = self.pop();
+        let condition = conditional.then(|| self.pop());
+        self.flush();
+        if let Some(condition) = condition {
+            let any = self.builder.ins().bor(condition.lo, condition.hi);
+            let zero = self.wide(0);
+            let taken = self.builder.ins().icmp(IntCC::NotEqual, any, zero);
+            let (jump, skip) = (self.builder.create_block(), self.builder.create_block());
+            self.builder.ins().brif(taken, jump, &[], skip, &[]);
+            self.builder.switch_to_block(skip);
+            self.fall_through(end);
+            self.builder.switch_to_block(jump);
+        }
+
+        let valid = destination.constant.map(|destination| usize::try_from(destination).ok().filter(|destination| self.code.is_jumpdest(*destination)));
+        if let Some(Some(destination)) = valid {
+            self.builder.ins().jump(self.labels[&destination], &[]);
+            return;
+        }
+
+        // Destinations out of the code are none of its JUMPDESTs
+        let length = self.wide(self.code.len() as u128);
+        let zero = self.wide(0);
+        let high = self.builder.ins().icmp(IntCC::Equal, destination.hi, zero);
+        let low = self.builder.ins().icmp(IntCC::UnsignedLessThan, destination.lo, length);
+        let in_code = self.builder.ins().band(high, low);
+        let (low, outside) = (self.builder.ins().ireduce(types::I64, destination.lo), self.builder.ins().iconst(types::I64, self.code.len() as i64));
+        let target = self.builder.ins().select(in_code, low, outside);
+        let condition = condition.unwrap_or(destination);
+        let args = [target, self.builder.ins().iconst(types::I64, pc as i64), self.builder.ins().iconst(types::I64, prepaid as i64),
+            self.builder.ins().iconst(types::I64, if conditional { 2 } else { 1 }), destination.lo, destination.hi, condition.lo, condition.hi];
+        self.builder.ins().jump(self.jumps, &args.map(BlockArg::Value));
+    }
+
+    // Invalid jumps are left to the interpreter, with their operands back on the stack, so that it fails them
+    fn dispatch_jumps(&mut self) {
+        self.builder.switch_to_block(self.jumps);
+        let [target, pc, prepaid, popped, destination_lo, destination_hi, condition_lo, condition_hi] = self.builder.block_params(self.jumps)[..] else { unreachable!() };
+        let invalid = self.builder.create_block();
+        let mut switch = Switch::new();
+        for (start, label) in &self.labels {
+            if self.code.is_jumpdest(*start) { switch.set_entry(*start as u128, *label); }
+        }
+        switch.emit(&mut self.builder, target, invalid);
+
+        self.builder.switch_to_block(invalid);
+        let len = self.load(self.pointers[LEN]);
+        let offset = self.builder.ins().ishl_imm_u(len, 5);
+        let base = self.builder.ins().iadd(self.pointers[STACK], offset);
+        // The condition is overwritten when there is none
+        self.builder.ins().store(MemFlagsData::trusted(), condition_lo, base, 0);
+        self.builder.ins().store(MemFlagsData::trusted(), condition_hi, base, 16);
+        let offset = self.builder.ins().iadd_imm_s(popped, -1);
+        let offset = self.builder.ins().ishl_imm_u(offset, 5);
+        let top = self.builder.ins().iadd(base, offset);
+        self.builder.ins().store(MemFlagsData::trusted(), destination_lo, top, 0);
+        self.builder.ins().store(MemFlagsData::trusted(), destination_hi, top, 16);
+        let len = self.builder.ins().iadd(len, popped);
+        self.builder.ins().store(MemFlagsData::trusted(), len, self.pointers[LEN], 0);
+        self.builder.ins().store(MemFlagsData::trusted(), pc, self.pointers[PC], 0);
+        self.builder.ins().store(MemFlagsData::trusted(), prepaid, self.pointers[PREPAID], 0);
+        self.builder.ins().jump(self.exit, &[]);
+    }
+
+    // Goes on with the block at `end`, once the items are written back to the stack
+    fn fall_through(&mut self, end: usize) {
+        match self.labels.get(&end) {
+            Some(next) => { self.builder.ins().jump(*next, &[]); },
+            None => self.exit_at(end, 0), // the STOP past the end of the code
+        }
+    }
+
+    // Leaves the instruction at pc to the interpreter, once the items are written back to the stack
+    fn exit_at(&mut self, pc: usize, prepaid: usize) {
+        for (pointer, value) in [(self.pointers[PC], pc), (self.pointers[PREPAID], prepaid)] {
+            let value = self.builder.ins().iconst(types::I64, value as i64);
+            self.builder.ins().store(MemFlagsData::trusted(), value, pointer, 0);
+        }
+        self.builder.ins().jump(self.exit, &[]);
+    }
+
+    fn load(&mut self, pointer: Value) -> Value {
+        self.builder.ins().load(types::I64, MemFlagsData::trusted(), pointer, 0)
+    }
+
+    fn wide(&mut self, value: u128) -> Value {
+        let lo = self.builder.ins().iconst(types::I64, value as u64 as i64);
+        let hi = self.builder.ins().iconst(types::I64, (value >> 64) as u64 as i64);
+        self.builder.ins().iconcat(lo, hi)
+    }
+
+    fn constant(&mut self, value: u256) {
+        let (lo, hi) = (self.wide(*value.low()), self.wide(*value.high()));
+        self.push(lo, hi, Some(value));
+    }
+
+    // Reads the n-th item from the top, from the stack unless it is in registers already
+    fn peek(&mut self, n: usize) -> Item {
+        let position = self.delta - 1 - n as i64;
+        if let Some(item) = self.items.get(&position) { return *item; }
+        let lo = self.builder.ins().load(types::I128, MemFlagsData::trusted(), self.base, (position * 32) as i32);
+        let hi = self.builder.ins().load(types::I128, MemFlagsData::trusted(), self.base, (position * 32 + 16) as i32);
+        let item = Item { constant: None, dirty: false, hi, lo };
+        self.items.insert(position, item);
+        item
+    }
+
+    fn pop(&mut self) -> Item {
+        let item = self.peek(0);
+        self.delta -= 1;
+        self.items.remove(&self.delta);
+        item
+    }
+
+    fn push(&mut self, lo: Value, hi: Value, constant: Option<u256>) {
+        self.items.insert(self.delta, Item { constant, dirty: true, hi, lo });
+        self.delta += 1;
+    }
+
+    fn binary(&mut self, operation: impl FnOnce(&mut Self, Item, Item) -> (Value, Value)) {
+        let (a, b) = (self.pop(), self.pop());
+        let (lo, hi) = operation(self, a, b);
+        self.push(lo, hi, None);
+    }
+
+    fn flush(&mut self) {
+        let delta = self.delta;
+        self.items.retain(|position, _| *position < delta);
+        for (position, item) in self.items.iter_mut().filter(|(_, item)| item.dirty) {
+            self.builder.ins().store(MemFlagsData::trusted(), item.lo, self.base, (position * 32) as i32);
+            self.builder.ins().store(MemFlagsData::trusted(), item.hi, self.base, (position * 32 + 16) as i32);
+            item.dirty = false;
+        }
+        let len = self.builder.ins().iadd_imm_s(self.len, delta);
+        self.builder.ins().store(MemFlagsData::trusted(), len, self.pointers[LEN], 0);
+    }
+
+    fn boolean(&mut self, condition: Value) -> (Value, Value) {
+        (self.builder.ins().uextend(types::I128, condition), self.wide(0))
+    }
+
+    fn add(&mut self, a: Item, b: Item) -> (Value, Value) {
+        let lo = self.builder.ins().iadd(a.lo, b.lo);
+        let carry = self.builder.ins().icmp(IntCC::UnsignedLessThan, lo, a.lo);
+        let carry = self.builder.ins().uextend(types::I128, carry);
+        let hi = self.builder.ins().iadd(a.hi, b.hi);
+        (lo, self.builder.ins().iadd(hi, carry))
+    }
+
+    fn sub(&mut self, a: Item, b: Item) -> (Value, Value) {
+        let lo = self.builder.ins().isub(a.lo, b.lo);
+        let borrow = self.builder.ins().icmp(IntCC::UnsignedLessThan, a.lo, b.lo);
+        let borrow = self.builder.ins().uextend(types::I128, borrow);
+        let hi = self.builder.ins().isub(a.hi, b.hi);
+        (lo, self.builder.ins().isub(hi, borrow))
+    }
+
+    // Schoolbook multiplication of the 64-bit limbs, keeping the low 256 bits
+    fn mul(&mut self, a: Item, b: Item) -> (Value, Value) {
+        let limbs = |t: &mut Self, item: Item| {
+            let (l0, l1) = t.builder.ins().isplit(item.lo);
+            let (l2, l3) = t.builder.ins().isplit(item.hi);
+            [l0, l1, l2, l3]
+        };
+        let (a, b) = (limbs(self, a), limbs(self, b));
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        let mut result = [zero; 4];
+        for i in 0..4 {
+            let mut carry = zero;
+            for j in 0..4 - i {
+                let low = self.builder.ins().imul(a[i], b[j]);
+                let sum = self.builder.ins().iadd(result[i + j], low);
+                let first = self.builder.ins().icmp(IntCC::UnsignedLessThan, sum, low);
+                result[i + j] = self.builder.ins().iadd(sum, carry);
+                if i + j == 3 { continue; }
+                let second = self.builder.ins().icmp(IntCC::UnsignedLessThan, result[i + j], carry);
+                let high = self.builder.ins().umulhi(a[i], b[j]);
+                let (first, second) = (self.builder.ins().uextend(types::I64, first), self.builder.ins().uextend(types::I64, second));
+                let high = self.builder.ins().iadd(high, first);
+                carry = self.builder.ins().iadd(high, second);
+            }
+        }
+        (self.builder.ins().iconcat(result[0], result[1]), self.builder.ins().iconcat(result[2], result[3]))
+    }
+
+    // Whether a is less than b, as a 256-bit item
+    fn less(&mut self, a: Item, b: Item, signed: bool) -> (Value, Value) {
+        let high = self.builder.ins().icmp(if signed { IntCC::SignedLessThan } else { IntCC::UnsignedLessThan }, a.hi, b.hi);
+        let equal = self.builder.ins().icmp(IntCC::Equal, a.hi, b.hi);
+        let low = self.builder.ins().icmp(IntCC::UnsignedLessThan, a.lo, b.lo);
+        let low = self.builder.ins().band(equal, low);
+        let less = self.builder.ins().bor(high, low);
+        self.boolean(less)
+    }
+}
+
+// Compiles some legacy code on the current thread, or returns None if it cannot be compiled
+pub fn compile(code: &Bytecode) -> Option<CompiledCode> {
+    JIT.with_borrow_mut(|jit| {
+        let jit = jit.get_or_insert_with(Jit::default);
+        if let Some(compiled) = jit.compiled.get(&code.hash()) { return compiled.clone(); }
+
+        // Code that is still running keeps its memory until it returns
+        if jit.compiled.len() >= MAX_COMPILED { jit.compiled.clear(); }
+        let compiled = Jit::compile(code);
+        jit.runs.remove(&code.hash());
+        jit.compiled.insert(code.hash(), compiled.clone());
+        compiled
+    })
+}
+
+// Counts a run of some legacy code, and returns its compiled version once it has become hot
+pub fn lookup(code: &Bytecode) -> Option<CompiledCode> {
+    let hot = JIT.with_borrow_mut(|jit| {
+        let jit = jit.get_or_insert_with(Jit::default);
+        if let Some(compiled) = jit.compiled.get(&code.hash()) { return Err(compiled.clone()); }
+
+        if jit.runs.len() >= MAX_COUNTED && !jit.runs.contains_key(&code.hash()) { jit.runs.clear(); }
+        let runs = jit.runs.entry(code.hash()).or_default();
+        *runs += 1;
+        Ok(*runs >= HOT_THRESHOLD)
+    });
+
+    match hot {
+        Err(compiled) => compiled,
+        Ok(true) => compile(code),
+        Ok(false) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use super::*;
+    use crate::blockchain::backend::StateBackend;
+    use crate::blockchain::primitives::{Account, Address, Block, Transaction};
+    use crate::evm::Evm;
+    use crate::machine::context::CallContextContract;
+
+    // Sums 1 to 10 in a loop, stores the result in memory and returns it
+    const LOOP: &str = "600a5f5b81019060019003908160035760005260205ff3";
+
+    fn context(code: &Bytecode, gas: usize) -> CallContext {
+        let mut cctx = CallContext::new(CallContextContract { code: code.clone(), gas, ..Default::default() });
+        cctx.depth = 1;
+        cctx
+    }
+
+    fn interpret(code: &Bytecode, gas: usize) -> (Result<(), Error>, CallContext) {
+        let cctx = &mut context(code, gas);
+        let mut result = Ok(());
+        while !cctx.stop && result.is_ok() {
+            result = Machine::execute_next_opcode(&mut WorldState::default(), &TransactionContext::default(), cctx);
+        }
+        (result, std::mem::take(cctx))
+    }
+
+    // Goes back and forth between the compiled code and the interpreter, like `Machine::execute_code`
+    fn run_compiled(code: &Bytecode, gas: usize) -> (Result<(), Error>, CallContext) {
+        let (compiled, cctx) = (compile(code).unwrap(), &mut context(code, gas));
+        let (s, tctx) = (&mut WorldState::default(), &TransactionContext::default());
+        let mut result = Ok(());
+        while !cctx.stop && result.is_ok() {
+            result = compiled.run(s, tctx, cctx);
+            if !cctx.stop && result.is_ok() { result = Machine::execute_next_opcode(s, tctx, cctx); }
+        }
+        (result, std::mem::take(cctx))
+    }
+
+    fn assert_runs_like_the_interpreter(code: &str, gas: usize) -> (Result<(), Error>, CallContext) {
+        let code = Bytecode::from(hex::decode(code.replace(' ', "")).unwrap());
+        let (result, cctx) = run_compiled(&code, gas);
+        let (expected_result, expected) = interpret(&code, gas);
+        assert_eq!(result, expected_result, "{:?} with {} gas", code, gas);
+        assert_eq!((cctx.contract.gas, cctx.pc, cctx.stack.items(), &cctx.r#return), (expected.contract.gas, expected.pc, expected.stack.items(), &expected.r#return), "{:?} with {} gas", code, gas);
+        (result, cctx)
+    }
+
+    #[test]
+    fn runs_compiled_code_like_the_interpreter() {
+        let (result, cctx) = assert_runs_like_the_interpreter(LOOP, 1000);
+        assert_eq!(result, Ok(()));
+        assert!(cctx.stop);
+        assert_eq!(cctx.r#return, uint!("55").to_be_bytes());
+    }
+
+    #[test]
+    fn runs_out_of_gas_like_the_interpreter() {
+        for gas in [0, 10, 100, 300] {
+            assert_eq!(assert_runs_like_the_interpreter(LOOP, gas).0, Err(Error::OutOfGas));
+        }
+        for gas in 0..400 {
+            let _ = assert_runs_like_the_interpreter(LOOP, gas);
+        }
+
+        // MSTORE can afford the static gas of its block, but not to expand the memory
+        for gas in (0..8000).step_by(97) {
+            let _ = assert_runs_like_the_interpreter("6001 61FFFF 52 6001 6002 01 00", gas);
+        }
+    }
+
+    #[test]
+    fn computes_like_the_interpreter() {
+        let values = [uint!("0"), uint!("1"), uint!("2"), u256::from(u64::MAX), u256::from(u64::MAX) + 1, u256::from(u128::MAX), u256::ONE << 128, u256::ONE << 255,
+            u256::MAX, u256::MAX - 1, uint!("0x123456789ABCDEF0FEDCBA98765432100F1E2D3C4B5A69788796A5B4C3D2E1F0")];
+        for opcode in ["01", "02", "03", "10", "11", "12", "13", "14", "15", "16", "17", "18", "19"] {
+            let code: String = values.iter().flat_map(|a| values.iter().map(move |b| format!("7F{:064x} 7F{:064x} {}", b, a, opcode))).collect();
+            let (result, cctx) = assert_runs_like_the_interpreter(&code, 1_000_000);
+            assert_eq!(result, Ok(()));
+            assert!(cctx.stack.len() >= values.len() * values.len());
+        }
+
+        // DUP, SWAP, POP and PC
+        let (result, cctx) = assert_runs_like_the_interpreter("6001 6002 6003 82 91 58 50 81 90 9F 58 00", 1000);
+        assert_eq!(result, Err(Error::EmptyStack));
+        let (result, cctx_) = assert_runs_like_the_interpreter("6001 6002 6003 82 91 58 50 81 90 58 00", 1000);
+        assert_eq!((result, cctx.stack.len(), cctx_.stack.items()), (Ok(()), 5, [1u8, 1, 3, 3, 2, 12].map(u256::from).as_slice()));
+    }
+
+    #[test]
+    fn jumps_like_the_interpreter() {
+        // Destinations that are computed, and destinations that are not JUMPDESTs
+        assert_eq!(assert_runs_like_the_interpreter("6003 6005 01 56 00 00 5B 602A 00", 1000).1.stack.items(), [uint!("42")]);
+        assert_eq!(assert_runs_like_the_interpreter("6003 6004 01 56 00 00 5B 602A 00", 1000).0, Err(Error::InvalidJumpDest));
+        assert_eq!(assert_runs_like_the_interpreter("6001 56", 1000).0, Err(Error::InvalidJumpDest));
+        assert_eq!(assert_runs_like_the_interpreter(&format!("7F{:064x} 56 5B", (u256::ONE << 200) + uint!("4")), 1000).0, Err(Error::InvalidJumpDest));
+
+        // Conditions, including ones that only have high bits set
+        assert_eq!(assert_runs_like_the_interpreter("5F 60FF 57 6007 00", 1000).1.stack.items(), [uint!("7")]);
+        assert_eq!(assert_runs_like_the_interpreter(&format!("7F{:064x} 6026 57 00 00 5B 6007 00", u256::ONE << 200), 1000).1.stack.items(), [uint!("7")]);
+        assert_eq!(assert_runs_like_the_interpreter("5F 6003 6004 01 57 00", 1000).0, Ok(()));
+        assert_eq!(assert_runs_like_the_interpreter("6001 6003 6004 01 57 00", 1000).0, Err(Error::InvalidJumpDest));
+        assert_eq!(assert_runs_like_the_interpreter("6001 6003 57 00", 1000).0, Err(Error::InvalidJumpDest));
+    }
+
+    #[test]
+    fn fails_on_the_stack_like_the_interpreter() {
+        assert_eq!(assert_runs_like_the_interpreter("01", 1000).0, Err(Error::EmptyStack));
+        assert_eq!(assert_runs_like_the_interpreter("6001 6002 01 01", 1000).0, Err(Error::EmptyStack));
+        assert_eq!(assert_runs_like_the_interpreter("5B 5F 5F 56", 100000).0, Err(Error::StackOverflow)); // JUMPDEST PUSH0 PUSH0 JUMP
+    }
+
+    #[test]
+    fn leaves_the_rest_to_the_interpreter() {
+        let code = Bytecode::from(hex::decode("600150").unwrap()); // PUSH1 1 POP, then the implicit STOP past the end of the code
+        let cctx = &mut context(&code, 1000);
+
+        let result = compile(&code).unwrap().run(&mut WorldState::default(), &TransactionContext::default(), cctx);
+        assert_eq!(result, Ok(()));
+        assert!(!cctx.stop);
+        assert_eq!(cctx.pc, 3);
+        assert_eq!(cctx.contract.gas, 995);
+
+        assert!(compile(&Bytecode::default()).is_none());
+        assert!(lookup(&Bytecode::from(vec![0x00, 0x00])).is_none());
+    }
+
+    #[test]
+    fn pays_for_compiled_code_like_the_interpreter() {
+        // Counts down from 100, like the countdown in benches/interpreter.rs
+        let (caller, contract) = (Address(uint!("0xA0")), Address(uint!("0xC0")));
+        let mut evm = Evm::builder()
+            .account(caller, Account { balance: uint!("1000000000"), ..Default::default() })
+            .account(contract, Account { balance: uint!("0"), code: hex::decode("60645B600190038060025700").unwrap().into(), nonce: 1 })
+            .build();
+
+        let balance = |evm: &Evm| evm.state().account(caller).unwrap().balance;
+        let mut paid = vec![];
+        for nonce in 0..2 * HOT_THRESHOLD {
+            let before = balance(&evm);
+            let tx = Transaction { from: caller, gas: 100000, gas_price: 3, nonce, to: contract, ..Default::default() };
+            evm.transact(Block::default(), tx).unwrap();
+            paid.push(before - balance(&evm));
+        }
+        assert!(JIT.with_borrow(|jit| jit.as_ref().unwrap().compiled.len()) == 1);
+        assert!(paid.iter().all(|cost| *cost == paid[0]));
+    }
+
+    #[test]
+    fn bounds_the_compiled_code() {
+        let code = Bytecode::from(hex::decode(LOOP).unwrap());
+        let compiled = compile(&code).unwrap();
+
+        // PUSH2 i POP, each compiled once
+        for i in 0..MAX_COMPILED as u16 {
+            compile(&Bytecode::from([vec![0x61], i.to_be_bytes().to_vec(), vec![0x50]].concat()));
+        }
+        assert!(JIT.with_borrow(|jit| jit.as_ref().unwrap().compiled.len()) <= MAX_COMPILED);
+        assert!(JIT.with_borrow(|jit| !jit.as_ref().unwrap().compiled.contains_key(&code.hash())));
+
+        // Evicted code that is still held keeps running
+        let cctx = &mut context(&code, 1000);
+        let (s, tctx) = (&mut WorldState::default(), &TransactionContext::default());
+        while !cctx.stop {
+            compiled.run(s, tctx, cctx).unwrap();
+            if !cctx.stop { Machine::execute_next_opcode(s, tctx, cctx).unwrap(); }
+        }
+        assert_eq!(cctx.r#return, uint!("55").to_be_bytes());
+    }
+}
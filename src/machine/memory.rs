@@ -1,8 +1,14 @@
-use ethnum::{u256, U256};
+use ethnum::{u256, AsU256, U256};
 use crate::blockchain::errors::Error;
 
+// Memory is resized in place, and its cost is cached to only compute the cost of the new size on expansion.
+// Call frames share a single arena, in which each frame owns the window starting at its checkpoint
 #[derive(Default, Debug)]
-pub struct Memory(pub Vec<u8>);
+pub struct Memory {
+    checkpoint: usize,
+    cost: usize,
+    data: Vec<u8>,
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ReadWriteOperation<T> {
@@ -13,13 +19,19 @@ pub struct ReadWriteOperation<T> {
     pub result: T,
 }
 
+impl From<Vec<u8>> for Memory {
+    fn from(data: Vec<u8>) -> Self {
+        Self { checkpoint: 0, cost: Memory::memory_cost(data.len()), data }
+    }
+}
+
 impl Memory {
     pub fn new() -> Self {
-        Self(Default::default())
+        Self::default()
     }
 
     fn extension_size(&self, offset: usize, size: usize) -> usize {
-        if self.size() >= offset + size { 0_usize } else { (((offset + size - self.size() - 1) >> 5) + 1) << 5 }
+        if size == 0 || self.size() >= offset + size { 0_usize } else { (((offset + size - self.size() - 1) >> 5) + 1) << 5 }
     }
 
     fn memory_cost(memory_byte_size: usize) -> usize {
@@ -27,13 +39,36 @@ impl Memory {
         (memory_size_word.pow(2) >> 9) + (3 * memory_size_word)
     }
 
-    fn memory_expansion_cost(&self, last_memory_byte_size: usize) -> usize {
-        let new_memory_byte_size = self.size();
-        Memory::memory_cost(new_memory_byte_size) - Memory::memory_cost(last_memory_byte_size)
+    // Grows the memory to cover offset..offset + size, and returns the extension size and cost
+    fn expand(&mut self, offset: usize, size: usize) -> (usize, usize) {
+        let extension_size = self.extension_size(offset, size);
+        if extension_size == 0 { return (0, 0); }
+
+        self.data.resize(self.data.len() + extension_size, 0);
+        let cost = Memory::memory_cost(self.size());
+        let extension_cost = cost - self.cost;
+        self.cost = cost;
+        (extension_size, extension_cost)
     }
 
     pub fn size(&self) -> usize {
-        self.0.len()
+        self.data.len() - self.checkpoint
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[self.checkpoint..]
+    }
+
+    // Hands the arena over to a new call frame, whose window starts where the current one ends
+    pub fn enter_frame(&mut self) -> Memory {
+        let data = std::mem::take(&mut self.data);
+        Memory { checkpoint: data.len(), cost: 0, data }
+    }
+
+    // Takes the arena back from a returning call frame, and releases its window
+    pub fn leave_frame(&mut self, mut frame: Memory) {
+        frame.data.truncate(frame.checkpoint);
+        self.data = frame.data;
     }
 
     fn try_offset_size(offset: u256, size: u256) -> Result<(usize, usize), Error> {
@@ -50,67 +85,60 @@ impl Memory {
 
     pub fn store_byte(&mut self, offset: u256, value: u256) -> Result<ReadWriteOperation<()>, Error> {
         let (offset, size) = Memory::try_offset_size(offset, U256::ONE)?;
-        let last_memory_byte_size = self.size();
-        let extension_size = self.extension_size(offset, size);
-        self.0.append(&mut vec![0; extension_size]);
+        let (extension_size, extension_cost) = self.expand(offset, size);
 
-        self.0[offset] = (value & 0xFF).try_into().unwrap();
-        Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost: self.memory_expansion_cost(last_memory_byte_size), result: () })
+        self.data[self.checkpoint + offset] = (value & 0xFF).try_into().unwrap();
+        Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost, result: () })
     }
 
-    pub fn store_word(&mut self, offset: u256, mut value: u256) -> Result<ReadWriteOperation<()>, Error> {
+    pub fn store_word(&mut self, offset: u256, value: u256) -> Result<ReadWriteOperation<()>, Error> {
         let (offset, size) = Memory::try_offset_size(offset, u256::from(32_u8))?;
-        let last_memory_byte_size = self.size();
-        let extension_size = self.extension_size(offset, size);
-        self.0.append(&mut vec![0; extension_size]);
+        let (extension_size, extension_cost) = self.expand(offset, size);
 
-        for i in 0..size {
-            self.0[offset + 31 - i] = (value & 0xFF).try_into().unwrap();
-            value >>= 8;
-        }
-        Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost: self.memory_expansion_cost(last_memory_byte_size), result: () })
+        let start = self.checkpoint + offset;
+        self.data[start..start + size].copy_from_slice(&value.to_be_bytes());
+        Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost, result: () })
     }
 
-    pub fn store(&mut self, offset: u256, size: u256, value: Vec<u8>) -> Result<ReadWriteOperation<()>, Error> {
+    // Writes `size` bytes, padding `value` with zeros or truncating it
+    pub fn store(&mut self, offset: u256, size: u256, value: &[u8]) -> Result<ReadWriteOperation<()>, Error> {
         let (offset, size) = Memory::try_offset_size(offset, size)?;
-        let last_memory_byte_size = self.size();
-        let extension_size = self.extension_size(offset, size);
-        self.0.append(&mut vec![0; extension_size]);
+        let (extension_size, extension_cost) = self.expand(offset, size);
+        if size == 0 { return Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost, result: () }); }
 
-        for i in 0..size {
-            self.0[offset + i] = *value.get(i).unwrap_or(&0_u8);
-        }
-        Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost: self.memory_expansion_cost(last_memory_byte_size), result: () })
+        let (start, copied) = (self.checkpoint + offset, std::cmp::min(size, value.len()));
+        self.data[start..start + copied].copy_from_slice(&value[..copied]);
+        self.data[start + copied..start + size].fill(0);
+        Ok(ReadWriteOperation::<()> { offset, size, extension_size, extension_cost, result: () })
     }
 
     pub fn load_word(&mut self, offset: u256) -> Result<ReadWriteOperation<u256>, Error> {
         let (offset, size) = Memory::try_offset_size(offset, u256::from(32_u8))?;
-        let last_memory_byte_size = self.size();
-        let extension_size = self.extension_size(offset, size);
-        self.0.append(&mut vec![0; extension_size]);
-
-        let mut result = U256::ZERO;
-        for i in 0..size {
-            result <<= 8;
-            result |= u256::from(match self.0.get(offset + i) {
-                Some(v) => *v,
-                None => 0,
-            });
-        }
-        Ok(ReadWriteOperation::<u256> { offset, size, extension_size, extension_cost: self.memory_expansion_cost(last_memory_byte_size), result })
+        let (extension_size, extension_cost) = self.expand(offset, size);
+
+        let start = self.checkpoint + offset;
+        let result = u256::from_be_bytes(self.data[start..start + size].try_into().unwrap());
+        Ok(ReadWriteOperation::<u256> { offset, size, extension_size, extension_cost, result })
     }
 
-    pub fn load(&mut self, offset: u256, size: u256) -> Result<ReadWriteOperation<Vec<u8>>, Error> {
+    pub fn load(&mut self, offset: u256, size: u256) -> Result<ReadWriteOperation<&[u8]>, Error> {
         let (offset, size) = Memory::try_offset_size(offset, size)?;
-        let last_memory_byte_size = self.size();
-        let extension_size = self.extension_size(offset, size);
-        self.0.append(&mut vec![0; extension_size]);
+        let (extension_size, extension_cost) = self.expand(offset, size);
 
-        let mut result = Vec::<u8>::new();
-        for i in 0..size {
-            result.push(*self.0.get(offset + i).unwrap_or(&0_u8));
-        }
-        Ok(ReadWriteOperation::<Vec<u8>> { offset, size, extension_size, extension_cost: self.memory_expansion_cost(last_memory_byte_size), result })
+        let start = self.checkpoint + offset;
+        let result = if size == 0 { &[][..] } else { &self.data[start..start + size] };
+        Ok(ReadWriteOperation::<&[u8]> { offset, size, extension_size, extension_cost, result })
+    }
+
+    // Copies `size` bytes within memory, the areas may overlap. The extension covers both of them
+    pub fn copy(&mut self, dest_offset: u256, offset: u256, size: u256) -> Result<ReadWriteOperation<()>, Error> {
+        let (offset, size) = Memory::try_offset_size(offset, size)?;
+        let (dest_offset, _) = Memory::try_offset_size(dest_offset, size.as_u256())?;
+        let (extension_size, extension_cost) = self.expand(std::cmp::max(offset, dest_offset), size);
+        if size == 0 { return Ok(ReadWriteOperation::<()> { offset: dest_offset, size, extension_size, extension_cost, result: () }); }
+
+        self.data.copy_within(self.checkpoint + offset..self.checkpoint + offset + size, self.checkpoint + dest_offset);
+        Ok(ReadWriteOperation::<()> { offset: dest_offset, size, extension_size, extension_cost, result: () })
     }
 }
 
@@ -123,7 +151,7 @@ mod tests {
     fn stores_a_word() {
         let mut memory = Memory::new();
 
-        assert_eq!(memory.0.len(), 0);
+        assert_eq!(memory.data.len(), 0);
         assert_eq!(memory.store_word(uint!("4"), uint!("0x0000000000000000000000000000000000000000000000000000000004050607")), Ok(ReadWriteOperation::<()> {
             offset: 4,
             size: 32,
@@ -131,12 +159,12 @@ mod tests {
             extension_cost: 6,
             result: (),
         }));
-        assert_eq!(memory.0, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(memory.data, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
     }
 
     #[test]
     fn fails_to_store_a_word_out_of_memory() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
         assert_eq!(memory.store_word(uint!("0x10000000000000000"), uint!("0xFF")), Err(Error::MemoryOutOfBounds));
     }
@@ -145,27 +173,27 @@ mod tests {
     fn stores() {
         let mut memory = Memory::new();
 
-        assert_eq!(memory.0.len(), 0);
-        assert_eq!(memory.store(uint!("4"), uint!("40"), vec![4, 5, 6, 7]), Ok(ReadWriteOperation::<()> {
+        assert_eq!(memory.data.len(), 0);
+        assert_eq!(memory.store(uint!("4"), uint!("40"), &[4, 5, 6, 7]), Ok(ReadWriteOperation::<()> {
             offset: 4,
             size: 40,
             extension_size: 64,
             extension_cost: 6,
             result: (),
         }));
-        assert_eq!(memory.0, vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(memory.data, vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
     }
 
     #[test]
     fn fails_to_store_out_of_memory() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
-        assert_eq!(memory.store(uint!("0x10000000000000000"), uint!("0xFF"), vec![]), Err(Error::MemoryOutOfBounds));
+        assert_eq!(memory.store(uint!("0x10000000000000000"), uint!("0xFF"), &[]), Err(Error::MemoryOutOfBounds));
     }
 
     #[test]
     fn loads_a_word() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
         assert_eq!(memory.load_word(uint!("6")), Ok(ReadWriteOperation::<u256> {
             offset: 6,
@@ -178,41 +206,41 @@ mod tests {
 
     #[test]
     fn fails_to_load_a_word_out_of_memory() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
         assert_eq!(memory.load_word(uint!("0x10000000000000000")), Err(Error::MemoryOutOfBounds));
     }
 
     #[test]
     fn loads() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
-        assert_eq!(memory.load(uint!("6"), uint!("4")), Ok(ReadWriteOperation::<Vec<u8>> {
+        assert_eq!(memory.load(uint!("6"), uint!("4")), Ok(ReadWriteOperation::<&[u8]> {
             offset: 6,
             size: 4,
             extension_size: 0,
             extension_cost: 0,
-            result: vec![6, 7, 0, 0],
+            result: &[6, 7, 0, 0][..],
         }));
     }
 
     #[test]
     fn fails_to_load_out_of_memory() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
         assert_eq!(memory.load(uint!("0x10000000000000000"), uint!("4")), Err(Error::MemoryOutOfBounds));
     }
 
     #[test]
     fn returns_the_current_size() {
-        let memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0]);
+        let memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0]);
 
         assert_eq!(memory.size(), 11);
     }
 
     #[test]
     fn computes_extension_size() {
-        let memory = Memory(vec![0; 32]);
+        let memory = Memory::from(vec![0; 32]);
 
         assert_eq!(memory.extension_size(0, 32), 0);
         assert_eq!(memory.extension_size(1, 32), 32);
@@ -234,7 +262,7 @@ mod tests {
             extension_cost: 3,
             result: (),
         }));
-        assert_eq!(memory.0, vec![0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(memory.data, vec![0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
         assert_eq!(memory.store_byte(uint!("32"), uint!("0xFFAB")), Ok(ReadWriteOperation::<()> {
             offset: 32,
@@ -243,12 +271,12 @@ mod tests {
             extension_cost: 3,
             result: (),
         }));
-        assert_eq!(memory.0, vec![0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(memory.data, vec![0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
     }
 
     #[test]
     fn fails_to_store_a_byte_out_of_memory() {
-        let mut memory = Memory(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut memory = Memory::from(vec![0, 0, 0, 0, 4, 5, 6, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
 
         assert_eq!(memory.store_byte(uint!("0x10000000000000000"), uint!("0xFF")), Err(Error::MemoryOutOfBounds));
     }
@@ -270,4 +298,72 @@ mod tests {
 
         assert_eq!(Memory::try_offset_size(uint!("4"), uint!("32")), Ok((4, 32)));
     }
+
+    #[test]
+    fn does_not_expand_on_empty_accesses() {
+        let mut memory = Memory::new();
+
+        assert_eq!(memory.load(uint!("64"), uint!("0")), Ok(ReadWriteOperation::<&[u8]> {
+            offset: 64,
+            size: 0,
+            extension_size: 0,
+            extension_cost: 0,
+            result: &[][..],
+        }));
+        assert_eq!(memory.store(uint!("64"), uint!("0"), &[1]).unwrap().extension_cost, 0);
+        assert_eq!(memory.size(), 0);
+    }
+
+    #[test]
+    fn caches_the_memory_cost() {
+        let mut memory = Memory::new();
+
+        assert_eq!(memory.store(uint!("0"), uint!("1024"), &[]).unwrap().extension_cost, 98);
+        assert_eq!(memory.cost, 98);
+        assert_eq!(memory.store(uint!("1024"), uint!("1024"), &[]).unwrap().extension_cost, 102);
+        assert_eq!(memory.cost, 200);
+        assert_eq!(memory.store(uint!("0"), uint!("2048"), &[]).unwrap().extension_cost, 0);
+        assert_eq!(Memory::from(vec![0; 2048]).cost, 200);
+    }
+
+    #[test]
+    fn copies_within_memory() {
+        let mut memory = Memory::from([vec![1, 2, 3, 4], vec![0; 28]].concat());
+
+        assert_eq!(memory.copy(uint!("1"), uint!("0"), uint!("3")), Ok(ReadWriteOperation::<()> {
+            offset: 1,
+            size: 3,
+            extension_size: 0,
+            extension_cost: 0,
+            result: (),
+        }));
+        assert_eq!(memory.data[..4], [1, 1, 2, 3]);
+
+        assert_eq!(memory.copy(uint!("32"), uint!("0"), uint!("4")), Ok(ReadWriteOperation::<()> {
+            offset: 32,
+            size: 4,
+            extension_size: 32,
+            extension_cost: 3,
+            result: (),
+        }));
+        assert_eq!(memory.data[32..36], [1, 1, 2, 3]);
+        assert_eq!(memory.size(), 64);
+    }
+
+    #[test]
+    fn shares_the_arena_across_frames() {
+        let mut memory = Memory::from(vec![1; 32]);
+
+        let mut frame = memory.enter_frame();
+        assert_eq!(frame.size(), 0);
+        assert_eq!(frame.store_word(uint!("0"), uint!("2")).unwrap().extension_cost, 3);
+        assert_eq!(frame.load_word(uint!("0")).unwrap().result, uint!("2"));
+        assert_eq!(frame.data(), uint!("2").to_be_bytes());
+        assert_eq!(frame.data.len(), 64);
+
+        memory.leave_frame(frame);
+        assert_eq!(memory.data(), [1; 32]);
+        assert_eq!(memory.store_word(uint!("32"), uint!("3")).unwrap().extension_cost, 3);
+        assert!(memory.data.capacity() >= 64);
+    }
 }
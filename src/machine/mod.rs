@@ -1,17 +1,27 @@
 pub mod context;
+pub mod debugger;
+pub mod eof;
+pub mod inspector;
 pub mod instructions;
+#[cfg(feature = "jit")]
+pub mod jit;
 pub mod memory;
 pub mod opcode;
+pub mod parallel;
+pub mod precompiles;
 pub mod stack;
+pub mod tracers;
 pub mod transient;
 
-use ethnum::AsU256;
+use ethnum::{u256, AsU256, U256};
 
-use crate::blockchain::primitives::Account;
+use crate::blockchain::primitives::{Account, Address, Block, Transaction};
 use crate::blockchain::WorldState;
 use crate::blockchain::errors::Error;
-use crate::machine::context::{CallContext, TransactionContext};
-use crate::machine::opcode::OpCode;
+use crate::machine::context::{CallContext, Halt, TransactionContext};
+use crate::machine::eof::Container;
+use crate::machine::opcode::{InstructionInfo, OpCode, EOF_INSTRUCTIONS, INSTRUCTIONS};
+use crate::machine::precompiles::Precompile;
 
 #[derive(Default, Debug, Eq, PartialEq)]
 pub struct ExecutionOutput {
@@ -28,48 +38,218 @@ impl Machine {
         if cctx.contract.gas < gas_cost { cctx.contract.gas = 0; return Err(Error::OutOfGas); }
 
         cctx.contract.gas -= gas_cost;
-        s.decrease_balance(tctx.tx.from, (gas_cost * tctx.tx.gas_price).as_u256())?;
+        if cctx.depth == 0 && gas_cost != 0 { // gas consumed by sub-contexts is paid by their parent
+            s.decrease_balance(tctx.tx.from, (gas_cost * tctx.tx.gas_price).as_u256())?;
+        }
+
+        Ok(())
+    }
+
+    // Gives back the static gas paid upfront for the rest of the current basic block
+    fn refund_prepaid_gas(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> Result<(), Error> {
+        cctx.contract.gas += cctx.prepaid_gas;
+        if cctx.depth == 0 {
+            s.increase_balance(tctx.tx.from, (cctx.prepaid_gas * tctx.tx.gas_price).as_u256())?;
+        }
+        cctx.prepaid_gas = 0;
 
         Ok(())
     }
 
     fn execute_next_opcode(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> Result<(), Error> {
-        let opcode = OpCode(*cctx.contract.code.get(cctx.pc).unwrap_or(&0));
-        let output = opcode.execute(s, tctx, cctx)?;
+        let table = if cctx.contract.eof.is_some() { &EOF_INSTRUCTIONS } else { &INSTRUCTIONS };
+        let opcode = cctx.contract.code.padded()[cctx.pc];
+        Machine::execute_instruction(s, tctx, cctx, &table.0[opcode as usize])
+    }
+
+    // Executes the instruction at pc, whose handler and metadata are given by `info`, and pays for it
+    pub fn execute_instruction(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, info: &InstructionInfo) -> Result<(), Error> {
+        if tctx.inspector.is_none() { return Machine::run_instruction(s, tctx, cctx, info).map(|_| ()); }
+
+        tctx.inspect(|inspector| inspector.step(s, cctx));
+        let result = Machine::run_instruction(s, tctx, cctx, info);
+        let (cost, result) = (*result.as_ref().unwrap_or(&0), result.map(|_| ()));
+        tctx.inspect(|inspector| inspector.step_end(s, cctx, cost, &result));
+        result
+    }
 
-        Machine::pay_gas_cost(s, tctx, cctx, output.cost)?;
+    // Returns the gas that the instruction was charged
+    fn run_instruction(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, info: &InstructionInfo) -> Result<usize, Error> {
+        // Legacy code pays the static gas of a basic block when entering it, or meters it one instruction at a time if it cannot afford it
+        if cctx.prepaid_gas == 0 && cctx.contract.eof.is_none() {
+            let block_gas = cctx.contract.code.block_gas(cctx.pc);
+            if block_gas <= cctx.contract.gas {
+                Machine::pay_gas_cost(s, tctx, cctx, block_gas)?;
+                cctx.prepaid_gas = block_gas;
+            }
+        }
+
+        let output = match (info.execute)(s, tctx, cctx) {
+            Ok(output) => output,
+            Err(error) => {
+                Machine::refund_prepaid_gas(s, tctx, cctx)?;
+                return Err(error);
+            },
+        };
+
+        if cctx.prepaid_gas == 0 {
+            Machine::pay_gas_cost(s, tctx, cctx, output.cost)?;
+        } else if output.cost - info.static_gas <= cctx.contract.gas {
+            cctx.prepaid_gas -= info.static_gas;
+            Machine::pay_gas_cost(s, tctx, cctx, output.cost - info.static_gas)?;
+        } else { // the prepaid gas may have been enough to pay the dynamic cost
+            Machine::refund_prepaid_gas(s, tctx, cctx)?;
+            Machine::pay_gas_cost(s, tctx, cctx, output.cost)?;
+        }
         cctx.pc += output.jump;
 
+        Ok(output.cost)
+    }
+
+    // Runs the code of the call context until it halts, natively once it has been compiled. Inspectors see every
+    // instruction, so code that they inspect is only interpreted
+    fn execute_code(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> Result<(), Error> {
+        #[cfg(feature = "jit")]
+        let compiled = if cctx.contract.eof.is_none() && tctx.inspector.is_none() { jit::lookup(&cctx.contract.code) } else { None };
+
+        while !cctx.stop {
+            #[cfg(feature = "jit")]
+            if let Some(compiled) = &compiled {
+                compiled.run(s, tctx, cctx)?;
+                if cctx.stop { break; }
+            }
+            Machine::execute_next_opcode(s, tctx, cctx)?;
+        }
+
+        Ok(())
+    }
+
+    fn execute_precompile(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, precompile: Precompile) -> Result<(), Error> {
+        cctx.stop = true;
+        match precompile.execute(&cctx.contract.input) {
+            Ok(output) => {
+                Machine::pay_gas_cost(s, tctx, cctx, output.cost)?;
+                cctx.r#return = output.data;
+            },
+            Err(_) => Machine::consume_all_gas(s, tctx, cctx, Halt::PrecompileFailure)?,
+        }
+
+        Ok(())
+    }
+
+    fn consume_all_gas(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, halt: Halt) -> Result<(), Error> {
+        Machine::pay_gas_cost(s, tctx, cctx, cctx.contract.gas)?;
+        cctx.stop = true;
+        cctx.revert = true;
+        cctx.halt = Some(halt);
+
         Ok(())
     }
 
+    fn run_subcontext(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, transfer: u256, precompile: Option<Precompile>) -> Result<(), Error> {
+        if transfer != U256::ZERO {
+            s.decrease_balance(cctx.contract.caller, transfer)?;
+            s.increase_balance(cctx.contract.address, transfer)?;
+        }
+
+        match precompile {
+            Some(precompile) => {
+                let output = precompile.execute(&cctx.contract.input)?;
+                Machine::pay_gas_cost(s, tctx, cctx, output.cost)?;
+                cctx.r#return = output.data;
+            },
+            None => Machine::execute_code(s, tctx, cctx)?,
+        }
+
+        Ok(())
+    }
+
+    fn inspect_enter(s: &WorldState, tctx: &TransactionContext, cctx: &CallContext, opcode: OpCode, target: Address) {
+        match opcode.is_create() {
+            true => tctx.inspect(|inspector| inspector.create(s, cctx, opcode)),
+            false => tctx.inspect(|inspector| inspector.call(s, cctx, opcode, target)),
+        }
+    }
+
+    fn inspect_exit(s: &WorldState, tctx: &TransactionContext, cctx: &CallContext, opcode: OpCode, result: &Result<(), Error>) {
+        match opcode.is_create() {
+            true => tctx.inspect(|inspector| inspector.create_end(s, cctx, result)),
+            false => tctx.inspect(|inspector| inspector.call_end(s, cctx, result)),
+        }
+    }
+
+    // Runs a sub-context, made by `opcode` to run the code of `target`, after transferring `transfer` from its caller.
+    // The world state and the transient storage are rolled back unless it succeeds
+    pub fn execute_subcontext(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, transfer: u256, precompile: Option<Precompile>, opcode: OpCode, target: Address) -> Result<(), Error> {
+        let (checkpoint, transient) = (s.checkpoint(), cctx.transient.checkpoint());
+        Machine::inspect_enter(s, tctx, cctx, opcode, target);
+
+        let result = Machine::run_subcontext(s, tctx, cctx, transfer, precompile);
+        if result.is_err() {
+            cctx.contract.gas = 0;
+            cctx.r#return = vec![];
+        }
+        if result.is_err() || cctx.revert {
+            s.revert_checkpoint(checkpoint);
+            cctx.transient.revert(transient);
+        } else {
+            s.commit_checkpoint();
+        }
+
+        Machine::inspect_exit(s, tctx, cctx, opcode, &result);
+        result
+    }
+
+    // Applies the EIP-7702 authorization list, skipping invalid tuples, and returns the gas refund
+    fn apply_authorizations(s: &mut WorldState, tctx: &TransactionContext) -> Result<usize, Error> {
+        let mut refund = 0;
+        for authorization in &tctx.tx.authorization_list {
+            if authorization.chain_id != U256::ZERO && authorization.chain_id != s.chain_id { continue; }
+            if authorization.nonce as u64 == u64::MAX { continue; }
+            let Some(authority) = authorization.authority() else { continue };
+
+            let mut account = s.load_account(authority)?.value;
+            if !account.code.is_empty() && account.delegation().is_none() { continue; }
+            if account.nonce != authorization.nonce { continue; }
+
+            if !account.is_empty() { refund += 25000 - 12500; }
+            account.delegate_to(authorization.address);
+            account.nonce += 1;
+            s.store_account(authority, account)?;
+        }
+        Ok(refund)
+    }
+
     pub fn execute_transaction(s: &mut WorldState, tctx: &TransactionContext) -> ExecutionResult {
-        let cctx = &mut CallContext::from_transaction(s, &tctx.tx);
+        if tctx.inspector.is_none() || s.log.is_some() { return Machine::process_transaction(s, tctx); }
+
+        // Inspectors find every account and slot that the transaction accessed, even in reverted calls, in the access log
+        s.log = Some(Default::default());
+        let result = Machine::process_transaction(s, tctx);
+        s.log = None;
+        result
+    }
 
-        let sender = s.accounts.load(tctx.tx.from).value;
+    fn process_transaction(s: &mut WorldState, tctx: &TransactionContext) -> ExecutionResult {
+        let sender = s.load_account(tctx.tx.from)?.value;
+        if tctx.tx.nonce != sender.nonce { return Err(Error::InvalidNonce(sender.nonce)); }
         let max_cost = (tctx.tx.gas * tctx.tx.gas_price).as_u256() + tctx.tx.value;
 
         sender.check_enough_funds(max_cost)?;
 
         let intrisic_gas_cost = tctx.tx.intrinsic_gas_cost();
-        Machine::pay_gas_cost(s, tctx, cctx, intrisic_gas_cost).map_err(|e| match e {
-            Error::OutOfGas => Error::IntrisicGasTooLow(intrisic_gas_cost),
-            _ => e,
-        })?;
+        if tctx.tx.gas < intrisic_gas_cost { return Err(Error::IntrisicGasTooLow(intrisic_gas_cost)); }
 
-        // TODO (fguerin - 22/12/2024) Handle sub-context creations
-        while !cctx.stop {
-            Machine::execute_next_opcode(s, tctx, cctx)?;
-        }
+        s.store_account(tctx.tx.from, Account { nonce: sender.nonce + 1, ..sender })?;
+        s.refund = Machine::apply_authorizations(s, tctx)?;
 
-        if tctx.tx.is_contract_creation() {
-            Machine::pay_gas_cost(s, tctx, cctx, 200 * cctx.r#return.clone().len())?; // code deposit cost
-            s.accounts.store(cctx.contract.address, Account {
-                balance: tctx.tx.value,
-                code: cctx.r#return.clone(),
-            });
-            s.decrease_balance(tctx.tx.from, tctx.tx.value)?;
-        }
+        let (mut cctx, delegation_cost) = CallContext::from_transaction(s, &tctx.tx)?;
+        let cctx = &mut cctx;
+        let opcode = OpCode(if tctx.tx.is_contract_creation() { 0xF0 } else { 0xF1 });
+        Machine::inspect_enter(s, tctx, cctx, opcode, cctx.contract.address);
+        let result = Machine::run_transaction(s, tctx, cctx, intrisic_gas_cost + delegation_cost);
+        Machine::inspect_exit(s, tctx, cctx, opcode, &result);
+        result?;
 
         Ok(ExecutionOutput {
             data: cctx.r#return.clone(),
@@ -77,4 +257,51 @@ impl Machine {
             revert: cctx.revert,
         })
     }
+
+    // Runs the code of a transaction once it has been validated and its sender charged
+    fn run_transaction(s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext, intrisic_gas_cost: usize) -> Result<(), Error> {
+        Machine::pay_gas_cost(s, tctx, cctx, intrisic_gas_cost)?;
+
+        if tctx.tx.is_contract_creation() && Container::is_eof(&tctx.tx.data) && cctx.contract.eof.is_none() { // invalid EOF initcode
+            Machine::consume_all_gas(s, tctx, cctx, Halt::InvalidInitcode)?;
+        }
+
+        if let Some(precompile) = Precompile::at(tctx.tx.to) {
+            Machine::execute_precompile(s, tctx, cctx, precompile)?;
+        }
+
+        Machine::execute_code(s, tctx, cctx)?;
+
+        if tctx.tx.is_contract_creation() && cctx.contract.eof.is_none() && cctx.r#return.first() == Some(&0xEF) { // EIP-3541
+            Machine::consume_all_gas(s, tctx, cctx, Halt::InvalidCode)?;
+        }
+
+        if tctx.tx.is_contract_creation() && !cctx.revert {
+            if cctx.contract.eof.is_none() { // RETURNCONTRACT already paid the code deposit of EOF contracts
+                Machine::pay_gas_cost(s, tctx, cctx, 200 * cctx.r#return.clone().len())?; // code deposit cost
+            }
+            s.store_account(cctx.contract.address, Account {
+                balance: tctx.tx.value,
+                code: cctx.r#return.as_slice().into(),
+                nonce: 1,
+            })?;
+            s.decrease_balance(tctx.tx.from, tctx.tx.value)?;
+        }
+
+        let refund = std::cmp::min(s.refund, (tctx.tx.gas - cctx.contract.gas) / 5);
+        cctx.contract.gas += refund;
+        s.increase_balance(tctx.tx.from, (refund * tctx.tx.gas_price).as_u256())?;
+
+        Ok(())
+    }
+
+    // Executes the transactions of a block one after the other, each one starting with every account and slot cold
+    pub fn execute_block(s: &mut WorldState, block: &Block, txs: &[Transaction]) -> Vec<ExecutionResult> {
+        s.commit();
+        txs.iter().map(|tx| {
+            let result = Machine::execute_transaction(s, &TransactionContext { block: block.clone(), inspector: None, tx: tx.clone() });
+            s.commit();
+            result
+        }).collect()
+    }
 }
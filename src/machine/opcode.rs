@@ -4,8 +4,46 @@ use crate::blockchain::WorldState;
 use crate::machine::context::{CallContext, TransactionContext};
 use crate::machine::instructions::{Instructions, InstructionResult};
 
+pub type Instruction = fn(&mut WorldState, &TransactionContext, &mut CallContext) -> InstructionResult;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct OpCode(pub u8);
 
+#[derive(Clone, Copy)]
+pub struct InstructionInfo {
+    pub execute: Instruction,
+    pub immediate_size: usize,
+    pub stack_io: Option<(usize, usize)>,
+    pub static_gas: usize,
+    pub terminating: bool,
+}
+
+// Handlers and metadata of every opcode, indexed by opcode
+pub struct InstructionTable(pub [InstructionInfo; 256]);
+
+impl InstructionTable {
+    const fn new(eof: bool) -> Self {
+        let mut table = [InstructionInfo { execute: Instructions::invalid, immediate_size: 0, stack_io: None, static_gas: 0, terminating: false }; 256];
+        let mut i = 0;
+        while i < 256 {
+            let opcode = OpCode(i as u8);
+            table[i] = InstructionInfo {
+                execute: if eof { opcode.eof_instruction() } else { opcode.instruction() },
+                immediate_size: opcode.immediate_size(),
+                stack_io: opcode.stack_io(),
+                static_gas: opcode.static_gas(),
+                terminating: opcode.is_terminating(),
+            };
+            i += 1;
+        }
+        Self(table)
+    }
+}
+
+// Only one fork is implemented, whose legacy and EOF code each get a table built at compile time
+pub static INSTRUCTIONS: InstructionTable = InstructionTable::new(false);
+pub static EOF_INSTRUCTIONS: InstructionTable = InstructionTable::new(true);
+
 impl Display for OpCode {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self.0 {
@@ -148,13 +186,32 @@ impl Display for OpCode {
             0xA2 => "LOG2",
             0xA3 => "LOG3",
             0xA4 => "LOG4",
+            0xD0 => "DATALOAD",
+            0xD1 => "DATALOADN",
+            0xD2 => "DATASIZE",
+            0xD3 => "DATACOPY",
+            0xE0 => "RJUMP",
+            0xE1 => "RJUMPI",
+            0xE2 => "RJUMPV",
+            0xE3 => "CALLF",
+            0xE4 => "RETF",
+            0xE5 => "JUMPF",
+            0xE6 => "DUPN",
+            0xE7 => "SWAPN",
+            0xE8 => "EXCHANGE",
+            0xEC => "EOFCREATE",
+            0xEE => "RETURNCONTRACT",
             0xF0 => "CREATE",
             0xF1 => "CALL",
             0xF2 => "CALLCODE",
             0xF3 => "RETURN",
             0xF4 => "DELEGATECALL",
             0xF5 => "CREATE2",
+            0xF7 => "RETURNDATALOAD",
+            0xF8 => "EXTCALL",
+            0xF9 => "EXTDELEGATECALL",
             0xFA => "STATICCALL",
+            0xFB => "EXTSTATICCALL",
             0xFD => "REVERT",
             0xFE => "INVALID",
             0xFF => "SELFDESTRUCT",
@@ -164,8 +221,8 @@ impl Display for OpCode {
 }
 
 impl OpCode {
-    pub fn execute(&self, s: &mut WorldState, tctx: &TransactionContext, cctx: &mut CallContext) -> InstructionResult {
-        (match self.0 {
+    pub const fn instruction(&self) -> Instruction {
+        match self.0 {
             0x00 => Instructions::stop,
             0x01 => Instructions::add,
             0x02 => Instructions::mul,
@@ -316,6 +373,173 @@ impl OpCode {
             0xFE => Instructions::invalid,
             0xFF => Instructions::selfdestruct,
             _ => Instructions::invalid,
-        })(s, tctx, cctx)
+        }
+    }
+
+    // Opcodes introduced by EOF, all the other ones behave as in legacy code. Opcodes removed by EOF are rejected at validation time
+    // Opcodes that are undefined in EOF code never pass the validation, and are mapped to INVALID
+    pub const fn eof_instruction(&self) -> Instruction {
+        if !self.is_valid_in_eof() { return Instructions::invalid; }
+        match self.0 {
+            0xD0 => Instructions::dataload,
+            0xD1 => Instructions::dataloadn,
+            0xD2 => Instructions::datasize,
+            0xD3 => Instructions::datacopy,
+            0xE0 => Instructions::rjump,
+            0xE1 => Instructions::rjumpi,
+            0xE2 => Instructions::rjumpv,
+            0xE3 => Instructions::callf,
+            0xE4 => Instructions::retf,
+            0xE5 => Instructions::jumpf,
+            0xE6 => Instructions::dupn,
+            0xE7 => Instructions::swapn,
+            0xE8 => Instructions::exchange,
+            0xEC => Instructions::eofcreate,
+            0xEE => Instructions::returncontract,
+            0xF7 => Instructions::returndataload,
+            0xF8 => Instructions::extcall,
+            0xF9 => Instructions::extdelegatecall,
+            0xFB => Instructions::extstaticcall,
+            _ => self.instruction(),
+        }
+    }
+
+    // Number of stack items popped and pushed, or None if the opcode is undefined. CALLF, RETF, JUMPF, DUPN, SWAPN and EXCHANGE depend on their immediate
+    pub const fn stack_io(&self) -> Option<(usize, usize)> {
+        Some(match self.0 {
+            0x00 => (0, 0),
+            0x01..=0x07 => (2, 1),
+            0x08 | 0x09 => (3, 1),
+            0x0A | 0x0B => (2, 1),
+            0x10..=0x14 => (2, 1),
+            0x15 => (1, 1),
+            0x16..=0x18 => (2, 1),
+            0x19 => (1, 1),
+            0x1A..=0x1D => (2, 1),
+            0x20 => (2, 1),
+            0x30 => (0, 1),
+            0x31 => (1, 1),
+            0x32..=0x34 => (0, 1),
+            0x35 => (1, 1),
+            0x36 => (0, 1),
+            0x37 => (3, 0),
+            0x38 => (0, 1),
+            0x39 => (3, 0),
+            0x3A => (0, 1),
+            0x3B => (1, 1),
+            0x3C => (4, 0),
+            0x3D => (0, 1),
+            0x3E => (3, 0),
+            0x3F | 0x40 => (1, 1),
+            0x41..=0x48 => (0, 1),
+            0x49 => (1, 1),
+            0x4A => (0, 1),
+            0x50 => (1, 0),
+            0x51 => (1, 1),
+            0x52 | 0x53 => (2, 0),
+            0x54 => (1, 1),
+            0x55 => (2, 0),
+            0x56 => (1, 0),
+            0x57 => (2, 0),
+            0x58..=0x5A => (0, 1),
+            0x5B => (0, 0),
+            0x5C => (1, 1),
+            0x5D => (2, 0),
+            0x5E => (3, 0),
+            0x5F..=0x7F => (0, 1),
+            0x80..=0x8F => ((self.0 - 0x7F) as usize, (self.0 - 0x7E) as usize),
+            0x90..=0x9F => ((self.0 - 0x8E) as usize, (self.0 - 0x8E) as usize),
+            0xA0..=0xA4 => ((self.0 - 0x9E) as usize, 0),
+            0xD0 => (1, 1),
+            0xD1 | 0xD2 => (0, 1),
+            0xD3 => (3, 0),
+            0xE0 => (0, 0),
+            0xE1 | 0xE2 => (1, 0),
+            0xE3..=0xE8 => (0, 0),
+            0xEC => (4, 1),
+            0xEE => (2, 0),
+            0xF0 => (3, 1),
+            0xF1 | 0xF2 => (7, 1),
+            0xF3 => (2, 0),
+            0xF4 => (6, 1),
+            0xF5 => (4, 1),
+            0xF7 => (1, 1),
+            0xF8 => (4, 1),
+            0xF9 => (3, 1),
+            0xFA => (6, 1),
+            0xFB => (3, 1),
+            0xFD => (2, 0),
+            0xFE => (0, 0),
+            0xFF => (1, 0),
+            _ => return None,
+        })
+    }
+
+    // Size of the immediate following the opcode. RJUMPV is followed by a jump table whose size depends on its first immediate byte
+    pub const fn immediate_size(&self) -> usize {
+        match self.0 {
+            0x60..=0x7F => (self.0 - 0x5F) as usize,
+            0xD1 | 0xE0 | 0xE1 | 0xE3 | 0xE5 => 2,
+            0xE2 | 0xE6..=0xE8 | 0xEC | 0xEE => 1,
+            _ => 0,
+        }
+    }
+
+    pub const fn is_valid_in_eof(&self) -> bool {
+        !matches!(self.0, 0x38 | 0x39 | 0x3B | 0x3C | 0x3F | 0x56..=0x58 | 0x5A | 0xF0..=0xF2 | 0xF4 | 0xF5 | 0xFA | 0xFF) && self.stack_io().is_some()
+    }
+
+    // Gas that a legacy instruction costs whatever its operands, which basic-block metering charges upfront.
+    // Opcodes that read the remaining gas have none, so that they see it as if metered one instruction at a time
+    pub const fn static_gas(&self) -> usize {
+        match self.0 {
+            0x01 | 0x03 | 0x10..=0x1D | 0x35 | 0x37 | 0x39 | 0x3E | 0x49 | 0x51..=0x53 | 0x5E | 0x60..=0x9F => 3,
+            0x02 | 0x04..=0x07 | 0x0B | 0x47 => 5,
+            0x08 | 0x09 | 0x56 => 8,
+            0x0A | 0x57 => 10,
+            0x20 => 30,
+            0x30 | 0x32..=0x34 | 0x36 | 0x38 | 0x3A | 0x3D | 0x41..=0x46 | 0x48 | 0x4A | 0x50 | 0x58 | 0x59 | 0x5F => 2,
+            0x40 => 20,
+            0x5B => 1,
+            0x5C | 0x5D => 100,
+            0xA0..=0xA4 => 375 * (self.0 - 0x9F) as usize,
+            0xFF => 5000,
+            _ => 0,
+        }
+    }
+
+    pub const fn is_create(&self) -> bool {
+        matches!(self.0, 0xEC | 0xF0 | 0xF5)
+    }
+
+    pub const fn is_terminating(&self) -> bool {
+        matches!(self.0, 0x00 | 0xE4 | 0xE5 | 0xEE | 0xF3 | 0xFD | 0xFE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use super::*;
+
+    #[test]
+    fn builds_the_instruction_tables() {
+        let cctx = &mut CallContext::default();
+        cctx.stack.push_array([uint!("1"), uint!("2")]).unwrap();
+        assert!((INSTRUCTIONS.0[0x01].execute)(&mut WorldState::default(), &TransactionContext::default(), cctx).is_ok());
+        assert_eq!(cctx.stack.pop(), Some(uint!("3")));
+
+        assert_eq!(INSTRUCTIONS.0[0x61].immediate_size, 2);
+        assert_eq!(INSTRUCTIONS.0[0x61].stack_io, Some((0, 1)));
+        assert!(INSTRUCTIONS.0[0xF3].terminating);
+        assert_eq!(INSTRUCTIONS.0[0x0C].stack_io, None);
+        assert_eq!(INSTRUCTIONS.0[0xA2].static_gas, 1125);
+        assert_eq!(INSTRUCTIONS.0[0x5A].static_gas, 0);
+
+        // JUMP is undefined in EOF code
+        cctx.contract.gas = 100;
+        assert!((INSTRUCTIONS.0[0x56].execute)(&mut WorldState::default(), &TransactionContext::default(), cctx).is_err());
+        assert!((EOF_INSTRUCTIONS.0[0x56].execute)(&mut WorldState::default(), &TransactionContext::default(), cctx).is_ok());
+        assert!(cctx.revert);
     }
 }
@@ -0,0 +1,185 @@
+use ethnum::U256;
+use std::collections::HashSet;
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::blockchain::{AccessLog, StateKey, WorldState};
+use crate::blockchain::backend::{ChangeSet, StateBackendMut};
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::{Block, Transaction};
+use crate::machine::{ExecutionResult, Machine};
+use crate::machine::context::TransactionContext;
+
+// Outcome of a transaction run against the state committed by the `version` transactions before it
+struct Execution {
+    reads: HashSet<StateKey>,
+    result: ExecutionResult,
+    version: usize,
+    writes: ChangeSet,
+}
+
+impl Execution {
+    // Whether the transaction would have run the same after the ones committed since its snapshot
+    fn is_valid(&self, committed: &[HashSet<StateKey>]) -> bool {
+        committed[self.version..].iter().all(|written| written.is_disjoint(&self.reads))
+    }
+}
+
+impl Machine {
+    // Runs on an empty state that reads through to the committed one, so only what the transaction touches is copied
+    fn execute_speculatively(committed: &Arc<WorldState>, version: usize, block: &Block, tx: &Transaction) -> Execution {
+        let log = Arc::new(Mutex::new(AccessLog::default()));
+        let s = &mut WorldState { backend: Some(committed.clone()), chain_id: committed.chain_id, log: Some(log.clone()), ..Default::default() };
+        let result = Machine::execute_transaction(s, &TransactionContext { block: block.clone(), inspector: None, tx: tx.clone() });
+
+        let AccessLog { reads, writes: keys, .. } = std::mem::take(&mut *log.lock().unwrap());
+        let mut writes = ChangeSet::default();
+        for key in keys {
+            match key {
+                StateKey::Account(address) => { writes.accounts.insert(address, s.accounts.0.get(&address).map(|v| v.value.clone()).unwrap_or_default()); },
+                StateKey::Slot(address, key) => { writes.storage.entry(address).or_default().insert(key, s.storage.get(&address).and_then(|storage| storage.0.get(&key)).map_or(U256::ZERO, |v| v.value)); },
+            }
+        }
+        Execution { reads, result, version, writes }
+    }
+
+    // Runs the given transactions on up to `threads` threads, all against the same committed state
+    fn execute_speculatively_all(s: &Arc<WorldState>, version: usize, block: &Block, txs: &[Transaction], indices: &[usize], threads: usize) -> Vec<(usize, Execution)> {
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.clamp(1, indices.len())).map(|_| scope.spawn(|| {
+                let mut executions = vec![];
+                while let Some(i) = indices.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    executions.push((*i, Machine::execute_speculatively(s, version, block, &txs[*i])));
+                }
+                executions
+            })).collect();
+            workers.into_iter().flat_map(|worker| worker.join().unwrap_or_else(|payload| panic::resume_unwind(payload))).collect()
+        })
+    }
+
+    // Executes the transactions of a block optimistically in parallel, in the style of Block-STM, with the same
+    // results and final world state as execute_block. Every round runs the transactions that are known to be stale
+    // against the committed state, then commits in block order those whose reads were not written since their snapshot
+    pub fn execute_block_parallel(s: &mut WorldState, block: &Block, txs: &[Transaction], threads: usize) -> Vec<ExecutionResult> {
+        s.commit();
+        let mut executions: Vec<Option<Execution>> = txs.iter().map(|_| None).collect();
+        let mut committed: Vec<HashSet<StateKey>> = Vec::with_capacity(txs.len());
+        let mut results = Vec::with_capacity(txs.len());
+        let mut pending: Vec<usize> = (0..txs.len()).collect();
+
+        while !pending.is_empty() {
+            // Shared with the speculative states for the round, which are all dropped by its end
+            let shared = Arc::new(std::mem::take(s));
+            for (i, execution) in Machine::execute_speculatively_all(&shared, results.len(), block, txs, &pending, threads) {
+                executions[i] = Some(execution);
+            }
+            *s = Arc::into_inner(shared).expect("speculative states do not outlive their round");
+
+            // The first pending transaction ran against the latest state, so every round commits at least one
+            while let Some(execution) = executions.get_mut(results.len()).and_then(|e| e.take_if(|e| e.is_valid(&committed))) {
+                committed.push(execution.writes.keys().collect());
+                match s.apply(execution.writes) {
+                    Ok(()) => results.push(execution.result),
+                    Err(error) => results.push(Err(Error::Backend(error))),
+                }
+            }
+
+            pending = (results.len()..txs.len()).filter(|i| !executions[*i].as_ref().is_some_and(|e| e.is_valid(&committed))).collect();
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::{u256, uint};
+    use super::*;
+    use crate::blockchain::primitives::{Account, Address};
+
+    // PUSH0 SLOAD PUSH1 1 ADD PUSH0 SSTORE STOP
+    const COUNTER: [u8; 8] = [0x5F, 0x54, 0x60, 0x01, 0x01, 0x5F, 0x55, 0x00];
+
+    fn world_state() -> WorldState {
+        let mut s = WorldState::default();
+        for i in 1..=4u8 {
+            s.accounts.store(Address(u256::from(i)), Account { balance: uint!("100000000"), code: Default::default(), nonce: 0 });
+        }
+        s.accounts.store(Address(uint!("0xC0")), Account { balance: uint!("0"), code: COUNTER.to_vec().into(), nonce: 1 });
+        s
+    }
+
+    fn tx(from: u8, to: u256, value: u256) -> Transaction {
+        Transaction {
+            authorization_list: vec![],
+            data: vec![],
+            from: Address(u256::from(from)),
+            gas: 100000,
+            gas_price: 10,
+            nonce: 0,
+            to: Address(to),
+            value,
+        }
+    }
+
+    // Values of the accounts and slots, leaving out the ones that were only read
+    fn accounts(s: &WorldState) -> Vec<(u256, Account)> {
+        let mut accounts: Vec<_> = s.accounts.0.iter().filter(|(_, v)| v.value != Account::default()).map(|(a, v)| (a.0, v.value.clone())).collect();
+        accounts.sort_by_key(|(a, _)| *a);
+        accounts
+    }
+
+    fn slots(s: &WorldState) -> Vec<(u256, u256, u256)> {
+        let mut slots: Vec<_> = s.storage.iter()
+            .flat_map(|(a, storage)| storage.0.iter().filter(|(_, v)| v.value != 0).map(|(k, v)| (a.0, *k, v.value)))
+            .collect();
+        slots.sort();
+        slots
+    }
+
+    fn assert_same_as_sequential(txs: &[Transaction]) {
+        let (sequential, parallel) = (&mut world_state(), &mut world_state());
+        let expected = Machine::execute_block(sequential, &Block::default(), txs);
+
+        assert_eq!(Machine::execute_block_parallel(parallel, &Block::default(), txs, 4), expected);
+        assert_eq!(accounts(parallel), accounts(sequential));
+        assert_eq!(slots(parallel), slots(sequential));
+        assert_eq!(parallel.take_changes(), sequential.take_changes());
+    }
+
+    #[test]
+    fn executes_independent_transactions() {
+        assert_same_as_sequential(&[
+            tx(1, uint!("0xA1"), uint!("0")),
+            tx(2, uint!("0xA2"), uint!("0")),
+            tx(3, uint!("0xA3"), uint!("0")),
+            tx(4, uint!("0xA4"), uint!("0")),
+        ]);
+    }
+
+    #[test]
+    fn reexecutes_conflicting_transactions() {
+        let txs: Vec<_> = (0..12).map(|i| Transaction { nonce: i as usize / 4, ..tx(i % 4 + 1, uint!("0xC0"), uint!("0")) }).collect();
+        assert_same_as_sequential(&txs);
+
+        let s = &mut world_state();
+        Machine::execute_block_parallel(s, &Block::default(), &txs, 4);
+        assert_eq!(s.load_slot(Address(uint!("0xC0")), uint!("0")).unwrap().value, uint!("12"));
+        assert!(s.accounts.0.values().all(|v| !v.warm));
+    }
+
+    #[test]
+    fn commits_failed_transactions_in_order() {
+        assert_same_as_sequential(&[
+            tx(1, uint!("0xC0"), uint!("0")),
+            tx(5, uint!("0xC0"), uint!("0")), // no funds
+            Transaction { gas: 21004, ..tx(2, uint!("0xC0"), uint!("0")) }, // out of gas
+            Transaction { nonce: 1, ..tx(2, uint!("0xC0"), uint!("0")) },
+            tx(3, uint!("0xC0"), uint!("0")),
+            tx(3, uint!("0xC0"), uint!("0")), // replayed
+        ]);
+    }
+}
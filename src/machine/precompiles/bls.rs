@@ -0,0 +1,324 @@
+use blst::{
+    blst_bendian_from_fp, blst_final_exp, blst_fp, blst_fp12, blst_fp12_is_one, blst_fp2, blst_fp_from_bendian,
+    blst_map_to_g1, blst_map_to_g2, blst_miller_loop_n, blst_p1, blst_p1_add_or_double_affine, blst_p1_affine,
+    blst_p1_affine_in_g1, blst_p1_affine_on_curve, blst_p1_from_affine, blst_p1_to_affine, blst_p2,
+    blst_p2_add_or_double_affine, blst_p2_affine, blst_p2_affine_in_g2, blst_p2_affine_on_curve, blst_p2_from_affine,
+    blst_p2_to_affine, MultiPoint,
+};
+use crate::blockchain::errors::Error;
+use crate::machine::precompiles::{PrecompileOutput, PrecompileResult, Precompiles};
+
+// Field elements are 48 bytes big endian values left padded to 64 bytes
+const FP_LENGTH: usize = 48;
+const PADDED_FP_LENGTH: usize = 64;
+const PADDED_G1_LENGTH: usize = 2 * PADDED_FP_LENGTH;
+const PADDED_G2_LENGTH: usize = 4 * PADDED_FP_LENGTH;
+const SCALAR_LENGTH: usize = 32;
+
+const MODULUS: [u8; FP_LENGTH] = [
+    0x1A, 0x01, 0x11, 0xEA, 0x39, 0x7F, 0xE6, 0x9A, 0x4B, 0x1B, 0xA7, 0xB6, 0x43, 0x4B, 0xAC, 0xD7,
+    0x64, 0x77, 0x4B, 0x84, 0xF3, 0x85, 0x12, 0xBF, 0x67, 0x30, 0xD2, 0xA0, 0xF6, 0xB0, 0xF6, 0x24,
+    0x1E, 0xAB, 0xFF, 0xFE, 0xB1, 0x53, 0xFF, 0xFF, 0xB9, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xAA, 0xAB,
+];
+
+// Per mille discounts applied to k point-scalar pairs multi-scalar multiplications, capped at k = 128
+const G1_MSM_DISCOUNTS: [usize; 128] = [
+    1000, 949, 848, 797, 764, 750, 738, 728, 719, 712, 705, 698, 692, 687, 682, 677, 673, 669, 665, 661, 658, 654,
+    651, 648, 645, 642, 640, 637, 635, 632, 630, 627, 625, 623, 621, 619, 617, 615, 613, 611, 609, 608, 606, 604,
+    603, 601, 599, 598, 596, 595, 593, 592, 591, 589, 588, 586, 585, 584, 582, 581, 580, 579, 577, 576, 575, 574,
+    573, 572, 570, 569, 568, 567, 566, 565, 564, 563, 562, 561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 551,
+    550, 549, 548, 547, 547, 546, 545, 544, 543, 542, 541, 540, 540, 539, 538, 537, 536, 536, 535, 534, 533, 532,
+    532, 531, 530, 529, 528, 528, 527, 526, 525, 525, 524, 523, 522, 522, 521, 520, 520, 519,
+];
+const G2_MSM_DISCOUNTS: [usize; 128] = [
+    1000, 1000, 923, 884, 855, 832, 812, 796, 782, 770, 759, 749, 740, 732, 724, 717, 711, 704, 699, 693, 688, 683,
+    679, 674, 670, 666, 663, 659, 655, 652, 649, 646, 643, 640, 637, 634, 632, 629, 627, 624, 622, 620, 618, 615,
+    613, 611, 609, 607, 606, 604, 602, 600, 598, 597, 595, 593, 592, 590, 589, 587, 586, 584, 583, 582, 580, 579,
+    578, 576, 575, 574, 573, 571, 570, 569, 568, 567, 566, 565, 563, 562, 561, 560, 559, 558, 557, 556, 555, 554,
+    553, 552, 552, 551, 550, 549, 548, 547, 546, 545, 545, 544, 543, 542, 541, 541, 540, 539, 538, 537, 537, 536,
+    535, 535, 534, 533, 532, 532, 531, 530, 530, 529, 528, 528, 527, 526, 526, 525, 524, 524,
+];
+
+fn msm_cost(k: usize, discounts: &[usize; 128], multiplication_cost: usize) -> usize {
+    (k * discounts[std::cmp::min(k, 128) - 1] * multiplication_cost) / 1000
+}
+
+fn decode_fp(input: &[u8]) -> Result<blst_fp, Error> {
+    let (padding, value) = input.split_at(PADDED_FP_LENGTH - FP_LENGTH);
+    if padding.iter().any(|b| *b != 0) || value >= &MODULUS[..] { return Err(Error::PrecompileFailure); }
+
+    let mut fp = blst_fp::default();
+    unsafe { blst_fp_from_bendian(&mut fp, value.as_ptr()) };
+    Ok(fp)
+}
+
+fn decode_fp2(input: &[u8]) -> Result<blst_fp2, Error> {
+    Ok(blst_fp2 { fp: [decode_fp(&input[..PADDED_FP_LENGTH])?, decode_fp(&input[PADDED_FP_LENGTH..])?] })
+}
+
+fn decode_g1(input: &[u8], subgroup_check: bool) -> Result<blst_p1_affine, Error> {
+    let point = blst_p1_affine { x: decode_fp(&input[..PADDED_FP_LENGTH])?, y: decode_fp(&input[PADDED_FP_LENGTH..])? };
+    if unsafe { !blst_p1_affine_on_curve(&point) } { return Err(Error::PrecompileFailure); }
+    if subgroup_check && unsafe { !blst_p1_affine_in_g1(&point) } { return Err(Error::PrecompileFailure); }
+    Ok(point)
+}
+
+fn decode_g2(input: &[u8], subgroup_check: bool) -> Result<blst_p2_affine, Error> {
+    let point = blst_p2_affine { x: decode_fp2(&input[..2 * PADDED_FP_LENGTH])?, y: decode_fp2(&input[2 * PADDED_FP_LENGTH..])? };
+    if unsafe { !blst_p2_affine_on_curve(&point) } { return Err(Error::PrecompileFailure); }
+    if subgroup_check && unsafe { !blst_p2_affine_in_g2(&point) } { return Err(Error::PrecompileFailure); }
+    Ok(point)
+}
+
+fn encode_fp(output: &mut Vec<u8>, fp: &blst_fp) {
+    let mut value = [0_u8; FP_LENGTH];
+    unsafe { blst_bendian_from_fp(value.as_mut_ptr(), fp) };
+    output.extend_from_slice(&[0_u8; PADDED_FP_LENGTH - FP_LENGTH]);
+    output.extend_from_slice(&value);
+}
+
+fn encode_g1(point: &blst_p1) -> Vec<u8> {
+    let mut affine = blst_p1_affine::default();
+    unsafe { blst_p1_to_affine(&mut affine, point) };
+    let mut output = Vec::with_capacity(PADDED_G1_LENGTH);
+    encode_fp(&mut output, &affine.x);
+    encode_fp(&mut output, &affine.y);
+    output
+}
+
+fn encode_g2(point: &blst_p2) -> Vec<u8> {
+    let mut affine = blst_p2_affine::default();
+    unsafe { blst_p2_to_affine(&mut affine, point) };
+    let mut output = Vec::with_capacity(PADDED_G2_LENGTH);
+    for fp in [&affine.x.fp[0], &affine.x.fp[1], &affine.y.fp[0], &affine.y.fp[1]] {
+        encode_fp(&mut output, fp);
+    }
+    output
+}
+
+fn is_zero(input: &[u8]) -> bool {
+    input.iter().all(|b| *b == 0)
+}
+
+impl Precompiles {
+    pub fn bls12_g1add(input: &[u8]) -> PrecompileResult {
+        if input.len() != 2 * PADDED_G1_LENGTH { return Err(Error::PrecompileFailure); }
+
+        let (a, b) = (decode_g1(&input[..PADDED_G1_LENGTH], false)?, decode_g1(&input[PADDED_G1_LENGTH..], false)?);
+        let (mut a_jacobian, mut result) = (blst_p1::default(), blst_p1::default());
+        unsafe {
+            blst_p1_from_affine(&mut a_jacobian, &a);
+            blst_p1_add_or_double_affine(&mut result, &a_jacobian, &b);
+        }
+        Ok(PrecompileOutput { cost: 375, data: encode_g1(&result) })
+    }
+
+    pub fn bls12_g1msm(input: &[u8]) -> PrecompileResult {
+        if input.is_empty() || !input.len().is_multiple_of(PADDED_G1_LENGTH + SCALAR_LENGTH) { return Err(Error::PrecompileFailure); }
+
+        let k = input.len() / (PADDED_G1_LENGTH + SCALAR_LENGTH);
+        let (mut points, mut scalars) = (Vec::<blst_p1_affine>::with_capacity(k), Vec::<u8>::with_capacity(k * SCALAR_LENGTH));
+        for pair in input.chunks(PADDED_G1_LENGTH + SCALAR_LENGTH) {
+            let point = decode_g1(&pair[..PADDED_G1_LENGTH], true)?;
+            let scalar = &pair[PADDED_G1_LENGTH..];
+            if is_zero(scalar) { continue; }
+            points.push(point);
+            scalars.extend(scalar.iter().rev()); // blst expects little endian scalars
+        }
+        let result = if points.is_empty() { blst_p1::default() } else { points.mult(&scalars, 8 * SCALAR_LENGTH) };
+        Ok(PrecompileOutput { cost: msm_cost(k, &G1_MSM_DISCOUNTS, 12000), data: encode_g1(&result) })
+    }
+
+    pub fn bls12_g2add(input: &[u8]) -> PrecompileResult {
+        if input.len() != 2 * PADDED_G2_LENGTH { return Err(Error::PrecompileFailure); }
+
+        let (a, b) = (decode_g2(&input[..PADDED_G2_LENGTH], false)?, decode_g2(&input[PADDED_G2_LENGTH..], false)?);
+        let (mut a_jacobian, mut result) = (blst_p2::default(), blst_p2::default());
+        unsafe {
+            blst_p2_from_affine(&mut a_jacobian, &a);
+            blst_p2_add_or_double_affine(&mut result, &a_jacobian, &b);
+        }
+        Ok(PrecompileOutput { cost: 600, data: encode_g2(&result) })
+    }
+
+    pub fn bls12_g2msm(input: &[u8]) -> PrecompileResult {
+        if input.is_empty() || !input.len().is_multiple_of(PADDED_G2_LENGTH + SCALAR_LENGTH) { return Err(Error::PrecompileFailure); }
+
+        let k = input.len() / (PADDED_G2_LENGTH + SCALAR_LENGTH);
+        let (mut points, mut scalars) = (Vec::<blst_p2_affine>::with_capacity(k), Vec::<u8>::with_capacity(k * SCALAR_LENGTH));
+        for pair in input.chunks(PADDED_G2_LENGTH + SCALAR_LENGTH) {
+            let point = decode_g2(&pair[..PADDED_G2_LENGTH], true)?;
+            let scalar = &pair[PADDED_G2_LENGTH..];
+            if is_zero(scalar) { continue; }
+            points.push(point);
+            scalars.extend(scalar.iter().rev()); // blst expects little endian scalars
+        }
+        let result = if points.is_empty() { blst_p2::default() } else { points.mult(&scalars, 8 * SCALAR_LENGTH) };
+        Ok(PrecompileOutput { cost: msm_cost(k, &G2_MSM_DISCOUNTS, 22500), data: encode_g2(&result) })
+    }
+
+    pub fn bls12_pairing_check(input: &[u8]) -> PrecompileResult {
+        if input.is_empty() || !input.len().is_multiple_of(PADDED_G1_LENGTH + PADDED_G2_LENGTH) { return Err(Error::PrecompileFailure); }
+
+        let k = input.len() / (PADDED_G1_LENGTH + PADDED_G2_LENGTH);
+        let (mut g1_points, mut g2_points) = (Vec::<blst_p1_affine>::with_capacity(k), Vec::<blst_p2_affine>::with_capacity(k));
+        for pair in input.chunks(PADDED_G1_LENGTH + PADDED_G2_LENGTH) {
+            let (g1, g2) = (decode_g1(&pair[..PADDED_G1_LENGTH], true)?, decode_g2(&pair[PADDED_G1_LENGTH..], true)?);
+            // pairs involving the point at infinity contribute the identity
+            if is_zero(&pair[..PADDED_G1_LENGTH]) || is_zero(&pair[PADDED_G1_LENGTH..]) { continue; }
+            g1_points.push(g1);
+            g2_points.push(g2);
+        }
+        let success = g1_points.is_empty() || unsafe {
+            let (mut miller_loop, mut result) = (blst_fp12::default(), blst_fp12::default());
+            let (ps, qs) = ([g1_points.as_ptr(), std::ptr::null()], [g2_points.as_ptr(), std::ptr::null()]);
+            blst_miller_loop_n(&mut miller_loop, qs.as_ptr(), ps.as_ptr(), g1_points.len());
+            blst_final_exp(&mut result, &miller_loop);
+            blst_fp12_is_one(&result)
+        };
+        let mut data = vec![0_u8; 32];
+        data[31] = success.into();
+        Ok(PrecompileOutput { cost: 32600 * k + 37700, data })
+    }
+
+    pub fn bls12_map_fp_to_g1(input: &[u8]) -> PrecompileResult {
+        if input.len() != PADDED_FP_LENGTH { return Err(Error::PrecompileFailure); }
+
+        let fp = decode_fp(input)?;
+        let mut result = blst_p1::default();
+        unsafe { blst_map_to_g1(&mut result, &fp, std::ptr::null()) };
+        Ok(PrecompileOutput { cost: 5500, data: encode_g1(&result) })
+    }
+
+    pub fn bls12_map_fp2_to_g2(input: &[u8]) -> PrecompileResult {
+        if input.len() != 2 * PADDED_FP_LENGTH { return Err(Error::PrecompileFailure); }
+
+        let fp2 = decode_fp2(input)?;
+        let mut result = blst_p2::default();
+        unsafe { blst_map_to_g2(&mut result, &fp2, std::ptr::null()) };
+        Ok(PrecompileOutput { cost: 23800, data: encode_g2(&result) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G1: &str = "0000000000000000000000000000000017f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb0000000000000000000000000000000008b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1";
+    const NEG_G1: &str = "0000000000000000000000000000000017f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb00000000000000000000000000000000114d1d6855d545a8aa7d76c8cf2e21f267816aef1db507c96655b9d5caac42364e6f38ba0ecb751bad54dcd6b939c2ca";
+    const G2: &str = "00000000000000000000000000000000024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb80000000000000000000000000000000013e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e000000000000000000000000000000000ce5d527727d6e118cc9cdc6da2e351aadfd9baa8cbdd3a76d429a695160d12c923ac9cc3baca289e193548608b82801000000000000000000000000000000000606c4a02ea734cc32acd2b02bc28b99cb3e287e85a763af267492ab572e99ab3f370d275cec1da1aaa9075ff05f79be";
+    const G1_INFINITY: &str = "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+
+    fn input(parts: &[&str]) -> Vec<u8> {
+        hex::decode(parts.concat()).unwrap()
+    }
+
+    #[test]
+    fn computes_msm_cost() {
+        assert_eq!(msm_cost(1, &G1_MSM_DISCOUNTS, 12000), 12000);
+        assert_eq!(msm_cost(2, &G1_MSM_DISCOUNTS, 12000), 22776);
+        assert_eq!(msm_cost(1, &G2_MSM_DISCOUNTS, 22500), 22500);
+        assert_eq!(msm_cost(2, &G2_MSM_DISCOUNTS, 22500), 45000);
+        assert_eq!(msm_cost(128, &G1_MSM_DISCOUNTS, 12000), 797184);
+        assert_eq!(msm_cost(200, &G1_MSM_DISCOUNTS, 12000), 1245600); // discount is capped at k = 128
+    }
+
+    #[test]
+    fn bls12_g1add() {
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1, G1])), Ok(PrecompileOutput {
+            cost: 375,
+            data: hex::decode("000000000000000000000000000000000572cbea904d67468808c8eb50a9450c9721db309128012543902d0ac358a62ae28f75bb8f1c7c42c39a8c5529bf0f4e00000000000000000000000000000000166a9d8cabc673a322fda673779d8e3822ba3ecb8670e461f73bb9021d5fd76a4c56d9d4cd16bd1bba86881979749d28").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1, G1_INFINITY])), Ok(PrecompileOutput { cost: 375, data: input(&[G1]) }));
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1, NEG_G1])), Ok(PrecompileOutput { cost: 375, data: input(&[G1_INFINITY]) }));
+    }
+
+    #[test]
+    fn fails_to_add_invalid_g1_points() {
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1])), Err(Error::PrecompileFailure));
+        // the point is not on the curve
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1, &G1.replace("e1", "e2")])), Err(Error::PrecompileFailure));
+        // the padding is not zero
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1, &G1.replacen("00", "01", 1)])), Err(Error::PrecompileFailure));
+        // the coordinate is not lower than the field modulus
+        assert_eq!(Precompiles::bls12_g1add(&input(&[G1, "000000000000000000000000000000001a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab", &G1[128..]])), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn bls12_g1msm() {
+        assert_eq!(Precompiles::bls12_g1msm(&input(&[G1, "0000000000000000000000000000000000000000000000000000000000000003"])), Ok(PrecompileOutput {
+            cost: 12000,
+            data: hex::decode("0000000000000000000000000000000009ece308f9d1f0131765212deca99697b112d61f9be9a5f1f3780a51335b3ff981747a0b2ca2179b96d2c0c9024e522400000000000000000000000000000000032b80d3a6f5b09f8a84623389c5f80ca69a0cddabc3097f9d9c27310fd43be6e745256c634af45ca3473b0590ae30d1").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_g1msm(&input(&[G1, "0000000000000000000000000000000000000000000000000000000000000001", G1, "0000000000000000000000000000000000000000000000000000000000000001"])), Ok(PrecompileOutput {
+            cost: 22776,
+            data: hex::decode("000000000000000000000000000000000572cbea904d67468808c8eb50a9450c9721db309128012543902d0ac358a62ae28f75bb8f1c7c42c39a8c5529bf0f4e00000000000000000000000000000000166a9d8cabc673a322fda673779d8e3822ba3ecb8670e461f73bb9021d5fd76a4c56d9d4cd16bd1bba86881979749d28").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_g1msm(&input(&[G1, "0000000000000000000000000000000000000000000000000000000000000000"])), Ok(PrecompileOutput { cost: 12000, data: input(&[G1_INFINITY]) }));
+        assert_eq!(Precompiles::bls12_g1msm(&[]), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn checks_g1_subgroup_membership_for_msm_only() {
+        // (4, sqrt(4^3 + 4)) is on the curve but not in the G1 subgroup
+        let point = "00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000a989badd40d6212b33cffc3f3763e9bc760f988c9926b26da9dd85e928483446346b8ed00e1de5d5ea93e354abe706c";
+
+        assert!(Precompiles::bls12_g1add(&input(&[point, G1_INFINITY])).is_ok());
+        assert_eq!(Precompiles::bls12_g1msm(&input(&[point, "0000000000000000000000000000000000000000000000000000000000000001"])), Err(Error::PrecompileFailure));
+        assert_eq!(Precompiles::bls12_pairing_check(&input(&[point, G2])), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn bls12_g2add() {
+        assert_eq!(Precompiles::bls12_g2add(&input(&[G2, G2])), Ok(PrecompileOutput {
+            cost: 600,
+            data: hex::decode("000000000000000000000000000000001638533957d540a9d2370f17cc7ed5863bc0b995b8825e0ee1ea1e1e4d00dbae81f14b0bf3611b78c952aacab827a053000000000000000000000000000000000a4edef9c1ed7f729f520e47730a124fd70662a904ba1074728114d1031e1572c6c886f6b57ec72a6178288c47c33577000000000000000000000000000000000468fb440d82b0630aeb8dca2b5256789a66da69bf91009cbfe6bd221e47aa8ae88dece9764bf3bd999d95d71e4c9899000000000000000000000000000000000f6d4552fa65dd2638b361543f887136a43253d9c66c411697003f7a13c308f5422e1aa0a59c8967acdefd8b6e36ccf3").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_g2add(&input(&[G2, &G2[..500], "ff"])), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn bls12_g2msm() {
+        assert_eq!(Precompiles::bls12_g2msm(&input(&[G2, "0000000000000000000000000000000000000000000000000000000000000002", G2, "0000000000000000000000000000000000000000000000000000000000000001"])), Ok(PrecompileOutput {
+            cost: 45000,
+            data: hex::decode("00000000000000000000000000000000122915c824a0857e2ee414a3dccb23ae691ae54329781315a0c75df1c04d6d7a50a030fc866f09d516020ef82324afae0000000000000000000000000000000009380275bbc8e5dcea7dc4dd7e0550ff2ac480905396eda55062650f8d251c96eb480673937cc6d9d6a44aaa56ca66dc000000000000000000000000000000000b21da7955969e61010c7a1abc1a6f0136961d1e3b20b1a7326ac738fef5c721479dfd948b52fdf2455e44813ecfd8920000000000000000000000000000000008f239ba329b3967fe48d718a36cfe5f62a7e42e0bf1c1ed714150a166bfbd6bcf6b3b58b975b9edea56d53f23a0e849").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_g2msm(&input(&[G2])), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn bls12_pairing_check() {
+        // e(G1, G2) * e(-G1, G2) == 1
+        assert_eq!(Precompiles::bls12_pairing_check(&input(&[G1, G2, NEG_G1, G2])), Ok(PrecompileOutput {
+            cost: 102900,
+            data: hex::decode("0000000000000000000000000000000000000000000000000000000000000001").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_pairing_check(&input(&[G1, G2, G1, G2])), Ok(PrecompileOutput {
+            cost: 102900,
+            data: hex::decode("0000000000000000000000000000000000000000000000000000000000000000").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_pairing_check(&input(&[G1_INFINITY, G2])), Ok(PrecompileOutput {
+            cost: 70300,
+            data: hex::decode("0000000000000000000000000000000000000000000000000000000000000001").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_pairing_check(&input(&[G1])), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn bls12_map_fp_to_g1() {
+        assert_eq!(Precompiles::bls12_map_fp_to_g1(&input(&["00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000123456789"])), Ok(PrecompileOutput {
+            cost: 5500,
+            data: hex::decode("000000000000000000000000000000000dbcad1bce7c29305769809e03dd4eef1ca72ecdc8533643c3ae38321bb49655a3af555a1dc052ddf2e468591881d49c000000000000000000000000000000000f9b483bba9b4ee3e2cef816d98979e331ed90b8d173e03a6cb4384ccb1763c98a76f5910b9f22bedc72c88e75f32347").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_map_fp_to_g1(&input(&["000000000000000000000000000000001a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab"])), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn bls12_map_fp2_to_g2() {
+        assert_eq!(Precompiles::bls12_map_fp2_to_g2(&input(&["00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000123456789", "00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000abcdef"])), Ok(PrecompileOutput {
+            cost: 23800,
+            data: hex::decode("0000000000000000000000000000000011fe2ebf50f518a66d97ac5dd175062b3abd506d7d9caeb8ca76c52bf8efaa8053f6832d3e224c0b0acad36944e782210000000000000000000000000000000009dcfa825016b44f479840e5ccc1e06e58cff7f8680b8e89015490a4bd33d7965bae7f2478310814826f378661d56451000000000000000000000000000000000e132e56b43b805dc3f2fcc8c0e3f8d7d567903fbf40cfc83d6ceaec078d35044725007712cbdc871476fbdcc8675f13000000000000000000000000000000000e93f9a5715b491d486b1226b6a7c1d8ac4123d15c0ddbbfa7e99eaf163248b949d85790d162f0618f467b84517a49d5").unwrap(),
+        }));
+        assert_eq!(Precompiles::bls12_map_fp2_to_g2(&input(&[&G1[..128]])), Err(Error::PrecompileFailure));
+    }
+}
@@ -0,0 +1,112 @@
+use c_kzg::{Bytes32, Bytes48, KzgSettings};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::OnceLock;
+use crate::blockchain::errors::Error;
+use crate::machine::precompiles::{PrecompileOutput, PrecompileResult, Precompiles};
+
+const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+// FIELD_ELEMENTS_PER_BLOB (4096) and BLS_MODULUS as 32 bytes big endian values
+const POINT_EVALUATION_RETURN_VALUE: [u8; 64] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00,
+    0x73, 0xED, 0xA7, 0x53, 0x29, 0x9D, 0x7D, 0x48, 0x33, 0x39, 0xD8, 0x08, 0x09, 0xA1, 0xD8, 0x05,
+    0x53, 0xBD, 0xA4, 0x02, 0xFF, 0xFE, 0x5B, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x01,
+];
+
+static TRUSTED_SETUP: OnceLock<KzgSettings> = OnceLock::new();
+
+// Replaces the bundled mainnet trusted setup. Must be called before the first point evaluation
+pub fn load_trusted_setup_file(path: &Path) -> Result<(), Error> {
+    let settings = KzgSettings::load_trusted_setup_file(path, 0).map_err(|_| Error::InvalidTrustedSetup)?;
+    TRUSTED_SETUP.set(settings).map_err(|_| Error::InvalidTrustedSetup)
+}
+
+fn trusted_setup() -> &'static KzgSettings {
+    TRUSTED_SETUP.get().unwrap_or_else(|| c_kzg::ethereum_kzg_settings(0))
+}
+
+fn kzg_to_versioned_hash(commitment: &[u8]) -> [u8; 32] {
+    let mut hash: [u8; 32] = Sha256::digest(commitment).into();
+    hash[0] = VERSIONED_HASH_VERSION_KZG;
+    hash
+}
+
+impl Precompiles {
+    pub fn point_evaluation(input: &[u8]) -> PrecompileResult {
+        // versioned_hash (32) | z (32) | y (32) | commitment (48) | proof (48)
+        if input.len() != 192 { return Err(Error::PrecompileFailure); }
+
+        let commitment = &input[96..144];
+        if kzg_to_versioned_hash(commitment) != input[0..32] { return Err(Error::PrecompileFailure); }
+
+        let (commitment, z, y, proof) = match (Bytes48::from_bytes(commitment), Bytes32::from_bytes(&input[32..64]), Bytes32::from_bytes(&input[64..96]), Bytes48::from_bytes(&input[144..192])) {
+            (Ok(commitment), Ok(z), Ok(y), Ok(proof)) => (commitment, z, y, proof),
+            _ => return Err(Error::PrecompileFailure),
+        };
+        match trusted_setup().verify_kzg_proof(&commitment, &z, &y, &proof) {
+            Ok(true) => Ok(PrecompileOutput { cost: 50000, data: POINT_EVALUATION_RETURN_VALUE.to_vec() }),
+            _ => Err(Error::PrecompileFailure),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/ethereum/c-kzg-4844/blob/main/tests/verify_kzg_proof/kzg-mainnet/verify_kzg_proof_case_correct_proof_4_4/data.yaml
+    const COMMITMENT: &str = "8f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7";
+    const Z: &str = "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000";
+    const Y: &str = "1522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e9";
+    const PROOF: &str = "a62ad71d14c5719385c0686f1871430475bf3a00f0aa3f7b8dd99a9abc2160744faf0070725e00b60ad9a026a15b1a8c";
+
+    fn input(versioned_hash: [u8; 32], y: &str) -> Vec<u8> {
+        [versioned_hash.to_vec(), hex::decode(Z).unwrap(), hex::decode(y).unwrap(), hex::decode(COMMITMENT).unwrap(), hex::decode(PROOF).unwrap()].concat()
+    }
+
+    #[test]
+    fn computes_the_versioned_hash() {
+        let hash = Sha256::digest(hex::decode(COMMITMENT).unwrap());
+        let versioned_hash = kzg_to_versioned_hash(&hex::decode(COMMITMENT).unwrap());
+
+        assert_eq!(versioned_hash[0], 0x01);
+        assert_eq!(versioned_hash[1..], hash[1..]);
+    }
+
+    #[test]
+    fn point_evaluation() {
+        let versioned_hash = kzg_to_versioned_hash(&hex::decode(COMMITMENT).unwrap());
+
+        assert_eq!(Precompiles::point_evaluation(&input(versioned_hash, Y)), Ok(PrecompileOutput {
+            cost: 50000,
+            data: hex::decode("000000000000000000000000000000000000000000000000000000000000100073eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001").unwrap(),
+        }));
+    }
+
+    #[test]
+    fn fails_to_evaluate_a_point_with_an_invalid_proof() {
+        let versioned_hash = kzg_to_versioned_hash(&hex::decode(COMMITMENT).unwrap());
+
+        assert_eq!(Precompiles::point_evaluation(&input(versioned_hash, "1522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e8")), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn fails_to_evaluate_a_point_with_a_mismatching_versioned_hash() {
+        let mut versioned_hash = kzg_to_versioned_hash(&hex::decode(COMMITMENT).unwrap());
+        versioned_hash[0] = 0x02;
+
+        assert_eq!(Precompiles::point_evaluation(&input(versioned_hash, Y)), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn fails_to_evaluate_a_point_with_an_invalid_input_length() {
+        assert_eq!(Precompiles::point_evaluation(&[0; 191]), Err(Error::PrecompileFailure));
+    }
+
+    #[test]
+    fn fails_to_load_a_missing_trusted_setup_file() {
+        assert_eq!(load_trusted_setup_file(Path::new("/nonexistent/trusted_setup.txt")), Err(Error::InvalidTrustedSetup));
+    }
+}
@@ -0,0 +1,56 @@
+pub mod bls;
+pub mod kzg;
+
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct PrecompileOutput {
+    pub cost: usize,
+    pub data: Vec<u8>,
+}
+
+pub type PrecompileResult = Result<PrecompileOutput, Error>;
+
+pub struct Precompiles {}
+
+pub struct Precompile(pub u8);
+
+impl Precompile {
+    pub fn at(address: Address) -> Option<Self> {
+        match TryInto::<u8>::try_into(address.0) {
+            Ok(id @ 0x0A..=0x11) => Some(Self(id)),
+            _ => None,
+        }
+    }
+
+    pub fn execute(&self, input: &[u8]) -> PrecompileResult {
+        (match self.0 {
+            0x0A => Precompiles::point_evaluation,
+            0x0B => Precompiles::bls12_g1add,
+            0x0C => Precompiles::bls12_g1msm,
+            0x0D => Precompiles::bls12_g2add,
+            0x0E => Precompiles::bls12_g2msm,
+            0x0F => Precompiles::bls12_pairing_check,
+            0x10 => Precompiles::bls12_map_fp_to_g1,
+            0x11 => Precompiles::bls12_map_fp2_to_g2,
+            _ => unreachable!(),
+        })(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use super::*;
+
+    #[test]
+    fn finds_precompiles_by_address() {
+        assert!(Precompile::at(Address(uint!("0x0A"))).is_some());
+        assert!(Precompile::at(Address(uint!("0x11"))).is_some());
+        assert!(Precompile::at(Address(uint!("0x00"))).is_none());
+        assert!(Precompile::at(Address(uint!("0x12"))).is_none());
+        assert!(Precompile::at(Address(uint!("0x010A"))).is_none());
+        assert!(Precompile::at(Address(uint!("0xF0490D46185BEC962CAC93120B52389748E99C0C"))).is_none());
+    }
+}
@@ -1,30 +1,102 @@
-use ethnum::u256;
+use ethnum::{u256, U256};
+use std::cmp::max;
 use crate::blockchain::errors::Error;
 
-#[derive(Debug, Default)]
+pub const STACK_LIMIT: usize = 1024;
+
+// Items are stored bottom first in a preallocated buffer, so that no operation allocates
+#[derive(Debug)]
 pub struct Stack {
-    size: u16,
-    top: i32,
-    arr: Vec<u256>,
+    arr: Box<[u256; STACK_LIMIT]>,
+    len: usize,
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Stack {
     pub fn new() -> Self {
-        Self { size: 1024, top: -1, arr: Vec::<u256>::new() }
+        Self { arr: Box::new([U256::ZERO; STACK_LIMIT]), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Items from the bottom to the top
+    pub fn items(&self) -> &[u256] {
+        &self.arr[..self.len]
+    }
+
+    // Where the items and their count are, for compiled code that works on the stack in place
+    #[cfg(feature = "jit")]
+    pub(crate) fn raw_parts(&mut self) -> (*mut u256, *mut usize) {
+        (self.arr.as_mut_ptr(), &mut self.len)
     }
 
     pub fn pop(&mut self) -> Option<u256> {
-        match self.arr.pop() {
-            Option::None => None,
-            Option::Some(elt) => { self.top -= 1; Some(elt) },
-        }
+        if self.len == 0 { return None; }
+        self.len -= 1;
+        Some(self.arr[self.len])
     }
 
     pub fn push(&mut self, value: u256) -> Result<(), Error> {
-        match self.top + 1 == self.size.into() {
-            true => Err(Error::StackOverflow),
-            false => { self.top += 1; self.arr.push(value); Ok(()) },
+        if self.len == STACK_LIMIT { return Err(Error::StackOverflow); }
+        self.arr[self.len] = value;
+        self.len += 1;
+        Ok(())
+    }
+
+    // Returns the n-th item from the top, starting at 0
+    pub fn peek(&self, n: usize) -> Result<u256, Error> {
+        if n >= self.len { return Err(Error::EmptyStack); }
+        Ok(self.arr[self.len - 1 - n])
+    }
+
+    // Pushes a copy of the n-th item from the top, starting at 1 like DUPn
+    pub fn dup(&mut self, n: usize) -> Result<(), Error> {
+        let value = self.peek(n - 1)?;
+        self.push(value)
+    }
+
+    // Swaps the top item with the (n + 1)-th one, like SWAPn
+    pub fn swap(&mut self, n: usize) -> Result<(), Error> {
+        self.exchange(0, n)
+    }
+
+    // Swaps the n-th and m-th items from the top, starting at 0
+    pub fn exchange(&mut self, n: usize, m: usize) -> Result<(), Error> {
+        if max(n, m) >= self.len { return Err(Error::EmptyStack); }
+        self.arr.swap(self.len - 1 - n, self.len - 1 - m);
+        Ok(())
+    }
+
+    // Pops N items at once, the top one first
+    pub fn pop_array<const N: usize>(&mut self) -> Result<[u256; N], Error> {
+        if N > self.len { return Err(Error::EmptyStack); }
+        let mut values = [U256::ZERO; N];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = self.arr[self.len - 1 - i];
+        }
+        self.len -= N;
+        Ok(values)
+    }
+
+    // Pushes N items at once, so that the first one ends up on top
+    pub fn push_array<const N: usize>(&mut self, values: [u256; N]) -> Result<(), Error> {
+        if self.len + N > STACK_LIMIT { return Err(Error::StackOverflow); }
+        for (i, value) in values.into_iter().rev().enumerate() {
+            self.arr[self.len + i] = value;
         }
+        self.len += N;
+        Ok(())
     }
 }
 
@@ -33,6 +105,14 @@ mod tests {
     use super::*;
     use ethnum::uint;
 
+    impl Stack {
+        fn from_items(items: &[u256]) -> Self {
+            let mut stack = Stack::new();
+            for item in items { stack.push(*item).unwrap(); }
+            stack
+        }
+    }
+
     #[test]
     fn fail_to_pop_from_an_empty_stack() {
         let mut stack = Stack::new();
@@ -42,12 +122,11 @@ mod tests {
 
     #[test]
     fn pops_from_the_stack() {
-        let mut stack = Stack { size: 1, top: 0, arr: vec![uint!("7")] };
+        let mut stack = Stack::from_items(&[uint!("7")]);
 
         assert_eq!(stack.pop(), Some(uint!("7")));
-        assert_eq!(stack.size, 1);
-        assert_eq!(stack.top, -1);
-        assert_eq!(stack.arr.len(), 0);
+        assert_eq!(stack.len(), 0);
+        assert!(stack.is_empty());
     }
 
     #[test]
@@ -62,11 +141,58 @@ mod tests {
 
     #[test]
     fn pushes_to_the_stack() {
-        let mut stack = Stack { size: 1, top: -1, arr: vec!() };
+        let mut stack = Stack::new();
 
         assert_eq!(stack.push(uint!("7")), Ok(()));
-        assert_eq!(stack.size, 1);
-        assert_eq!(stack.top, 0);
-        assert_eq!(stack.arr, vec!(uint!("7")));
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.items(), [uint!("7")]);
+    }
+
+    #[test]
+    fn peeks_into_the_stack() {
+        let stack = Stack::from_items(&[uint!("1"), uint!("2")]);
+
+        assert_eq!(stack.peek(0), Ok(uint!("2")));
+        assert_eq!(stack.peek(1), Ok(uint!("1")));
+        assert_eq!(stack.peek(2), Err(Error::EmptyStack));
+        assert_eq!(stack.len(), 2);
+    }
+
+    #[test]
+    fn dups_stack_items() {
+        let mut stack = Stack::from_items(&[uint!("1"), uint!("2")]);
+
+        assert_eq!(stack.dup(2), Ok(()));
+        assert_eq!(stack.items(), [uint!("1"), uint!("2"), uint!("1")]);
+        assert_eq!(stack.dup(4), Err(Error::EmptyStack));
+
+        let mut stack = Stack::from_items(&[uint!("1"); 1024]);
+        assert_eq!(stack.dup(1), Err(Error::StackOverflow));
+    }
+
+    #[test]
+    fn swaps_stack_items() {
+        let mut stack = Stack::from_items(&[uint!("1"), uint!("2"), uint!("3")]);
+
+        assert_eq!(stack.swap(2), Ok(()));
+        assert_eq!(stack.items(), [uint!("3"), uint!("2"), uint!("1")]);
+        assert_eq!(stack.exchange(1, 2), Ok(()));
+        assert_eq!(stack.items(), [uint!("2"), uint!("3"), uint!("1")]);
+        assert_eq!(stack.swap(3), Err(Error::EmptyStack));
+    }
+
+    #[test]
+    fn pops_and_pushes_arrays() {
+        let mut stack = Stack::from_items(&[uint!("1"), uint!("2"), uint!("3")]);
+
+        assert_eq!(stack.pop_array::<2>(), Ok([uint!("3"), uint!("2")]));
+        assert_eq!(stack.items(), [uint!("1")]);
+        assert_eq!(stack.pop_array::<2>(), Err(Error::EmptyStack));
+        assert_eq!(stack.items(), [uint!("1")]);
+
+        assert_eq!(stack.push_array([uint!("4"), uint!("5")]), Ok(()));
+        assert_eq!(stack.items(), [uint!("1"), uint!("5"), uint!("4")]);
+        assert_eq!(stack.push_array([uint!("0"); 1022]), Err(Error::StackOverflow));
+        assert_eq!(stack.len(), 3);
     }
 }
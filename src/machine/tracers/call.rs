@@ -0,0 +1,252 @@
+use ethnum::u256;
+use serde_json::{json, Value};
+
+use crate::blockchain::WorldState;
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+use crate::machine::context::{CallContext, Halt};
+use crate::machine::inspector::Inspector;
+use crate::machine::opcode::OpCode;
+use crate::machine::tracers::{address, bytes, quantity};
+
+// Selector of `Error(string)`, which Solidity reverts with
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xC3, 0x79, 0xA0];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CallFrame {
+    pub calls: Vec<CallFrame>,
+    pub error: Option<String>,
+    pub from: Address,
+    pub gas: usize,
+    pub gas_used: usize,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+    pub revert_reason: Option<String>,
+    pub to: Address,
+    pub r#type: OpCode,
+    // Calls that cannot transfer any value have none
+    pub value: Option<u256>,
+}
+
+impl CallFrame {
+    // Same shape as the output of geth's callTracer
+    pub fn to_json(&self) -> Value {
+        let mut frame = json!({
+            "type": self.r#type.to_string(),
+            "from": address(self.from),
+            "to": address(self.to),
+        });
+        if let Some(value) = self.value { frame["value"] = quantity(value).into(); }
+        frame["gas"] = quantity(self.gas).into();
+        frame["gasUsed"] = quantity(self.gas_used).into();
+        frame["input"] = bytes(&self.input).into();
+        if !self.output.is_empty() { frame["output"] = bytes(&self.output).into(); }
+        if let Some(error) = &self.error { frame["error"] = error.clone().into(); }
+        if let Some(reason) = &self.revert_reason { frame["revertReason"] = reason.clone().into(); }
+        if !self.calls.is_empty() { frame["calls"] = self.calls.iter().map(CallFrame::to_json).collect(); }
+        frame
+    }
+}
+
+// Builds the tree of calls and creates of each transaction
+#[derive(Default)]
+pub struct CallTracer {
+    // Calls that have not returned yet, the innermost last
+    running: Vec<CallFrame>,
+    // Root frame of every transaction, in order
+    pub traces: Vec<CallFrame>,
+}
+
+impl CallTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Delegate calls run on the account of the caller, whose value they inherit, so they come `from` that account
+    fn enter(&mut self, cctx: &CallContext, opcode: OpCode, to: Address, input: Vec<u8>) {
+        let (from, value) = match opcode.0 {
+            0xF4 | 0xF9 => (cctx.contract.address, Some(cctx.contract.value)),
+            0xFA | 0xFB => (cctx.contract.caller, None),
+            _ => (cctx.contract.caller, Some(cctx.contract.value)),
+        };
+        self.running.push(CallFrame {
+            calls: vec![],
+            error: None,
+            from,
+            gas: cctx.contract.gas,
+            gas_used: 0,
+            input,
+            output: vec![],
+            revert_reason: None,
+            to,
+            r#type: opcode,
+            value,
+        });
+    }
+
+    fn exit(&mut self, cctx: &CallContext, result: &Result<(), Error>) {
+        let Some(mut frame) = self.running.pop() else { return };
+        frame.gas_used = frame.gas.saturating_sub(cctx.contract.gas);
+        frame.output = cctx.r#return.clone();
+        match (result, cctx.halt) {
+            (Err(error), _) => frame.error = Some(error_message(error)),
+            (Ok(()), Some(Halt::Revert)) => {
+                frame.error = Some("execution reverted".to_string());
+                frame.revert_reason = revert_reason(&cctx.r#return);
+            },
+            (Ok(()), Some(halt)) => frame.error = Some(halt_message(halt)),
+            (Ok(()), None) => {},
+        }
+
+        match self.running.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.traces.push(frame),
+        }
+    }
+}
+
+// Messages that geth gives to the same errors, so that trees can be compared
+fn error_message(error: &Error) -> String {
+    match error {
+        Error::EmptyStack => "stack underflow".to_string(),
+        Error::InvalidJumpDest => "invalid jump destination".to_string(),
+        Error::OutOfGas => "out of gas".to_string(),
+        Error::PrecompileFailure => "precompile failure".to_string(),
+        Error::StackOverflow => "stack limit reached 1024 (1023)".to_string(),
+        Error::StaticStateChange => "write protection".to_string(),
+        error => format!("{:?}", error),
+    }
+}
+
+fn halt_message(halt: Halt) -> String {
+    match halt {
+        Halt::InvalidCode => "invalid code: must not begin with 0xef".to_string(),
+        Halt::InvalidInitcode => "invalid initcode".to_string(),
+        Halt::InvalidOpcode(OpCode(0xFE)) => "invalid opcode: INVALID".to_string(),
+        Halt::InvalidOpcode(opcode) => format!("invalid opcode: opcode {:#x} not defined", opcode.0),
+        Halt::PrecompileFailure => "precompile failure".to_string(),
+        Halt::Revert => "execution reverted".to_string(),
+    }
+}
+
+// Decodes `Error(string)` revert data
+fn revert_reason(data: &[u8]) -> Option<String> {
+    if data.len() < 68 || data[..4] != ERROR_SELECTOR { return None; }
+    let length: usize = u256::from_be_bytes(data[36..68].try_into().unwrap()).try_into().ok()?;
+    let reason = data.get(68..68_usize.checked_add(length)?)?;
+    String::from_utf8(reason.to_vec()).ok()
+}
+
+impl Inspector for CallTracer {
+    fn call(&mut self, _s: &WorldState, cctx: &CallContext, opcode: OpCode, target: Address) {
+        self.enter(cctx, opcode, target, cctx.contract.input.clone());
+    }
+
+    fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, result: &Result<(), Error>) {
+        self.exit(cctx, result);
+    }
+
+    fn create(&mut self, _s: &WorldState, cctx: &CallContext, opcode: OpCode) {
+        self.enter(cctx, opcode, cctx.contract.address, cctx.contract.code.to_vec());
+    }
+
+    fn selfdestruct(&mut self, cctx: &CallContext, beneficiary: Address, balance: u256) {
+        let Some(parent) = self.running.last_mut() else { return };
+        parent.calls.push(CallFrame {
+            calls: vec![],
+            error: None,
+            from: cctx.contract.address,
+            gas: 0,
+            gas_used: 0,
+            input: vec![],
+            output: vec![],
+            revert_reason: None,
+            to: beneficiary,
+            r#type: OpCode(0xFF),
+            value: Some(balance),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use super::*;
+    use crate::evm::Evm;
+    use crate::machine::tracers::tests::inspect;
+
+    // Reverts with Error("nope")
+    const REVERT: &str = "6308C379A060E01B5F52 6020600452 6004602452 636E6F706560E01B604452 60645FFD";
+
+    fn trace() -> CallFrame {
+        let caller = "5F5F5F5F5F60C15AF1505F5F5F5F60C25AF45000"; // CALL 0xC1, DELEGATECALL 0xC2, STOP
+        let (mut tracer, result) = inspect(Evm::builder(), &[caller, REVERT, "FE"], &[0x12, 0x34], CallTracer::new());
+        result.unwrap();
+        assert_eq!(tracer.traces.len(), 1);
+        tracer.traces.remove(0)
+    }
+
+    #[test]
+    fn builds_the_call_tree() {
+        let root = trace();
+        assert_eq!((root.r#type, root.from, root.to, root.value), (OpCode(0xF1), Address(uint!("0xA0")), Address(uint!("0xC0")), Some(uint!("0"))));
+        assert_eq!((root.gas, root.input.as_slice(), root.error.as_deref()), (100000, [0x12, 0x34].as_slice(), None));
+        assert_eq!(root.calls.len(), 2);
+
+        let call = &root.calls[0];
+        assert_eq!((call.r#type, call.from, call.to), (OpCode(0xF1), Address(uint!("0xC0")), Address(uint!("0xC1"))));
+        assert_eq!(call.error.as_deref(), Some("execution reverted"));
+        assert_eq!(call.revert_reason.as_deref(), Some("nope"));
+        assert_eq!(call.output.len(), 100);
+        assert!(call.gas_used < call.gas);
+
+        let delegatecall = &root.calls[1];
+        assert_eq!((delegatecall.r#type, delegatecall.from, delegatecall.to, delegatecall.value), (OpCode(0xF4), Address(uint!("0xC0")), Address(uint!("0xC2")), Some(uint!("0"))));
+        assert_eq!(delegatecall.error.as_deref(), Some("invalid opcode: INVALID"));
+        assert_eq!(delegatecall.gas_used, delegatecall.gas);
+
+        assert_eq!(root.gas_used, 21032 + 2600 + call.gas_used + 2600 + delegatecall.gas_used + 32); // intrinsic, cold accounts, callees and instructions
+    }
+
+    #[test]
+    fn labels_halts() {
+        // CALL the BLS12-381 G1 addition precompile without any input, STATICCALL 0xC1, STOP, each call with 10000 gas
+        let caller = "5F5F5F5F5F600B612710F1505F5F5F5F60C1612710FA5000";
+        let (mut tracer, result) = inspect(Evm::builder(), &[caller, "0C"], &[], CallTracer::new());
+        result.unwrap();
+        let root = tracer.traces.remove(0);
+        assert_eq!(root.error, None);
+        assert_eq!(root.calls[0].error.as_deref(), Some("precompile failure"));
+        assert_eq!((root.calls[1].r#type, root.calls[1].value), (OpCode(0xFA), None));
+        assert_eq!(root.calls[1].error.as_deref(), Some("invalid opcode: opcode 0xc not defined"));
+    }
+
+    #[test]
+    fn writes_the_tree_like_geth() {
+        let root = trace().to_json();
+        assert_eq!(root["type"], "CALL");
+        assert_eq!(root["from"], "0x00000000000000000000000000000000000000a0");
+        assert_eq!(root["value"], "0x0");
+        assert_eq!(root["gas"], "0x186a0");
+        assert_eq!(root["input"], "0x1234");
+        assert!(root.get("output").is_none() && root.get("error").is_none());
+        assert_eq!(root["calls"][0]["revertReason"], "nope");
+        assert_eq!(root["calls"][1]["to"], "0x00000000000000000000000000000000000000c2");
+        assert_eq!(root["calls"][1]["from"], "0x00000000000000000000000000000000000000c0");
+        assert_eq!(root["calls"][1]["value"], "0x0");
+        assert!(root["calls"][1].get("calls").is_none());
+        assert_eq!(root.as_object().unwrap().keys().collect::<Vec<_>>(), ["type", "from", "to", "value", "gas", "gasUsed", "input", "calls"]);
+    }
+
+    #[test]
+    fn decodes_revert_reasons() {
+        let data = hex::decode(REVERT.replace(' ', "")).unwrap();
+        assert_eq!(revert_reason(&data), None);
+        let mut data = [ERROR_SELECTOR.to_vec(), [0; 64].to_vec(), b"nope".to_vec()].concat();
+        data[35] = 0x20;
+        data[67] = 4;
+        assert_eq!(revert_reason(&data), Some("nope".to_string()));
+        data[67] = 5;
+        assert_eq!(revert_reason(&data), None);
+    }
+}
@@ -0,0 +1,155 @@
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use crate::blockchain::WorldState;
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+use crate::machine::context::CallContext;
+use crate::machine::inspector::Inspector;
+use crate::machine::opcode::OpCode;
+use crate::machine::tracers::{bytes, quantity};
+
+// Writes an EIP-3155 JSON line per step, in the order the steps start like geth does, then a summary line per
+// transaction. The cost of a step is only known once it ends, so a call or a create holds back its own line and the
+// lines of its callee until it returns. Like in geth, the gas cost of a call or a create includes all the gas it
+// forwarded, used or not, and a step that fails costs its static gas
+pub struct Eip3155Tracer<W: Write> {
+    // Gas of the transaction when it starts
+    gas: usize,
+    // Lines that are not written yet, either still running or behind one that is
+    lines: VecDeque<Value>,
+    result: io::Result<()>,
+    // Gas left by the last callee that returned, which its caller got back
+    returned: usize,
+    // Line numbers and opcodes of the steps that are still running, the innermost last
+    running: Vec<(usize, OpCode)>,
+    // Number of lines written, which is the line number of the first one in `lines`
+    written: usize,
+    writer: W,
+}
+
+impl<W: Write> Eip3155Tracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { gas: 0, lines: VecDeque::new(), result: Ok(()), returned: 0, running: vec![], written: 0, writer }
+    }
+
+    // Returns the writer, or the first error it failed with, after which nothing more was written
+    pub fn finish(self) -> io::Result<W> {
+        self.result.map(|_| self.writer)
+    }
+
+    fn write_line(&mut self, line: &Value) {
+        if self.result.is_ok() {
+            self.result = writeln!(self.writer, "{}", line);
+        }
+    }
+
+    // Writes the lines up to the first step that is still running
+    fn write_ended(&mut self) {
+        while !self.lines.is_empty() && self.running.first().map(|(number, _)| *number) != Some(self.written) {
+            let line = self.lines.pop_front().unwrap();
+            self.write_line(&line);
+            self.written += 1;
+        }
+    }
+}
+
+impl<W: Write> Inspector for Eip3155Tracer<W> {
+    fn step(&mut self, s: &WorldState, cctx: &CallContext) {
+        let opcode = cctx.opcode();
+        self.returned = 0;
+        self.running.push((self.written + self.lines.len(), opcode));
+        self.lines.push_back(json!({
+            "pc": cctx.pc,
+            "op": opcode.0,
+            "gas": quantity(cctx.available_gas()),
+            "gasCost": quantity(0),
+            "memSize": cctx.memory.size(),
+            "stack": cctx.stack.items().iter().map(quantity).collect::<Vec<_>>(),
+            "depth": cctx.depth + 1,
+            "refund": s.refund,
+            "opName": opcode.to_string(),
+        }));
+    }
+
+    fn step_end(&mut self, _s: &WorldState, _cctx: &CallContext, cost: usize, result: &Result<(), Error>) {
+        let Some((number, opcode)) = self.running.pop() else { return };
+        let line = &mut self.lines[number - self.written];
+        let returned = std::mem::take(&mut self.returned);
+        match result {
+            Ok(()) => line["gasCost"] = quantity(cost + returned).into(),
+            Err(error) => {
+                line["gasCost"] = quantity(opcode.static_gas()).into();
+                line["error"] = format!("{:?}", error).into();
+            },
+        }
+        self.write_ended();
+    }
+    fn call(&mut self, _s: &WorldState, cctx: &CallContext, _opcode: OpCode, _target: Address) {
+        if cctx.depth == 0 { self.gas = cctx.contract.gas; }
+    }
+
+    fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, result: &Result<(), Error>) {
+        if cctx.depth != 0 {
+            self.returned = cctx.contract.gas;
+            return;
+        }
+
+        let mut summary = json!({
+            "output": bytes(&cctx.r#return),
+            "gasUsed": quantity(self.gas - cctx.contract.gas),
+            "pass": result.is_ok() && !cctx.revert,
+        });
+        if let Err(error) = result {
+            summary["error"] = format!("{:?}", error).into();
+        }
+        self.write_line(&summary);
+        if self.result.is_ok() {
+            self.result = self.writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::Evm;
+    use crate::machine::tracers::tests::inspect;
+
+    fn trace(contracts: &[&str]) -> (Vec<Value>, Result<(), Error>) {
+        let (tracer, result) = inspect(Evm::builder(), contracts, &[], Eip3155Tracer::new(vec![]));
+        let output = String::from_utf8(tracer.finish().unwrap()).unwrap();
+        (output.lines().map(|line| serde_json::from_str(line).unwrap()).collect(), result.map(|_| ()))
+    }
+
+    #[test]
+    fn writes_a_line_per_step_in_the_order_they_start() {
+        // STATICCALL 0xC1, which runs PUSH1 1 STOP
+        let (lines, result) = trace(&["5F5F5F5F60C15AFA00", "600100"]);
+        assert_eq!(result, Ok(()));
+
+        assert_eq!(lines[0].to_string(), r#"{"pc":0,"op":95,"gas":"0x13498","gasCost":"0x2","memSize":0,"stack":[],"depth":1,"refund":0,"opName":"PUSH0"}"#);
+        assert_eq!(lines.iter().filter_map(|line| line["opName"].as_str()).collect::<Vec<_>>(), [
+            "PUSH0", "PUSH0", "PUSH0", "PUSH0", "PUSH1", "GAS", "STATICCALL", "PUSH1", "STOP", "STOP",
+        ]);
+        assert_eq!(lines[5]["gasCost"], "0x2");
+        assert_eq!(lines[6]["stack"], json!(["0x0", "0x0", "0x0", "0x0", "0xc1", "0x1348b"]));
+        assert_eq!(lines[6]["gasCost"], "0x12fe2"); // 2600 to access 0xC1, then the 75194 forwarded to the callee
+        assert_eq!(lines[7]["depth"], 2);
+        assert_eq!(lines[7]["gas"], "0x125ba");
+        assert_eq!(lines[9]["gas"], "0x12a60");
+        assert_eq!(lines[10], json!({ "output": "0x", "gasUsed": "0x5c40", "pass": true }));
+        assert_eq!(lines.len(), 11);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let (lines, result) = trace(&["01"]); // ADD
+        assert_eq!(result, Err(Error::EmptyStack));
+        assert_eq!(lines[0]["error"], "EmptyStack");
+        assert_eq!(lines[0]["gasCost"], "0x3");
+        assert_eq!(lines[1]["pass"], false);
+        assert_eq!(lines[1]["error"], "EmptyStack");
+    }
+}
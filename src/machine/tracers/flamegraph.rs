@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::blockchain::WorldState;
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+use crate::machine::context::CallContext;
+use crate::machine::inspector::Inspector;
+use crate::machine::opcode::OpCode;
+use crate::machine::tracers::{address, bytes};
+
+struct Frame {
+    // Gas used by the calls that the frame made
+    callees: usize,
+    gas: usize,
+    stack: String,
+}
+
+// Adds up the gas used by every call stack of the executions it inspects, in the folded format of flamegraph tools.
+// A frame is the account whose code runs, followed by the selector of the call, and its weight is the gas that the
+// frame used itself, without the gas of its callees. Like in call traces, the transaction's intrinsic gas is part of
+// the root frame
+#[derive(Default)]
+pub struct FlamegraphTracer {
+    running: Vec<Frame>,
+    pub stacks: HashMap<String, usize>,
+}
+
+impl FlamegraphTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // One `frame;frame;frame gas` line per stack, sorted by stack
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<_> = self.stacks.iter().filter(|(_, gas)| **gas != 0).collect();
+        stacks.sort();
+        stacks.into_iter().fold(String::new(), |mut folded, (stack, gas)| {
+            writeln!(folded, "{} {}", stack, gas).unwrap();
+            folded
+        })
+    }
+
+    fn enter(&mut self, cctx: &CallContext, frame: String) {
+        let stack = match self.running.last() {
+            Some(parent) => format!("{};{}", parent.stack, frame),
+            None => frame,
+        };
+        self.running.push(Frame { callees: 0, gas: cctx.contract.gas, stack });
+    }
+
+    fn exit(&mut self, cctx: &CallContext) {
+        let Some(frame) = self.running.pop() else { return };
+        let gas = frame.gas.saturating_sub(cctx.contract.gas);
+        *self.stacks.entry(frame.stack).or_default() += gas.saturating_sub(frame.callees);
+        if let Some(parent) = self.running.last_mut() {
+            parent.callees += gas;
+        }
+    }
+}
+
+impl Inspector for FlamegraphTracer {
+    fn call(&mut self, _s: &WorldState, cctx: &CallContext, _opcode: OpCode, target: Address) {
+        let frame = match cctx.contract.input.get(..4) {
+            Some(selector) => format!("{}:{}", address(target), bytes(selector)),
+            None => address(target),
+        };
+        self.enter(cctx, frame);
+    }
+
+    fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, _result: &Result<(), Error>) {
+        self.exit(cctx);
+    }
+
+    fn create(&mut self, _s: &WorldState, cctx: &CallContext, opcode: OpCode) {
+        self.enter(cctx, format!("{}:{}", address(cctx.contract.address), opcode));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::Evm;
+    use crate::machine::tracers::tests::inspect;
+
+    #[test]
+    fn folds_the_gas_of_each_call_stack() {
+        // Calls 0xC1 with the selector 0xAABBCCDD, twice
+        let call = "63AABBCCDD60E01B5F52 5F5F60045F5F60C15AF150";
+        let caller = format!("{}{}00", call, call);
+        let callee = "6001600201505F00"; // PUSH1 1 PUSH1 2 ADD POP PUSH0 STOP
+        let (tracer, result) = inspect(Evm::builder(), &[&caller, callee], &[0x12, 0x34, 0x56, 0x78, 0x9A], FlamegraphTracer::new());
+        let output = result.unwrap();
+        let root = "0x00000000000000000000000000000000000000c0:0x12345678";
+        let callee = format!("{};0x00000000000000000000000000000000000000c1:0xaabbccdd", root);
+        assert_eq!(tracer.stacks[&callee], 2 * 13);
+        assert_eq!(tracer.stacks.values().sum::<usize>(), 100000 - output.remaining_gas);
+        assert_eq!(tracer.folded(), format!("{} {}\n{} {}\n", root, tracer.stacks[root], callee, 2 * 13));
+    }
+}
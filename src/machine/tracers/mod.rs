@@ -0,0 +1,52 @@
+pub mod call;
+pub mod eip3155;
+pub mod flamegraph;
+pub mod prestate;
+pub mod profiler;
+
+use std::fmt::LowerHex;
+use crate::blockchain::primitives::Address;
+
+// Quantities and byte strings are written as 0x-prefixed hexadecimal, like in the JSON of other clients
+fn quantity(value: impl LowerHex) -> String {
+    format!("{:#x}", value)
+}
+
+fn bytes(data: &[u8]) -> String {
+    format!("0x{}", hex::encode(data))
+}
+
+fn address(address: Address) -> String {
+    format!("{:#042x}", address.0)
+}
+
+// Storage keys and values are written in full
+fn word(value: impl LowerHex) -> String {
+    format!("{:#066x}", value)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ethnum::u256;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::blockchain::primitives::{Account, Address, Block, Transaction};
+    use crate::evm::EvmBuilder;
+    use crate::machine::ExecutionResult;
+    use crate::machine::inspector::Inspector;
+
+    // Inspects a transaction with `data` from 0xA0, which can pay for it, to 0xC0, after deploying the given code at
+    // 0xC0, 0xC1 and so on. Returns the inspector along with the result
+    pub(crate) fn inspect<I: Inspector + 'static>(evm: EvmBuilder, contracts: &[&str], data: &[u8], inspector: I) -> (I, ExecutionResult) {
+        let evm = contracts.iter().enumerate().fold(evm, |evm, (i, code)| {
+            let code = hex::decode(code.replace(' ', "")).unwrap();
+            evm.account(Address(u256::from(0xC0 + i as u8)), Account { balance: u256::ZERO, code: code.into(), nonce: 1 })
+        });
+        let mut evm = evm.account(Address(u256::new(0xA0)), Account { balance: u256::new(100000000), ..Default::default() }).build();
+        let inspector = Rc::new(RefCell::new(inspector));
+        let tx = Transaction { data: data.to_vec(), from: Address(u256::new(0xA0)), gas: 100000, gas_price: 1, to: Address(u256::new(0xC0)), ..Default::default() };
+
+        let result = evm.inspect(Block::default(), tx, inspector.clone());
+        (Rc::into_inner(inspector).unwrap().into_inner(), result)
+    }
+}
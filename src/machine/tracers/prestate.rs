@@ -0,0 +1,205 @@
+use ethnum::u256;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+use crate::blockchain::WorldState;
+use crate::blockchain::bytecode::Bytecode;
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+use crate::machine::context::CallContext;
+use crate::machine::inspector::Inspector;
+use crate::machine::tracers::{address, bytes, quantity, word};
+
+// Fields of an account that a trace reports, the others being left out
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct AccountState {
+    pub balance: Option<u256>,
+    pub code: Option<Bytecode>,
+    pub nonce: Option<usize>,
+    pub storage: BTreeMap<u256, u256>,
+}
+
+impl AccountState {
+    fn to_json(&self) -> Value {
+        let mut account = Map::new();
+        if let Some(balance) = self.balance { account.insert("balance".to_string(), quantity(balance).into()); }
+        if let Some(nonce) = self.nonce { account.insert("nonce".to_string(), nonce.into()); }
+        if let Some(code) = &self.code { account.insert("code".to_string(), bytes(code).into()); }
+        if !self.storage.is_empty() {
+            account.insert("storage".to_string(), self.storage.iter().map(|(key, value)| (word(*key), Value::from(word(*value)))).collect::<Map<_, _>>().into());
+        }
+        account.into()
+    }
+}
+
+pub type State = BTreeMap<Address, AccountState>;
+
+fn state_to_json(state: &State) -> Value {
+    state.iter().map(|(a, account)| (address(*a), account.to_json())).collect::<Map<_, _>>().into()
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StateTrace {
+    // Every account and slot that the transaction accessed, as they were before it
+    Prestate(State),
+    // Accounts that the transaction changed, with their changed fields before and after it.
+    // Prior values also include the balance and nonce, like geth does
+    Diff { pre: State, post: State },
+}
+
+impl StateTrace {
+    // Same shape as the output of geth's prestateTracer
+    pub fn to_json(&self) -> Value {
+        match self {
+            StateTrace::Prestate(state) => state_to_json(state),
+            StateTrace::Diff { pre, post } => json!({ "pre": state_to_json(pre), "post": state_to_json(post) }),
+        }
+    }
+}
+
+// Builds the prestate or the state diff of each transaction, from the values that the access log recorded when the
+// transaction first accessed each account and slot
+pub struct PrestateTracer {
+    diff: bool,
+    pub traces: Vec<StateTrace>,
+}
+
+impl PrestateTracer {
+    pub fn prestate() -> Self {
+        Self { diff: false, traces: vec![] }
+    }
+
+    pub fn diff() -> Self {
+        Self { diff: true, traces: vec![] }
+    }
+
+    fn trace(&self, s: &WorldState) -> StateTrace {
+        let Some(log) = &s.log else { return StateTrace::Prestate(State::new()) };
+        let log = log.lock().unwrap();
+        let mut slots = BTreeMap::<Address, BTreeMap<u256, u256>>::new();
+        for address in log.accounts.keys() { slots.entry(*address).or_default(); }
+        for ((address, key), before) in &log.slots { slots.entry(*address).or_default().insert(*key, *before); }
+
+        let (mut pre, mut post) = (State::new(), State::new());
+        for (address, keys) in slots {
+            // What is not in memory anymore got rolled back to the value it had when first accessed
+            let after = s.accounts.0.get(&address).map(|entry| entry.value.clone()).or_else(|| log.accounts.get(&address).cloned()).unwrap_or_default();
+            let before = log.accounts.get(&address).cloned().unwrap_or_else(|| after.clone());
+            let storage: BTreeMap<u256, (u256, u256)> = keys.into_iter()
+                .map(|(key, before)| (key, (before, s.storage.get(&address).and_then(|storage| storage.0.get(&key)).map_or(before, |entry| entry.value))))
+                .filter(|(_, (before, after))| !self.diff || before != after)
+                .collect();
+
+            if !self.diff {
+                pre.insert(address, AccountState {
+                    balance: Some(before.balance),
+                    code: (!before.code.is_empty()).then(|| before.code.clone()),
+                    nonce: (before.nonce != 0).then_some(before.nonce),
+                    storage: storage.iter().map(|(key, (before, _))| (*key, *before)).collect(),
+                });
+                continue;
+            }
+            if before == after && storage.is_empty() { continue; }
+
+            pre.insert(address, AccountState {
+                balance: Some(before.balance),
+                code: (!before.code.is_empty()).then(|| before.code.clone()),
+                nonce: Some(before.nonce),
+                storage: storage.iter().map(|(key, (before, _))| (*key, *before)).collect(),
+            });
+            post.insert(address, AccountState {
+                balance: (before.balance != after.balance).then_some(after.balance),
+                code: (before.code != after.code).then(|| after.code.clone()),
+                nonce: (before.nonce != after.nonce).then_some(after.nonce),
+                storage: storage.iter().map(|(key, (_, after))| (*key, *after)).collect(),
+            });
+        }
+
+        match self.diff {
+            true => StateTrace::Diff { pre, post },
+            false => StateTrace::Prestate(pre),
+        }
+    }
+}
+
+impl Inspector for PrestateTracer {
+    fn call_end(&mut self, s: &WorldState, cctx: &CallContext, _result: &Result<(), Error>) {
+        if cctx.depth == 0 { self.traces.push(self.trace(s)); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::blockchain::primitives::{Account, Block, Transaction};
+    use crate::evm::Evm;
+    use crate::machine::tracers::tests::inspect;
+
+    fn trace(tracer: PrestateTracer) -> (StateTrace, usize) {
+        // Increments slot 0, then calls 0xC1, which reads slot 7 and reverts
+        let evm = Evm::builder()
+            .storage(Address(uint!("0xC0")), uint!("0"), uint!("41"))
+            .storage(Address(uint!("0xC1")), uint!("7"), uint!("5"));
+        let (mut tracer, result) = inspect(evm, &["5F546001015F555F5F5F5F5F60C15AF15000", "6007545F5FFD"], &[], tracer);
+        let output = result.unwrap();
+        assert_eq!(tracer.traces.len(), 1);
+        (tracer.traces.remove(0), 100000 - output.remaining_gas)
+    }
+
+    #[test]
+    fn reports_the_prestate_of_every_access() {
+        let (trace, _) = trace(PrestateTracer::prestate());
+        let StateTrace::Prestate(state) = &trace else { panic!("not a prestate") };
+        assert_eq!(state.keys().copied().collect::<Vec<_>>(), [Address(uint!("0xA0")), Address(uint!("0xC0")), Address(uint!("0xC1"))]);
+        assert_eq!(state[&Address(uint!("0xA0"))], AccountState { balance: Some(uint!("100000000")), code: None, nonce: None, storage: BTreeMap::new() });
+        assert_eq!(state[&Address(uint!("0xC0"))].storage, BTreeMap::from([(uint!("0"), uint!("41"))]));
+        // Read by a call that reverted
+        assert_eq!(state[&Address(uint!("0xC1"))].storage, BTreeMap::from([(uint!("7"), uint!("5"))]));
+
+        let json = trace.to_json();
+        assert_eq!(json["0x00000000000000000000000000000000000000c1"], serde_json::json!({
+            "balance": "0x0",
+            "nonce": 1,
+            "code": "0x6007545f5ffd",
+            "storage": { format!("0x{:064x}", 7): format!("0x{:064x}", 5) },
+        }));
+    }
+
+    #[test]
+    fn reports_the_changes() {
+        let (trace, gas_used) = trace(PrestateTracer::diff());
+        let StateTrace::Diff { pre, post } = &trace else { panic!("not a diff") };
+        assert_eq!(pre.keys().collect::<Vec<_>>(), post.keys().collect::<Vec<_>>());
+        assert_eq!(pre[&Address(uint!("0xA0"))], AccountState { balance: Some(uint!("100000000")), code: None, nonce: Some(0), storage: BTreeMap::new() });
+        assert_eq!(post[&Address(uint!("0xA0"))], AccountState { balance: Some(uint!("100000000") - gas_used as u128), code: None, nonce: Some(1), storage: BTreeMap::new() });
+        assert_eq!(pre[&Address(uint!("0xC0"))].storage, BTreeMap::from([(uint!("0"), uint!("41"))]));
+        assert_eq!(post[&Address(uint!("0xC0"))], AccountState { balance: None, code: None, nonce: None, storage: BTreeMap::from([(uint!("0"), uint!("42"))]) });
+        assert!(!pre.contains_key(&Address(uint!("0xC1"))));
+
+        let json = trace.to_json();
+        assert_eq!(json["post"]["0x00000000000000000000000000000000000000c0"], serde_json::json!({
+            "storage": { format!("0x{:064x}", 0): format!("0x{:064x}", 42) },
+        }));
+    }
+
+    #[test]
+    fn starts_from_the_changes_of_uncommitted_transactions() {
+        let mut evm = Evm::builder()
+            .account(Address(uint!("0xA0")), Account { balance: uint!("100000000"), ..Default::default() })
+            .account(Address(uint!("0xC0")), Account { balance: uint!("0"), code: hex::decode("5F546001015F5500").unwrap().into(), nonce: 1 }) // increments slot 0
+            .storage(Address(uint!("0xC0")), uint!("0"), uint!("41"))
+            .build();
+        let tx = Transaction { from: Address(uint!("0xA0")), gas: 100000, gas_price: 1, to: Address(uint!("0xC0")), ..Default::default() };
+        evm.run(Block::default(), tx.clone()).unwrap();
+
+        let tracer = Rc::new(RefCell::new(PrestateTracer::diff()));
+        evm.inspect(Block::default(), Transaction { nonce: 1, ..tx }, tracer.clone()).unwrap();
+        let StateTrace::Diff { pre, post } = &tracer.borrow().traces[0] else { panic!("not a diff") };
+        assert_eq!(pre[&Address(uint!("0xA0"))].nonce, Some(1));
+        assert_eq!(pre[&Address(uint!("0xC0"))].storage, BTreeMap::from([(uint!("0"), uint!("42"))]));
+        assert_eq!(post[&Address(uint!("0xC0"))].storage, BTreeMap::from([(uint!("0"), uint!("43"))]));
+    }
+}
@@ -0,0 +1,161 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::blockchain::WorldState;
+use crate::blockchain::errors::Error;
+use crate::blockchain::primitives::Address;
+use crate::machine::context::CallContext;
+use crate::machine::inspector::Inspector;
+use crate::machine::opcode::OpCode;
+use crate::machine::tracers::address;
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct GasStats {
+    pub count: usize,
+    pub gas: usize,
+}
+
+impl GasStats {
+    fn add(&mut self, gas: usize) {
+        self.count += 1;
+        self.gas += gas;
+    }
+}
+
+struct Step {
+    // Gas used by the calls that the step made
+    callees: usize,
+    gas: usize,
+    opcode: OpCode,
+    pc: usize,
+}
+
+// Adds up the count and gas of the steps of every execution it inspects, per opcode and per pc of each contract.
+// The gas of a step is its own cost, without the gas its callees used, and intrinsic gas is not counted
+#[derive(Default)]
+pub struct GasProfiler {
+    // Accounts whose code runs in each running call, the innermost last, and the gas of the call when it started
+    calls: Vec<(Address, usize)>,
+    pub opcodes: HashMap<OpCode, GasStats>,
+    pub pcs: HashMap<(Address, usize), (OpCode, GasStats)>,
+    running: Vec<Step>,
+}
+
+impl GasProfiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Most expensive first
+    pub fn sorted_opcodes(&self) -> Vec<(OpCode, GasStats)> {
+        let mut opcodes: Vec<_> = self.opcodes.iter().map(|(opcode, stats)| (*opcode, *stats)).collect();
+        opcodes.sort_by(|(a, a_stats), (b, b_stats)| b_stats.gas.cmp(&a_stats.gas).then(b_stats.count.cmp(&a_stats.count)).then(a.cmp(b)));
+        opcodes
+    }
+
+    pub fn sorted_pcs(&self) -> Vec<(Address, usize, OpCode, GasStats)> {
+        let mut pcs: Vec<_> = self.pcs.iter().map(|((address, pc), (opcode, stats))| (*address, *pc, *opcode, *stats)).collect();
+        pcs.sort_by(|a, b| b.3.gas.cmp(&a.3.gas).then(b.3.count.cmp(&a.3.count)).then((a.0, a.1).cmp(&(b.0, b.1))));
+        pcs
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "opcodes": self.sorted_opcodes().into_iter().map(|(opcode, stats)| json!({
+                "op": opcode.to_string(),
+                "count": stats.count,
+                "gas": stats.gas,
+            })).collect::<Vec<_>>(),
+            "pcs": self.sorted_pcs().into_iter().map(|(contract, pc, opcode, stats)| json!({
+                "address": address(contract),
+                "pc": pc,
+                "op": opcode.to_string(),
+                "count": stats.count,
+                "gas": stats.gas,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn table(&self) -> String {
+        let mut table = String::new();
+        writeln!(table, "{:<16} {:>10} {:>12}", "OPCODE", "COUNT", "GAS").unwrap();
+        for (opcode, stats) in self.sorted_opcodes() {
+            writeln!(table, "{:<16} {:>10} {:>12}", opcode.to_string(), stats.count, stats.gas).unwrap();
+        }
+        writeln!(table).unwrap();
+        writeln!(table, "{:<42} {:>6} {:<16} {:>10} {:>12}", "ADDRESS", "PC", "OPCODE", "COUNT", "GAS").unwrap();
+        for (contract, pc, opcode, stats) in self.sorted_pcs() {
+            writeln!(table, "{:<42} {:>6} {:<16} {:>10} {:>12}", address(contract), pc, opcode.to_string(), stats.count, stats.gas).unwrap();
+        }
+        table
+    }
+}
+
+impl Inspector for GasProfiler {
+    fn step(&mut self, _s: &WorldState, cctx: &CallContext) {
+        self.running.push(Step { callees: 0, gas: cctx.available_gas(), opcode: cctx.opcode(), pc: cctx.pc });
+    }
+
+    fn step_end(&mut self, _s: &WorldState, cctx: &CallContext, _cost: usize, _result: &Result<(), Error>) {
+        let (Some(step), Some((contract, _))) = (self.running.pop(), self.calls.last()) else { return };
+        let gas = step.gas.saturating_sub(cctx.available_gas()).saturating_sub(step.callees);
+        self.opcodes.entry(step.opcode).or_default().add(gas);
+        self.pcs.entry((*contract, step.pc)).or_insert((step.opcode, GasStats::default())).1.add(gas);
+    }
+
+    fn call(&mut self, _s: &WorldState, cctx: &CallContext, _opcode: OpCode, target: Address) {
+        self.calls.push((target, cctx.available_gas()));
+    }
+
+    fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, _result: &Result<(), Error>) {
+        let Some((_, gas)) = self.calls.pop() else { return };
+        if let Some(step) = self.running.last_mut() {
+            step.callees += gas.saturating_sub(cctx.contract.gas);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethnum::uint;
+    use super::*;
+    use crate::evm::Evm;
+    use crate::machine::tracers::tests::inspect;
+
+    // Each run is a transaction on a fresh state, where 0xC0 does a STATICCALL to 0xC1, which runs PUSH1 1 PUSH1 2 ADD POP PUSH0 STOP
+    fn profile(runs: usize) -> GasProfiler {
+        (0..runs).fold(GasProfiler::new(), |profiler, _| {
+            let (profiler, result) = inspect(Evm::builder(), &["5F5F5F5F60C15AFA00", "6001600201505F00"], &[], profiler);
+            result.unwrap();
+            profiler
+        })
+    }
+
+    #[test]
+    fn adds_up_the_gas_of_each_opcode_and_pc() {
+        let profiler = profile(2);
+        assert_eq!(profiler.opcodes[&OpCode(0x5F)], GasStats { count: 10, gas: 20 });
+        assert_eq!(profiler.opcodes[&OpCode(0x01)], GasStats { count: 2, gas: 6 });
+        // Without the gas of the callee, and 0xC1 is cold in every transaction
+        assert_eq!(profiler.opcodes[&OpCode(0xFA)], GasStats { count: 2, gas: 5200 });
+        assert_eq!(profiler.pcs[&(Address(uint!("0xC1")), 4)], (OpCode(0x01), GasStats { count: 2, gas: 6 }));
+        assert_eq!(profiler.pcs[&(Address(uint!("0xC0")), 7)], (OpCode(0xFA), GasStats { count: 2, gas: 5200 }));
+        assert_eq!(profiler.pcs.len(), 8 + 6);
+    }
+
+    #[test]
+    fn sorts_the_report() {
+        let profiler = profile(1);
+        let json = profiler.to_json();
+        assert_eq!(json["opcodes"][0], json!({ "op": "STATICCALL", "count": 1, "gas": 2600 }));
+        assert_eq!(json["opcodes"][1], json!({ "op": "PUSH0", "count": 5, "gas": 10 }));
+        assert_eq!(json["pcs"][0], json!({ "address": "0x00000000000000000000000000000000000000c0", "pc": 7, "op": "STATICCALL", "count": 1, "gas": 2600 }));
+
+        let table = profiler.table();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), ["OPCODE", "COUNT", "GAS"]);
+        assert_eq!(lines[1].split_whitespace().collect::<Vec<_>>(), ["STATICCALL", "1", "2600"]);
+        assert_eq!(lines.iter().filter(|line| line.contains("PUSH0")).count(), 1 + 5);
+    }
+}
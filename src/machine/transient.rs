@@ -1,20 +1,40 @@
 use ethnum::{u256, U256};
 use std::collections::HashMap;
 
-#[derive(Default, Debug)]
-pub struct Transient(pub HashMap<u256, u256>);
+#[derive(Default, Debug, Clone)]
+pub struct Transient {
+    // Every store of the transaction, with the value it replaced
+    journal: Vec<(u256, Option<u256>)>,
+    pub slots: HashMap<u256, u256>,
+}
 
 impl Transient {
     pub fn new() -> Self {
-        Self(Default::default())
+        Self::default()
     }
 
     pub fn store(&mut self, key: u256, value: u256) -> Option<u256> {
-        self.0.insert(key, value)
+        let previous = self.slots.insert(key, value);
+        self.journal.push((key, previous));
+        previous
     }
 
     pub fn load(&mut self, key: u256) -> u256 {
-        *(self.0.get(&key).unwrap_or(&U256::ZERO))
+        *(self.slots.get(&key).unwrap_or(&U256::ZERO))
+    }
+
+    pub fn checkpoint(&self) -> usize {
+        self.journal.len()
+    }
+
+    // Undoes the stores made since the checkpoint
+    pub fn revert(&mut self, checkpoint: usize) {
+        for (key, previous) in self.journal.drain(checkpoint..).rev() {
+            match previous {
+                Some(value) => self.slots.insert(key, value),
+                None => self.slots.remove(&key),
+            };
+        }
     }
 }
 
@@ -30,14 +50,27 @@ mod tests {
 
         transient.store(uint!("42"), uint!("0x0000000004050607000000000000000000000000000000000000000000000000"));
 
-        assert_eq!(transient.0.get(&uint!("42")), Some(&uint!("0x0000000004050607000000000000000000000000000000000000000000000000")));
+        assert_eq!(transient.slots.get(&uint!("42")), Some(&uint!("0x0000000004050607000000000000000000000000000000000000000000000000")));
+    }
+
+    #[test]
+    fn reverts_to_a_checkpoint() {
+        let mut transient = Transient::new();
+        transient.store(uint!("1"), uint!("1"));
+
+        let checkpoint = transient.checkpoint();
+        transient.store(uint!("1"), uint!("2"));
+        transient.store(uint!("2"), uint!("3"));
+        transient.revert(checkpoint);
+
+        assert_eq!(transient.slots, HashMap::from([(uint!("1"), uint!("1"))]));
     }
 
     #[test]
     fn load() {
         let mut transient = Transient::new();
 
-        transient.0.insert(uint!("42"), uint!("0x0000000004050607000000000000000000000000000000000000000000000000"));
+        transient.slots.insert(uint!("42"), uint!("0x0000000004050607000000000000000000000000000000000000000000000000"));
 
         assert_eq!(transient.load(uint!("42")), uint!("0x0000000004050607000000000000000000000000000000000000000000000000"));
         assert_eq!(transient.load(uint!("43")), uint!("0"));
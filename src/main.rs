@@ -1,8 +1,38 @@
-mod blockchain;
-mod evm;
-mod machine;
-mod utils;
+use std::cell::RefCell;
+use std::process::ExitCode;
+use std::rc::Rc;
 
-fn main() {
-    println!("Hello, world!");
+use rusty_evm::{u256, Account, Address, Block, Debugger, Evm, Transaction};
+
+const USAGE: &str = "usage: rusty-evm debug <code> [calldata]";
+
+// Steps through some code in the terminal, called with the calldata by an account that can pay for it
+fn debug(code: &str, calldata: &str) -> Result<(), String> {
+    let decode = |data: &str| hex::decode(data.trim_start_matches("0x")).map_err(|error| error.to_string());
+    let (caller, contract) = (Address(u256::new(0xCA11E7)), Address(u256::new(0xC0)));
+    let mut evm = Evm::builder()
+        .account(caller, Account { balance: u256::MAX, ..Default::default() })
+        .account(contract, Account { balance: u256::ZERO, code: decode(code)?.into(), nonce: 1 })
+        .build();
+    let tx = Transaction { data: decode(calldata)?, from: caller, gas: 30_000_000, gas_price: 1, to: contract, ..Default::default() };
+
+    let output = evm.inspect(Block::default(), tx, Rc::new(RefCell::new(Debugger::terminal()))).map_err(|error| format!("{:?}", error))?;
+    println!("{} with 0x{}, {} gas left", if output.revert { "reverted" } else { "returned" }, hex::encode(&output.data), output.remaining_gas);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["debug", code] => debug(code, ""),
+        ["debug", code, calldata] => debug(code, calldata),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        },
+    }
 }
@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use rusty_evm::*;
+
+// Implements every hook with what the crate exports, looking into the types they hand over
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+    steps: usize,
+    step_ends: usize,
+}
+
+impl Inspector for Recorder {
+    fn step(&mut self, s: &WorldState, cctx: &CallContext) {
+        let (stack, memory, transient): (&Stack, &Memory, &Transient) = (&cctx.stack, &cctx.memory, &cctx.transient);
+        assert!(cctx.contract.eof.as_ref().is_none_or(|container: &Container| !container.types.is_empty()) && cctx.halt.is_none());
+        assert!(s.log.as_ref().is_none_or(|log| { let log: &AccessLog = &log.lock().unwrap(); log.accounts.len() <= 2 }));
+        assert!(stack.len() <= STACK_LIMIT && memory.data().len() % 32 == 0 && transient.slots.len() <= 1);
+        self.steps += 1;
+    }
+
+    fn step_end(&mut self, _s: &WorldState, _cctx: &CallContext, _cost: usize, _result: &Result<(), Error>) {
+        self.step_ends += 1;
+    }
+
+    fn call(&mut self, _s: &WorldState, cctx: &CallContext, opcode: OpCode, target: Address) {
+        self.events.push(format!("{} {:?} {}", opcode, target, cctx.depth));
+    }
+
+    fn call_end(&mut self, _s: &WorldState, cctx: &CallContext, result: &Result<(), Error>) {
+        self.events.push(format!("end {:?} {:?}", cctx.contract.address, result));
+    }
+
+    fn create(&mut self, _s: &WorldState, cctx: &CallContext, opcode: OpCode) {
+        self.events.push(format!("{} {}", opcode, cctx.depth));
+    }
+
+    fn create_end(&mut self, _s: &WorldState, cctx: &CallContext, result: &Result<(), Error>) {
+        self.events.push(format!("create end {} {:?}", cctx.depth, result));
+    }
+
+    fn log(&mut self, _cctx: &CallContext, log: &Log) {
+        self.events.push(format!("log {:?} {:?}", log.data, log.topics));
+    }
+
+    fn sstore(&mut self, _cctx: &CallContext, key: u256, value: u256, previous: &StorageValue<u256>) {
+        self.events.push(format!("sstore {} {} {} {}", key, value, previous.original_value, previous.warm));
+    }
+
+    fn selfdestruct(&mut self, _cctx: &CallContext, beneficiary: Address, balance: u256) {
+        self.events.push(format!("selfdestruct {:?} {}", beneficiary, balance));
+    }
+}
+
+#[test]
+fn inspects_through_the_public_api() {
+    // PUSH1 42 PUSH0 SSTORE PUSH0 PUSH0 LOG0 PUSH1 0xA0 SELFDESTRUCT
+    let code = vec![0x60, 0x2A, 0x5F, 0x55, 0x5F, 0x5F, 0xA0, 0x60, 0xA0, 0xFF];
+    let (caller, contract) = (Address(u256::new(0xA0)), Address(u256::new(0xC0)));
+    let mut evm = Evm::builder()
+        .account(caller, Account { balance: u256::new(100000000), ..Default::default() })
+        .account(contract, Account { balance: u256::new(7), code: code.into(), nonce: 1 })
+        .build();
+    let recorder = Rc::new(RefCell::new(Recorder::default()));
+
+    let tx = Transaction { from: caller, gas: 100000, gas_price: 1, to: contract, ..Default::default() };
+    assert!(matches!(evm.inspect(Block::default(), tx, recorder.clone()), Ok(ExecutionOutput { revert: false, .. })));
+    // Creates a contract whose initcode is STOP
+    let tx = Transaction { data: vec![0x00], from: caller, gas: 100000, gas_price: 1, nonce: 1, ..Default::default() };
+    assert!(matches!(evm.inspect(Block::default(), tx, recorder.clone()), Ok(ExecutionOutput { revert: false, .. })));
+
+    let recorder = recorder.borrow();
+    assert_eq!(recorder.events, [
+        "CALL Address(0xC0) 0",
+        "sstore 0 42 0 false",
+        "log [] [None, None, None, None]",
+        "selfdestruct Address(0xA0) 7",
+        "end Address(0xC0) Ok(())",
+        "CREATE 0",
+        "create end 0 Ok(())",
+    ]);
+    assert_eq!((recorder.steps, recorder.step_ends), (9, 9));
+}